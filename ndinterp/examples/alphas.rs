@@ -46,8 +46,8 @@ fn main() {
 
     let cubic_interpolator = Cubic { grid };
 
-    let example_q: Vec<f64> = vec![1.7, 1.8, 2.6, 3.4, 4.1, 4.5, 10.0, 1.0];
-    let lhapdf_res = vec![
+    let example_q: [f64; 8] = [1.7, 1.8, 2.6, 3.4, 4.1, 4.5, 10.0, 1.0];
+    let lhapdf_res = [
         0.32580476, 0.31652747, 0.26841305, 0.24201896, 0.22660515, 0.21978229, 0.0, 1.0,
     ];
     for (i, qval) in example_q.iter().enumerate() {
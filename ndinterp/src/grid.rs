@@ -11,7 +11,11 @@
 //!
 use crate::interpolate::InterpolationError;
 use itertools::izip;
-use ndarray::{Array, ArrayView1, Axis, Dimension, Ix1, Ix2};
+use ndarray::{
+    ArrayBase, ArrayView1, ArrayView2, Axis, CowRepr, Data, Dimension, Ix1, Ix2, Ix3, Ix4, Ix5,
+    Ix6, OwnedRepr, RawData, ViewRepr,
+};
+use std::fmt;
 
 /// Together with the trait [`ToDimension`] this struct allows to convert a `usize` into a
 /// `Dimension` from the `ndarray` crate.
@@ -31,22 +35,98 @@ impl ToDimension for DimensionHelper<2> {
     type Dim = Ix2;
 }
 
+impl ToDimension for DimensionHelper<3> {
+    type Dim = Ix3;
+}
+
+impl ToDimension for DimensionHelper<4> {
+    type Dim = Ix4;
+}
+
+impl ToDimension for DimensionHelper<5> {
+    type Dim = Ix5;
+}
+
+impl ToDimension for DimensionHelper<6> {
+    type Dim = Ix6;
+}
+
 // Make public the families of interpolation algorithms implemented for grids
 pub mod cubic;
+pub mod ensemble;
+pub mod extrapolation;
+pub mod linear;
+pub mod regrid;
+
+#[cfg(feature = "serde")]
+pub(crate) mod serialization;
 
 /// A grid is made of two components:
 ///     A d-dimensional vector of 1-dimensional sorted vectors for the input points
 ///     A d-dimensional array for the grid values of
-#[derive(Debug)]
-pub struct Grid<const D: usize>
+///
+/// Like `ndarray::ArrayBase`, the grid is generic over the storage of the values: most of the
+/// time the values are owned by the grid, see [`Grid`], but they can also be borrowed, see
+/// [`GridView`], or either of the two, see [`CowGrid`].
+///
+/// With the `serde` feature grids, and ensembles of grids, can be serialized in a versioned
+/// layout, which is guaranteed to stay readable by future versions of the library.
+pub struct GridBase<S, const D: usize>
 where
+    S: RawData<Elem = f64>,
     DimensionHelper<D>: ToDimension,
 {
     /// Arrays with the input vectors (x_i)
     pub xgrid: Vec<Vec<f64>>,
 
     /// Output points
-    pub values: Array<f64, <DimensionHelper<D> as ToDimension>::Dim>,
+    pub values: ArrayBase<S, <DimensionHelper<D> as ToDimension>::Dim>,
+}
+
+/// A grid owning its values
+pub type Grid<const D: usize> = GridBase<OwnedRepr<f64>, D>;
+
+/// A grid borrowing its values
+pub type GridView<'a, const D: usize> = GridBase<ViewRepr<&'a f64>, D>;
+
+/// A grid either owning or borrowing its values, decided at runtime like [`ndarray::CowArray`]
+pub type CowGrid<'a, const D: usize> = GridBase<CowRepr<'a, f64>, D>;
+
+impl<const D: usize> From<Grid<D>> for CowGrid<'_, D>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    fn from(grid: Grid<D>) -> Self {
+        GridBase {
+            xgrid: grid.xgrid,
+            values: grid.values.into(),
+        }
+    }
+}
+
+impl<'a, const D: usize> From<GridView<'a, D>> for CowGrid<'a, D>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    fn from(grid: GridView<'a, D>) -> Self {
+        GridBase {
+            xgrid: grid.xgrid,
+            values: grid.values.into(),
+        }
+    }
+}
+
+impl<S, const D: usize> fmt::Debug for GridBase<S, D>
+where
+    S: Data<Elem = f64>,
+    DimensionHelper<D>: ToDimension,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GridBase")
+            .field("xgrid", &self.xgrid)
+            .field("values", &self.values)
+            .finish()
+    }
 }
 
 /// A grid slice is always 1-Dimensional
@@ -59,14 +139,14 @@ pub(crate) struct GridSlice<'a> {
     pub y: ArrayView1<'a, f64>,
 }
 
-pub(crate) trait Derivatives<'a> {
+pub(crate) trait Derivatives {
     /// Numerical derivative at index i with respect to the previous know
-    fn derivative_at(&'a self, index: usize) -> f64;
+    fn derivative_at(&self, index: usize) -> f64;
     /// Numerical derivative at index i averaged above and below
-    fn central_derivative_at(&'a self, index: usize) -> f64;
+    fn central_derivative_at(&self, index: usize) -> f64;
 }
 
-impl<'a> GridSlice<'a> {
+impl Derivatives for GridSlice<'_> {
     // TODO: at the moment we are using here the derivatives that LHAPDF is using for the
     // interpolation in alpha_s, these are probably enough for this use case but not in general
     // - [ ] Implement a more robust form of the derivative
@@ -77,7 +157,7 @@ impl<'a> GridSlice<'a> {
     /// input at position index as the ratio between the differences dy/dx computed as:
     ///     dy = y_{i} - y_{i-1}
     ///     dx = x_{i} - x_{x-1}
-    fn derivative_at(&'a self, index: usize) -> f64 {
+    fn derivative_at(&self, index: usize) -> f64 {
         let dx = self.x[index] - self.x[index - 1];
         let dy = self.y[index] - self.y[index - 1];
         dy / dx
@@ -88,16 +168,16 @@ impl<'a> GridSlice<'a> {
     ///
     /// Dx_{i} = \Delta x_{i} = x_{i} - x_{i-}
     /// y'_{i} = 1/2 * ( (y_{i+1}-y_{i})/Dx_{i+1} + (y_{i}-y_{i-1})/Dx_{i} )
-    fn central_derivative_at(&'a self, index: usize) -> f64 {
+    fn central_derivative_at(&self, index: usize) -> f64 {
         let dy_f = self.derivative_at(index + 1);
         let dy_b = self.derivative_at(index);
         0.5 * (dy_f + dy_b)
     }
 }
 
-impl Grid<1> {
+impl<S: Data<Elem = f64>> GridBase<S, 1> {
     /// Returns the 1d grid as a GridSlice object
-    pub(crate) fn grid1d_to_slice1d(&self) -> GridSlice {
+    pub(crate) fn grid1d_to_slice1d(&self) -> GridSlice<'_> {
         GridSlice {
             x: &self.xgrid[0],
             y: self.values.view(),
@@ -105,37 +185,136 @@ impl Grid<1> {
     }
 }
 
-impl Grid<2> {
-    /// Slice the grid along the given axis at position idx
-    pub(crate) fn grid2d_to_slice1d(&self, axis: usize, idx: usize) -> GridSlice {
+impl<'a> GridSlice<'a> {
+    /// Slice the 2d values, with input vectors `xgrid`, along the given axis at position idx
+    pub(crate) fn from_2d(
+        xgrid: &'a [Vec<f64>],
+        values: ArrayView2<'a, f64>,
+        axis: usize,
+        idx: usize,
+    ) -> Self {
         let axout = (axis + 1) % 2;
         GridSlice {
-            x: &self.xgrid[axis],
-            y: self.values.index_axis(Axis(axout), idx),
+            x: &xgrid[axis],
+            y: values.index_axis_move(Axis(axout), idx),
+        }
+    }
+}
+
+/// Checks that the input vectors `xgrid` are consistent with values of the given `shape`, see
+/// [`GridBase::new`]
+pub(crate) fn check_xgrid(xgrid: &[Vec<f64>], shape: &[usize]) -> Result<(), InterpolationError> {
+    if xgrid.len() != shape.len() {
+        return Err(InterpolationError::InvalidGrid(format!(
+            "{} input vectors given for a {}-dimensional grid",
+            xgrid.len(),
+            shape.len()
+        )));
+    }
+
+    for (i, (igrid, &size)) in xgrid.iter().zip(shape).enumerate() {
+        if igrid.len() < 2 {
+            return Err(InterpolationError::InvalidGrid(format!(
+                "input vector {i} has less than two points"
+            )));
+        }
+        if !igrid.windows(2).all(|w| w[0] < w[1]) {
+            return Err(InterpolationError::InvalidGrid(format!(
+                "input vector {i} is not sorted in strictly increasing order"
+            )));
+        }
+        if igrid.len() != size {
+            return Err(InterpolationError::InvalidGrid(format!(
+                "input vector {i} has {} points but the values have {size}",
+                igrid.len()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// See [`GridBase::closest_below`]
+pub(crate) fn closest_below<const D: usize>(
+    xgrid: &[Vec<f64>],
+    input_query: &[f64],
+) -> Result<[usize; D], InterpolationError> {
+    let mut ret = [0; D];
+
+    for (r, &query, igrid) in izip!(&mut ret, input_query, xgrid) {
+        if query > *igrid.last().unwrap() {
+            return Err(InterpolationError::ExtrapolationAbove(query));
+        } else if query < igrid[0] {
+            return Err(InterpolationError::ExtrapolationBelow(query));
+        }
+
+        // a query sitting exactly on the first node belongs to the first interval
+        let u_idx = igrid.partition_point(|x| x < &query);
+        *r = u_idx.max(1) - 1;
+    }
+    Ok(ret)
+}
+
+/// Orders the integration bounds `lower` and `upper` along each axis, checking that they lie
+/// within `xgrid`, and returns them together with the sign of the integral
+pub(crate) fn sort_bounds<const D: usize>(
+    xgrid: &[Vec<f64>],
+    lower: &[f64],
+    upper: &[f64],
+) -> Result<([f64; D], [f64; D], f64), InterpolationError> {
+    closest_below::<D>(xgrid, lower)?;
+    closest_below::<D>(xgrid, upper)?;
+
+    let mut sorted_lower = [0.; D];
+    let mut sorted_upper = [0.; D];
+    let mut sign = 1.;
+    for (l, u, &a, &b) in izip!(&mut sorted_lower, &mut sorted_upper, lower, upper) {
+        if a > b {
+            sign = -sign;
         }
+        (*l, *u) = (a.min(b), a.max(b));
     }
+
+    Ok((sorted_lower, sorted_upper, sign))
 }
 
-impl<const D: usize> Grid<D>
+impl<S, const D: usize> GridBase<S, D>
 where
+    S: Data<Elem = f64>,
     DimensionHelper<D>: ToDimension,
 {
+    /// Creates a grid checking that the input is consistent: there must be one input vector per
+    /// dimension, each of them sorted in strictly increasing order and with at least two points,
+    /// and its length must match the size of `values` along the corresponding axis
+    pub fn new(
+        xgrid: Vec<Vec<f64>>,
+        values: ArrayBase<S, <DimensionHelper<D> as ToDimension>::Dim>,
+    ) -> Result<Self, InterpolationError> {
+        check_xgrid(&xgrid, values.shape())?;
+
+        Ok(Self { xgrid, values })
+    }
+
     /// Find the index of the last value in the input xgrid such that xgrid(idx) < query
     /// If the query is outside the grid returns an extrapolation error
     pub fn closest_below(&self, input_query: &[f64]) -> Result<[usize; D], InterpolationError> {
-        let mut ret = [0; D];
+        closest_below(&self.xgrid, input_query)
+    }
 
-        for (r, &query, igrid) in izip!(&mut ret, input_query, &self.xgrid) {
-            if query > *igrid.last().unwrap() {
-                return Err(InterpolationError::ExtrapolationAbove(query));
-            } else if query < igrid[0] {
-                return Err(InterpolationError::ExtrapolationBelow(query));
-            }
+    /// Returns a grid borrowing the values of this one
+    pub fn view(&self) -> GridView<'_, D> {
+        GridBase {
+            xgrid: self.xgrid.clone(),
+            values: self.values.view(),
+        }
+    }
 
-            let u_idx = igrid.partition_point(|x| x < &query);
-            *r = u_idx - 1;
+    /// Returns a grid owning a copy of the values of this one
+    pub fn to_owned(&self) -> Grid<D> {
+        GridBase {
+            xgrid: self.xgrid.clone(),
+            values: self.values.to_owned(),
         }
-        Ok(ret)
     }
 }
 
@@ -170,5 +349,37 @@ mod tests {
         let grid = gen_grid();
         assert_eq!(grid.closest_below(&[0.5]).unwrap()[0], 0);
         assert_eq!(grid.closest_below(&[3.2]).unwrap()[0], 3);
+        assert_eq!(grid.closest_below(&[0.]).unwrap()[0], 0);
+        assert_eq!(grid.closest_below(&[4.]).unwrap()[0], 3);
+    }
+
+    #[test]
+    fn check_new() {
+        let grid = gen_grid();
+        assert!(Grid::<1>::new(grid.xgrid, grid.values).is_ok());
+
+        let values = array![[1., 2.], [3., 4.], [5., 6.]];
+        assert!(Grid::<2>::new(vec![vec![0., 1., 2.], vec![0., 1.]], values.clone()).is_ok());
+        // wrong number of input vectors
+        assert!(Grid::<2>::new(vec![vec![0., 1., 2.]], values.clone()).is_err());
+        // transposed input vectors
+        assert!(Grid::<2>::new(vec![vec![0., 1.], vec![0., 1., 2.]], values.clone()).is_err());
+        // unsorted input vector
+        assert!(Grid::<2>::new(vec![vec![0., 2., 1.], vec![0., 1.]], values).is_err());
+        // too few points
+        assert!(Grid::<1>::new(vec![vec![0.]], array![1.]).is_err());
+    }
+
+    #[test]
+    fn check_cow_grid() {
+        let grid = gen_grid();
+        let borrowed = CowGrid::from(grid.view());
+        assert!(borrowed.values.is_view());
+        assert_eq!(borrowed.values.as_ptr(), grid.values.as_ptr());
+        assert_eq!(borrowed.closest_below(&[2.5]).unwrap(), [2]);
+
+        let owned = CowGrid::from(grid.to_owned());
+        assert!(owned.values.is_owned());
+        assert_eq!(owned.values, grid.values);
     }
 }
@@ -3,19 +3,44 @@
 //! These are the algorithms used by the LHAPDF library for `alpha_s` and pdf(x, q)
 //!
 
-use crate::grid::{DimensionHelper, Grid, GridSlice, ToDimension};
-use crate::interpolate::InterpolationError;
+use crate::grid::{
+    closest_below, sort_bounds, Derivatives, DimensionHelper, GridBase, GridSlice, ToDimension,
+};
 pub use crate::interpolate::Interpolator;
-use itertools::izip;
+use crate::interpolate::{Differentiate, Integrate, InterpolationError};
+use ndarray::{ArrayView2, Data, OwnedRepr, RawData};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::slice;
 
 /// Cubic interpolation
-#[derive(Debug)]
-pub struct Cubic<const D: usize>
+///
+/// By default the interpolator owns the grid, but it can also work on a grid borrowing its values
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "GridBase<S, D>: serde::Serialize",
+        deserialize = "GridBase<S, D>: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Cubic<const D: usize, S = OwnedRepr<f64>>
 where
+    S: RawData<Elem = f64>,
     DimensionHelper<D>: ToDimension,
 {
     /// The grid object contains all necessary information to perform the interpolation
-    pub grid: Grid<D>,
+    pub grid: GridBase<S, D>,
+}
+
+impl<S, const D: usize> fmt::Debug for Cubic<D, S>
+where
+    S: Data<Elem = f64>,
+    DimensionHelper<D>: ToDimension,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cubic").field("grid", &self.grid).finish()
+    }
 }
 
 ///
@@ -38,14 +63,39 @@ fn cubic_interpolation_1d(t: f64, yl: f64, yu: f64, dydxl: f64, dydxu: f64) -> f
     p0 + p1 + m0 + m1
 }
 
-impl<'a> GridSlice<'a> {
-    /// Implements utilities for a GridSlice that can be used by cubic interpolation Nd
-    /// Takes as input the value being queried and its index within the given slice
+/// Derivative with respect to t of [`cubic_interpolation_1d`]
+fn cubic_derivative_1d(t: f64, yl: f64, yu: f64, dydxl: f64, dydxu: f64) -> f64 {
+    let t2 = t * t;
 
-    /// Perform 1d cubic interpolation such that f(x) = y
-    fn cubic_interpolate_1d(&'a self, query: f64, idx: usize) -> f64 {
-        // grid slice utilities are expected to be called multipled times for the same
-        // query and so it is convient to pass idx from the outside to avoid expensive searches
+    let p0 = yl * (6. * t2 - 6. * t);
+    let p1 = yu * (-6. * t2 + 6. * t);
+    let m0 = dydxl * (3. * t2 - 4. * t + 1.);
+    let m1 = dydxu * (3. * t2 - 2. * t);
+
+    p0 + p1 + m0 + m1
+}
+
+/// Primitive with respect to t of [`cubic_interpolation_1d`], vanishing at t = 0
+fn cubic_primitive_1d(t: f64, yl: f64, yu: f64, dydxl: f64, dydxu: f64) -> f64 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let t4 = t3 * t;
+
+    let p0 = yl * (0.5 * t4 - t3 + t);
+    let p1 = yu * (-0.5 * t4 + t3);
+    let m0 = dydxl * (0.25 * t4 - 2. / 3. * t3 + 0.5 * t2);
+    let m1 = dydxu * (0.25 * t4 - t3 / 3.);
+
+    p0 + p1 + m0 + m1
+}
+
+impl GridSlice<'_> {
+    // Implements utilities for a GridSlice that can be used by cubic interpolation Nd
+    // Takes as input the value being queried and its index within the given slice
+
+    /// Values and derivatives (times the width of the interval) at the lower and upper edges of
+    /// the interval `idx`, which define the cubic polynomial within it
+    fn hermite_coefficients(&self, idx: usize) -> [f64; 4] {
         let dx = self.x[idx + 1] - self.x[idx];
 
         // Upper and lower bounds and derivatives
@@ -64,13 +114,55 @@ impl<'a> GridSlice<'a> {
             dx * self.central_derivative_at(idx)
         };
 
+        [yl, yu, dydxl, dydxu]
+    }
+
+    /// Perform 1d cubic interpolation such that f(x) = y
+    pub(crate) fn cubic_interpolate_1d(&self, query: f64, idx: usize) -> f64 {
+        // grid slice utilities are expected to be called multipled times for the same
+        // query and so it is convient to pass idx from the outside to avoid expensive searches
+        let dx = self.x[idx + 1] - self.x[idx];
+        let [yl, yu, dydxl, dydxu] = self.hermite_coefficients(idx);
+
         let t = (query - self.x[idx]) / dx;
 
         cubic_interpolation_1d(t, yl, yu, dydxl, dydxu)
     }
+
+    /// Derivative of [`GridSlice::cubic_interpolate_1d`] with respect to the query
+    pub(crate) fn cubic_derivative_1d(&self, query: f64, idx: usize) -> f64 {
+        let dx = self.x[idx + 1] - self.x[idx];
+        let [yl, yu, dydxl, dydxu] = self.hermite_coefficients(idx);
+
+        let t = (query - self.x[idx]) / dx;
+
+        cubic_derivative_1d(t, yl, yu, dydxl, dydxu) / dx
+    }
+
+    /// Integral of the 1d cubic interpolation between `lower` and `upper`, with `lower <= upper`
+    pub(crate) fn cubic_integrate_1d(
+        &self,
+        lower: f64,
+        upper: f64,
+    ) -> Result<f64, InterpolationError> {
+        let [first] = closest_below::<1>(slice::from_ref(self.x), &[lower])?;
+        let [last] = closest_below::<1>(slice::from_ref(self.x), &[upper])?;
+
+        Ok((first..=last)
+            .map(|idx| {
+                let dx = self.x[idx + 1] - self.x[idx];
+                let [yl, yu, dydxl, dydxu] = self.hermite_coefficients(idx);
+                let t0 = (lower.max(self.x[idx]) - self.x[idx]) / dx;
+                let t1 = (upper.min(self.x[idx + 1]) - self.x[idx]) / dx;
+
+                dx * (cubic_primitive_1d(t1, yl, yu, dydxl, dydxu)
+                    - cubic_primitive_1d(t0, yl, yu, dydxl, dydxu))
+            })
+            .sum())
+    }
 }
 
-impl Interpolator<f64> for Cubic<1> {
+impl<S: Data<Elem = f64>> Interpolator<f64> for Cubic<1, S> {
     /// Use Cubic interpolation 1d to compute y(query)
     /// The interpolation uses the two nearest neighbours and their derivatives computed as an
     /// average of the differences above and below.
@@ -91,46 +183,320 @@ impl Interpolator<f64> for Cubic<1> {
     }
 }
 
-impl Interpolator<&[f64]> for Cubic<2> {
+impl<S: Data<Elem = f64>> Interpolator<&[f64]> for Cubic<1, S> {
+    /// Same as the scalar version, the query must contain a single coordinate
+    fn interpolate(&self, query: &[f64]) -> Result<f64, InterpolationError> {
+        self.interpolate(query[0])
+    }
+}
+
+/// Nodes of the x2 grid entering the 2d cubic interpolation in the interval `id_x2`: the nodes i-1
+/// and i+2 only exist away from the boundaries of the x2 grid
+fn x2_stencil(x2_grid: &[f64], id_x2: usize) -> RangeInclusive<usize> {
+    let lower = if id_x2 == 0 { id_x2 } else { id_x2 - 1 };
+    let upper = if id_x2 == x2_grid.len() - 2 {
+        id_x2 + 1
+    } else {
+        id_x2 + 2
+    };
+
+    lower..=upper
+}
+
+/// Values and derivatives in x2 (times the width of the interval) at the edges of the interval
+/// `id_x2`, given the values `vs` at the nodes (i-1, i, i+1, i+2) of the stencil
+fn x2_hermite_coefficients(x2_grid: &[f64], id_x2: usize, vs: [f64; 4]) -> [f64; 4] {
+    // At the boundaries the derivative is approximated by the forward (backward) difference
+    let first = id_x2 == 0;
+    let last = id_x2 == x2_grid.len() - 2;
+    let dx2_1 = x2_grid[id_x2 + 1] - x2_grid[id_x2];
+
+    let lower_derivative = if first {
+        vs[2] - vs[1]
+    } else {
+        let dx2_0 = x2_grid[id_x2] - x2_grid[id_x2 - 1];
+        0.5 * ((vs[2] - vs[1]) + (vs[1] - vs[0]) * dx2_1 / dx2_0)
+    };
+    let upper_derivative = if last {
+        vs[2] - vs[1]
+    } else {
+        let dx2_2 = x2_grid[id_x2 + 2] - x2_grid[id_x2 + 1];
+        0.5 * ((vs[2] - vs[1]) + (vs[3] - vs[2]) * dx2_1 / dx2_2)
+    };
+
+    [vs[1], vs[2], lower_derivative, upper_derivative]
+}
+
+/// Perform 2d cubic interpolation of `values`, with input vectors `xgrid`, given the indices `idx`
+/// of the nodes right below the query (see [`GridBase::closest_below`])
+///
+/// The interpolation uses the two nearest neighbours in both dimensions
+/// and their derivatives computed as an average of the differences above and below.
+pub(crate) fn cubic_interpolate_2d(
+    xgrid: &[Vec<f64>],
+    values: ArrayView2<f64>,
+    query: &[f64],
+    idx: [usize; 2],
+) -> f64 {
+    let x1 = query[0]; // x1 correspond to x in PDF interpolation
+    let x2 = query[1];
+    let [id_x1, id_x2] = idx;
+
+    // First interpolate in x1 by taken the nodes around the x2 index
+    // Create slices in x1 for values in x2 at (i+2, i+1, <query>, i, i-1)
+    let x2_grid = &xgrid[1];
+    let mut vs = [0.0; 4];
+    for i in x2_stencil(x2_grid, id_x2) {
+        vs[i + 1 - id_x2] =
+            GridSlice::from_2d(xgrid, values.view(), 0, i).cubic_interpolate_1d(x1, id_x1);
+    }
+
+    // Now perform the interpolation in x2
+    let [yl, yu, dydxl, dydxu] = x2_hermite_coefficients(x2_grid, id_x2, vs);
+    let t = (x2 - x2_grid[id_x2]) / (x2_grid[id_x2 + 1] - x2_grid[id_x2]);
+
+    cubic_interpolation_1d(t, yl, yu, dydxl, dydxu)
+}
+
+/// Same as [`cubic_interpolate_2d`], also computing the gradient
+///
+/// The interpolation is linear in the values of the slices in x1, therefore the derivative in x1
+/// is the interpolation in x2 of the derivatives of the slices
+fn cubic_gradient_2d(
+    xgrid: &[Vec<f64>],
+    values: ArrayView2<f64>,
+    query: &[f64],
+    idx: [usize; 2],
+) -> (f64, [f64; 2]) {
+    let [id_x1, id_x2] = idx;
+    let x2_grid = &xgrid[1];
+
+    let mut vs = [0.0; 4];
+    let mut dvs = [0.0; 4];
+    for i in x2_stencil(x2_grid, id_x2) {
+        let slice = GridSlice::from_2d(xgrid, values.view(), 0, i);
+        vs[i + 1 - id_x2] = slice.cubic_interpolate_1d(query[0], id_x1);
+        dvs[i + 1 - id_x2] = slice.cubic_derivative_1d(query[0], id_x1);
+    }
+
+    let dx2 = x2_grid[id_x2 + 1] - x2_grid[id_x2];
+    let t = (query[1] - x2_grid[id_x2]) / dx2;
+    let [yl, yu, dydxl, dydxu] = x2_hermite_coefficients(x2_grid, id_x2, vs);
+    let [dyl, dyu, ddydxl, ddydxu] = x2_hermite_coefficients(x2_grid, id_x2, dvs);
+
+    (
+        cubic_interpolation_1d(t, yl, yu, dydxl, dydxu),
+        [
+            cubic_interpolation_1d(t, dyl, dyu, ddydxl, ddydxu),
+            cubic_derivative_1d(t, yl, yu, dydxl, dydxu) / dx2,
+        ],
+    )
+}
+
+/// Integral of the 2d cubic interpolation over the box with corners `lower` and `upper`, with
+/// `lower <= upper` along both axes
+///
+/// Like for the gradient, the integral in x1 can be taken on the slices before interpolating in x2
+fn cubic_integrate_2d(
+    xgrid: &[Vec<f64>],
+    values: ArrayView2<f64>,
+    lower: [f64; 2],
+    upper: [f64; 2],
+) -> Result<f64, InterpolationError> {
+    let x2_grid = &xgrid[1];
+    let [first] = closest_below::<1>(&xgrid[1..], &lower[1..])?;
+    let [last] = closest_below::<1>(&xgrid[1..], &upper[1..])?;
+
+    // integrals in x1 of all the slices entering the interpolation
+    let offset = x2_stencil(x2_grid, first).start().to_owned();
+    let integrals = (offset..=*x2_stencil(x2_grid, last).end())
+        .map(|i| {
+            GridSlice::from_2d(xgrid, values.view(), 0, i).cubic_integrate_1d(lower[0], upper[0])
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut result = 0.;
+    for id_x2 in first..=last {
+        let mut vs = [0.0; 4];
+        for i in x2_stencil(x2_grid, id_x2) {
+            vs[i + 1 - id_x2] = integrals[i - offset];
+        }
+
+        let dx2 = x2_grid[id_x2 + 1] - x2_grid[id_x2];
+        let t0 = (lower[1].max(x2_grid[id_x2]) - x2_grid[id_x2]) / dx2;
+        let t1 = (upper[1].min(x2_grid[id_x2 + 1]) - x2_grid[id_x2]) / dx2;
+        let [yl, yu, dydxl, dydxu] = x2_hermite_coefficients(x2_grid, id_x2, vs);
+
+        result += dx2
+            * (cubic_primitive_1d(t1, yl, yu, dydxl, dydxu)
+                - cubic_primitive_1d(t0, yl, yu, dydxl, dydxu));
+    }
+
+    Ok(result)
+}
+
+impl<S: Data<Elem = f64>> Interpolator<&[f64]> for Cubic<2, S> {
     /// Use Cubic interpolation 2d to compute y([x1, x2])
     ///
     /// The interpolation uses the two nearest neighbours in both dimensions
     /// and their derivatives computed as an average of the differences above and below.
     fn interpolate(&self, query: &[f64]) -> Result<f64, InterpolationError> {
-        let raw_idx = self.grid.closest_below(query)?;
+        let idx = self.grid.closest_below(query)?;
+
+        Ok(cubic_interpolate_2d(
+            &self.grid.xgrid,
+            self.grid.values.view(),
+            query,
+            idx,
+        ))
+    }
+}
 
-        let x1 = query[0]; // x1 correspond to x in PDF interpolation
-        let x2 = query[1];
-        let id_x1 = raw_idx[0];
-        let id_x2 = raw_idx[1];
+impl<S: Data<Elem = f64>> Differentiate<&[f64]> for Cubic<1, S> {
+    fn value_and_gradient(
+        &self,
+        query: &[f64],
+        gradient: &mut [f64],
+    ) -> Result<f64, InterpolationError> {
+        let [idx] = self.grid.closest_below(query)?;
+        let slice = self.grid.grid1d_to_slice1d();
 
-        // First interpolate in x1 by taken the nodes around the x2 index
-        // Create slices in x1 for values in x2 at (i+2, i+1, <query>, i, i-1)
-        let mut vs = [0.0; 4];
-        for (v, i) in izip!(&mut vs, (id_x2 - 1)..(id_x2 + 3)) {
-            *v = self
+        gradient[0] = slice.cubic_derivative_1d(query[0], idx);
+        Ok(slice.cubic_interpolate_1d(query[0], idx))
+    }
+}
+
+impl<S: Data<Elem = f64>> Integrate<&[f64]> for Cubic<1, S> {
+    fn integrate(&self, lower: &[f64], upper: &[f64]) -> Result<f64, InterpolationError> {
+        let ([lower], [upper], sign) = sort_bounds::<1>(&self.grid.xgrid, lower, upper)?;
+
+        Ok(sign
+            * self
                 .grid
-                .grid2d_to_slice1d(0, i)
-                .cubic_interpolate_1d(x1, id_x1);
+                .grid1d_to_slice1d()
+                .cubic_integrate_1d(lower, upper)?)
+    }
+}
+
+impl<S: Data<Elem = f64>> Differentiate<&[f64]> for Cubic<2, S> {
+    fn value_and_gradient(
+        &self,
+        query: &[f64],
+        gradient: &mut [f64],
+    ) -> Result<f64, InterpolationError> {
+        let idx = self.grid.closest_below(query)?;
+        let (value, grad) =
+            cubic_gradient_2d(&self.grid.xgrid, self.grid.values.view(), query, idx);
+
+        gradient[..2].copy_from_slice(&grad);
+        Ok(value)
+    }
+}
+
+impl<S: Data<Elem = f64>> Integrate<&[f64]> for Cubic<2, S> {
+    fn integrate(&self, lower: &[f64], upper: &[f64]) -> Result<f64, InterpolationError> {
+        let (lower, upper, sign) = sort_bounds::<2>(&self.grid.xgrid, lower, upper)?;
+
+        Ok(sign * cubic_integrate_2d(&self.grid.xgrid, self.grid.values.view(), lower, upper)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use ndarray::{array, Array2};
+
+    /// Simpson's rule on each interval of `nodes` between `lower` and `upper`, exact for the
+    /// piecewise cubic interpolation
+    fn simpson(nodes: &[f64], lower: f64, upper: f64, f: impl Fn(f64) -> f64) -> f64 {
+        let mut points: Vec<_> = nodes
+            .iter()
+            .copied()
+            .filter(|&x| x > lower && x < upper)
+            .collect();
+        points.insert(0, lower);
+        points.push(upper);
+
+        points
+            .windows(2)
+            .map(|w| (w[1] - w[0]) / 6. * (f(w[0]) + 4. * f(0.5 * (w[0] + w[1])) + f(w[1])))
+            .sum()
+    }
+
+    fn gen_cubic_2d() -> Cubic<2> {
+        let x1: Vec<f64> = vec![0., 0.5, 1.5, 2., 3.];
+        let x2 = vec![-1., 0., 1., 2.5, 3.];
+        let values = Array2::from_shape_fn((x1.len(), x2.len()), |(i, j)| {
+            (x1[i] * x2[j]).sin() + x1[i] * x1[i]
+        });
+
+        Cubic {
+            grid: Grid::new(vec![x1, x2], values).unwrap(),
         }
+    }
 
-        // Now perform the interpolation in x2
-        let x2_grid = &self.grid.xgrid[1];
-        let dx2_0 = x2_grid[id_x2] - x2_grid[id_x2 - 1];
-        let dx2_1 = x2_grid[id_x2 + 1] - x2_grid[id_x2];
-        let dx2_2 = x2_grid[id_x2 + 2] - x2_grid[id_x2 + 1];
+    #[test]
+    fn check_gradient() {
+        let h = 1e-6;
 
-        let lower_derivative = 0.5 * ((vs[2] - vs[1]) + (vs[1] - vs[0]) * dx2_1 / dx2_0);
-        let upper_derivative = 0.5 * ((vs[2] - vs[1]) + (vs[3] - vs[2]) * dx2_1 / dx2_2);
+        let cubic = Cubic::<1> {
+            grid: Grid::new(vec![vec![0., 1., 2., 3.5, 4.]], array![1., 3., 2., 5., 4.]).unwrap(),
+        };
+        let mut gradient = [0.];
+        for x in [0.2, 1., 2.7, 3.9] {
+            let value = cubic.value_and_gradient(&[x], &mut gradient).unwrap();
+            assert_eq!(value, cubic.interpolate(x).unwrap());
+            let numerical =
+                (cubic.interpolate(x + h).unwrap() - cubic.interpolate(x - h).unwrap()) / (2. * h);
+            assert!((gradient[0] - numerical).abs() < 1e-6);
+        }
 
-        let t = (x2 - x2_grid[id_x2]) / dx2_1;
+        let cubic = gen_cubic_2d();
+        let mut gradient = [0.; 2];
+        for [x1, x2] in [[0.2, -0.5], [1.7, 0.5], [2.9, 2.9], [1., 2.]] {
+            let value = cubic.value_and_gradient(&[x1, x2], &mut gradient).unwrap();
+            assert_eq!(value, cubic.interpolate(&[x1, x2][..]).unwrap());
 
-        Ok(cubic_interpolation_1d(
-            t,
-            vs[1],
-            vs[2],
-            lower_derivative,
-            upper_derivative,
-        ))
+            let f = |x1: f64, x2: f64| cubic.interpolate(&[x1, x2][..]).unwrap();
+            let numerical = [
+                (f(x1 + h, x2) - f(x1 - h, x2)) / (2. * h),
+                (f(x1, x2 + h) - f(x1, x2 - h)) / (2. * h),
+            ];
+            for (g, n) in gradient.iter().zip(numerical) {
+                assert!((g - n).abs() < 1e-6);
+            }
+        }
+
+        assert!(cubic.value_and_gradient(&[3.5, 0.], &mut gradient).is_err());
+    }
+
+    #[test]
+    fn check_integral() {
+        let nodes = [0., 1., 2., 3.5, 4.];
+        let cubic = Cubic::<1> {
+            grid: Grid::new(vec![nodes.to_vec()], array![1., 3., 2., 5., 4.]).unwrap(),
+        };
+        for (a, b) in [(0., 4.), (0.3, 0.7), (0.5, 3.7), (1., 2.)] {
+            let expected = simpson(&nodes, a, b, |x| cubic.interpolate(x).unwrap());
+            assert!((cubic.integrate(&[a], &[b]).unwrap() - expected).abs() < 1e-12);
+            assert!((cubic.integrate(&[b], &[a]).unwrap() + expected).abs() < 1e-12);
+        }
+        assert!(cubic.integrate(&[-1.], &[1.]).is_err());
+
+        let cubic = gen_cubic_2d();
+        let (x1, x2) = (&cubic.grid.xgrid[0], &cubic.grid.xgrid[1]);
+        for ([a1, a2], [b1, b2]) in [
+            ([0., -1.], [3., 3.]),
+            ([0.2, 0.1], [0.4, 0.6]),
+            ([0.7, -0.5], [2.6, 2.8]),
+        ] {
+            let expected = simpson(x1, a1, b1, |u| {
+                simpson(x2, a2, b2, |v| cubic.interpolate(&[u, v][..]).unwrap())
+            });
+            let integral = cubic.integrate(&[a1, a2], &[b1, b2]).unwrap();
+            assert!((integral - expected).abs() < 1e-12);
+        }
+        assert!(cubic.integrate(&[0., -1.], &[3., 3.5]).is_err());
     }
 }
@@ -0,0 +1,356 @@
+//! Interpolation of ensembles of functions sharing the same grid
+//!
+//! A typical example is a PDF set: all the members (Monte Carlo replicas or Hessian eigenvectors)
+//! are tabulated on the same (x, Q²) nodes. The values of all the members are stored in a single
+//! array and the cell containing the query is found only once for all of them.
+//!
+//! The uncertainties are computed following the conventions of LHAPDF, with member 0 being the
+//! central member. For Monte Carlo replicas the central value of [`Uncertainty`] is the average of
+//! the replicas, as in LHAPDF, which can differ slightly from member 0 returned by
+//! [`Interpolator::interpolate`].
+
+use crate::grid::cubic::{cubic_interpolate_2d, Interpolator};
+use crate::grid::{check_xgrid, closest_below, DimensionHelper, Grid, GridSlice, ToDimension};
+use crate::interpolate::{check_query, InterpolationError};
+use ndarray::{Array, Axis, Dimension};
+
+/// How the uncertainty is computed from the members of an ensemble
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorType {
+    /// Monte Carlo replicas: the central value is the average of the members 1..N, not member 0,
+    /// and the uncertainty is their standard deviation
+    Replicas,
+
+    /// Hessian eigenvectors, with members 2k-1 and 2k being the positive and negative variations
+    /// of the k-th eigenvector: the uncertainties are asymmetric
+    Hessian,
+
+    /// Symmetric Hessian eigenvectors, with one member per eigenvector
+    SymmHessian,
+}
+
+/// Central value and uncertainties of an ensemble at a given point
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Uncertainty {
+    /// The central value
+    pub central: f64,
+    /// The uncertainty above the central value
+    pub errplus: f64,
+    /// The uncertainty below the central value
+    pub errminus: f64,
+    /// The symmetrized uncertainty
+    pub errsymm: f64,
+}
+
+impl ErrorType {
+    /// Computes the central value and uncertainties from the values of all the members
+    pub fn uncertainty(&self, values: &[f64]) -> Uncertainty {
+        let central = values[0];
+        let errors = &values[1..];
+
+        match self {
+            ErrorType::Replicas => {
+                let n = errors.len() as f64;
+                let average = errors.iter().sum::<f64>() / n;
+                let variance = errors.iter().map(|v| (v - average).powi(2)).sum::<f64>() / (n - 1.);
+                let std = if errors.len() > 1 {
+                    variance.sqrt()
+                } else {
+                    0.
+                };
+
+                Uncertainty {
+                    central: if errors.is_empty() { central } else { average },
+                    errplus: std,
+                    errminus: std,
+                    errsymm: std,
+                }
+            }
+            ErrorType::Hessian => {
+                let mut errplus = 0.;
+                let mut errminus = 0.;
+                let mut errsymm = 0.;
+                for pair in errors.chunks_exact(2) {
+                    let (up, down) = (pair[0] - central, pair[1] - central);
+                    errplus += up.max(down).max(0.).powi(2);
+                    errminus += (-up).max(-down).max(0.).powi(2);
+                    errsymm += (pair[0] - pair[1]).powi(2);
+                }
+
+                Uncertainty {
+                    central,
+                    errplus: errplus.sqrt(),
+                    errminus: errminus.sqrt(),
+                    errsymm: 0.5 * errsymm.sqrt(),
+                }
+            }
+            ErrorType::SymmHessian => {
+                let err = errors
+                    .iter()
+                    .map(|v| (v - central).powi(2))
+                    .sum::<f64>()
+                    .sqrt();
+
+                Uncertainty {
+                    central,
+                    errplus: err,
+                    errminus: err,
+                    errsymm: err,
+                }
+            }
+        }
+    }
+}
+
+/// Cubic interpolation of an ensemble of functions tabulated on the same grid
+#[derive(Debug)]
+pub struct Ensemble<const D: usize>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    /// Arrays with the input vectors (x_i), shared by all members
+    pub xgrid: Vec<Vec<f64>>,
+
+    /// Values of all the members, the first axis runs over the members
+    pub values: Array<f64, <<DimensionHelper<D> as ToDimension>::Dim as Dimension>::Larger>,
+
+    /// How uncertainties are computed from the members
+    pub error_type: ErrorType,
+}
+
+impl<const D: usize> Ensemble<D>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    /// Creates an ensemble checking that the input vectors are consistent with the values (see
+    /// [`Grid::new`]) and, for Hessian sets, that there are two members per eigenvector
+    pub fn new(
+        xgrid: Vec<Vec<f64>>,
+        values: Array<f64, <<DimensionHelper<D> as ToDimension>::Dim as Dimension>::Larger>,
+        error_type: ErrorType,
+    ) -> Result<Self, InterpolationError> {
+        let (&members, shape) = values.shape().split_first().unwrap();
+        check_xgrid(&xgrid, shape)?;
+
+        if members == 0 {
+            return Err(InterpolationError::InvalidGrid(
+                "the ensemble has no members".to_owned(),
+            ));
+        }
+        if error_type == ErrorType::Hessian && members % 2 == 0 {
+            return Err(InterpolationError::InvalidGrid(format!(
+                "a Hessian ensemble needs an even number of members besides the central one, got {}",
+                members - 1
+            )));
+        }
+
+        Ok(Self {
+            xgrid,
+            values,
+            error_type,
+        })
+    }
+
+    /// Creates an ensemble from the grids of each member, which must all share the same input
+    /// vectors
+    pub fn from_grids(
+        grids: &[Grid<D>],
+        error_type: ErrorType,
+    ) -> Result<Self, InterpolationError> {
+        let first = grids.first().ok_or_else(|| {
+            InterpolationError::InvalidGrid("the ensemble has no members".to_owned())
+        })?;
+        if grids.iter().any(|grid| grid.xgrid != first.xgrid) {
+            return Err(InterpolationError::InvalidGrid(
+                "the members do not share the same input vectors".to_owned(),
+            ));
+        }
+
+        let views: Vec<_> = grids.iter().map(|grid| grid.values.view()).collect();
+        let values = ndarray::stack(Axis(0), &views)
+            .map_err(|err| InterpolationError::InvalidGrid(err.to_string()))?;
+
+        Self::new(first.xgrid.clone(), values, error_type)
+    }
+
+    /// Number of members of the ensemble, including the central one
+    pub fn n_members(&self) -> usize {
+        self.values.len_of(Axis(0))
+    }
+}
+
+impl Ensemble<1> {
+    /// Interpolates all the members at `query`
+    pub fn members(&self, query: &[f64]) -> Result<Vec<f64>, InterpolationError> {
+        check_query(query, 1)?;
+        let [idx] = closest_below::<1>(&self.xgrid, query)?;
+
+        Ok(self
+            .values
+            .outer_iter()
+            .map(|member| {
+                GridSlice {
+                    x: &self.xgrid[0],
+                    y: member,
+                }
+                .cubic_interpolate_1d(query[0], idx)
+            })
+            .collect())
+    }
+
+    /// Interpolates all the members at `query` and computes the central value and uncertainties
+    pub fn uncertainty(&self, query: &[f64]) -> Result<Uncertainty, InterpolationError> {
+        Ok(self.error_type.uncertainty(&self.members(query)?))
+    }
+}
+
+impl Ensemble<2> {
+    /// Interpolates all the members at `query`
+    pub fn members(&self, query: &[f64]) -> Result<Vec<f64>, InterpolationError> {
+        check_query(query, 2)?;
+        let idx = closest_below::<2>(&self.xgrid, query)?;
+
+        Ok(self
+            .values
+            .outer_iter()
+            .map(|member| cubic_interpolate_2d(&self.xgrid, member, query, idx))
+            .collect())
+    }
+
+    /// Interpolates all the members at `query` and computes the central value and uncertainties
+    pub fn uncertainty(&self, query: &[f64]) -> Result<Uncertainty, InterpolationError> {
+        Ok(self.error_type.uncertainty(&self.members(query)?))
+    }
+}
+
+impl Interpolator<&[f64]> for Ensemble<1> {
+    /// Interpolates the central member only
+    fn interpolate(&self, query: &[f64]) -> Result<f64, InterpolationError> {
+        check_query(query, 1)?;
+        let [idx] = closest_below::<1>(&self.xgrid, query)?;
+
+        Ok(GridSlice {
+            x: &self.xgrid[0],
+            y: self.values.index_axis(Axis(0), 0),
+        }
+        .cubic_interpolate_1d(query[0], idx))
+    }
+}
+
+impl Interpolator<&[f64]> for Ensemble<2> {
+    /// Interpolates the central member only
+    fn interpolate(&self, query: &[f64]) -> Result<f64, InterpolationError> {
+        check_query(query, 2)?;
+        let idx = closest_below::<2>(&self.xgrid, query)?;
+
+        Ok(cubic_interpolate_2d(
+            &self.xgrid,
+            self.values.index_axis(Axis(0), 0),
+            query,
+            idx,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::cubic::Cubic;
+    use ndarray::{array, Array2};
+
+    fn gen_grids() -> Vec<Grid<2>> {
+        let x1 = vec![0., 0.5, 1.5, 2., 3.];
+        let x2 = vec![-1., 0., 1., 2.5];
+
+        (0..5)
+            .map(|m| {
+                let m = f64::from(m);
+                let values = Array2::from_shape_fn((x1.len(), x2.len()), |(i, j)| {
+                    (1. + 0.1 * m) * x1[i] * x1[i] - 2. * x2[j] + m * x1[i] * x2[j]
+                });
+                Grid::new(vec![x1.clone(), x2.clone()], values).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_members() {
+        let grids = gen_grids();
+        let ensemble = Ensemble::from_grids(&grids, ErrorType::Hessian).unwrap();
+        assert_eq!(ensemble.n_members(), 5);
+
+        let query = [1.2, 0.3];
+        let members = ensemble.members(&query).unwrap();
+        for (grid, value) in grids.into_iter().zip(members) {
+            assert_eq!(Cubic { grid }.interpolate(&query[..]).unwrap(), value);
+        }
+        assert_eq!(
+            ensemble.interpolate(&query[..]).unwrap(),
+            ensemble.members(&query).unwrap()[0]
+        );
+
+        assert!(matches!(
+            ensemble.members(&[3.5, 0.]),
+            Err(InterpolationError::ExtrapolationAbove(_))
+        ));
+        assert!(matches!(
+            ensemble.members(&[1.2]),
+            Err(InterpolationError::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn check_members_1d() {
+        let xgrid = vec![vec![0., 1., 2., 3.]];
+        let values = array![[1., 2., 3., 5.], [2., 3., 4., 6.]];
+        let ensemble = Ensemble::<1>::new(xgrid, values, ErrorType::SymmHessian).unwrap();
+
+        let members = ensemble.members(&[1.5]).unwrap();
+        assert!((members[1] - members[0] - 1.).abs() < 1e-12);
+        assert!((ensemble.uncertainty(&[1.5]).unwrap().errsymm - 1.).abs() < 1e-12);
+
+        for query in [&[][..], &[1.5, 0.5]] {
+            assert!(matches!(
+                ensemble.members(query),
+                Err(InterpolationError::InvalidQuery(_))
+            ));
+            assert!(ensemble.interpolate(query).is_err());
+        }
+    }
+
+    #[test]
+    fn check_uncertainties() {
+        let values = [1., 0.8, 1.2, 1.1, 0.9];
+
+        let replicas = ErrorType::Replicas.uncertainty(&values);
+        assert!((replicas.central - 1.).abs() < 1e-12);
+        assert!((replicas.errsymm - (0.1f64 / 3.).sqrt()).abs() < 1e-12);
+
+        let hessian = ErrorType::Hessian.uncertainty(&values);
+        assert_eq!(hessian.central, 1.);
+        assert!((hessian.errplus - 0.05f64.sqrt()).abs() < 1e-12);
+        assert!((hessian.errminus - 0.05f64.sqrt()).abs() < 1e-12);
+        assert!((hessian.errsymm - 0.5 * 0.2f64.sqrt()).abs() < 1e-12);
+
+        // both variations on the same side of the central value
+        let hessian = ErrorType::Hessian.uncertainty(&[1., 1.2, 1.1]);
+        assert!((hessian.errplus - 0.2).abs() < 1e-12);
+        assert_eq!(hessian.errminus, 0.);
+
+        let symmhessian = ErrorType::SymmHessian.uncertainty(&values);
+        assert_eq!(symmhessian.central, 1.);
+        assert!((symmhessian.errsymm - 0.1f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn check_invalid_ensemble() {
+        let mut grids = gen_grids();
+        assert!(Ensemble::from_grids(&grids[..4], ErrorType::Hessian).is_err());
+        assert!(Ensemble::from_grids(&grids[..4], ErrorType::Replicas).is_ok());
+        assert!(Ensemble::<2>::from_grids(&[], ErrorType::Replicas).is_err());
+
+        grids[1].xgrid[0][1] = 0.6;
+        assert!(Ensemble::from_grids(&grids, ErrorType::Replicas).is_err());
+    }
+}
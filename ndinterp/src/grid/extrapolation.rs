@@ -0,0 +1,274 @@
+//! Policies for queries outside of the grid
+//!
+//! By default the interpolators return an error when queried outside of the grid. Wrapping them
+//! in [`Extrapolated`] allows choosing a different behaviour for each axis.
+
+use crate::interpolate::{Differentiate, Integrate, InterpolationError, Interpolator};
+use itertools::izip;
+
+/// What to do with a query outside of the grid along one axis
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Extrapolation {
+    /// Return [`InterpolationError::ExtrapolationAbove`] or
+    /// [`InterpolationError::ExtrapolationBelow`]
+    #[default]
+    Error,
+
+    /// Move the query to the closest boundary of the grid along this axis
+    Clamp,
+
+    /// Return the given value, unless the query is outside of the grid along an axis with the
+    /// [`Extrapolation::Error`] policy. If more axes have this policy, the value of the first of
+    /// them along which the query is outside of the grid is returned
+    Constant(f64),
+}
+
+/// An interpolator applying an [`Extrapolation`] policy along each axis
+#[derive(Debug)]
+pub struct Extrapolated<I> {
+    /// The wrapped interpolator
+    pub interpolator: I,
+
+    /// Lower and upper boundary of the grid along each axis
+    pub bounds: Vec<[f64; 2]>,
+
+    /// The policy applied along each axis
+    pub policies: Vec<Extrapolation>,
+}
+
+impl<I> Extrapolated<I> {
+    /// Wraps `interpolator`, whose grid has input vectors `xgrid`, applying `policies[i]` to the
+    /// queries outside of the grid along the i-th axis
+    pub fn new(
+        interpolator: I,
+        xgrid: &[Vec<f64>],
+        policies: Vec<Extrapolation>,
+    ) -> Result<Self, InterpolationError> {
+        if policies.len() != xgrid.len() {
+            return Err(InterpolationError::InvalidGrid(format!(
+                "{} extrapolation policies given for a {}-dimensional grid",
+                policies.len(),
+                xgrid.len()
+            )));
+        }
+
+        let bounds = xgrid
+            .iter()
+            .map(|x| match (x.first(), x.last()) {
+                (Some(&first), Some(&last)) => Ok([first, last]),
+                _ => Err(InterpolationError::InvalidGrid(
+                    "empty input vector".to_owned(),
+                )),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            interpolator,
+            bounds,
+            policies,
+        })
+    }
+}
+
+/// A query after the application of the extrapolation policies
+enum Policed {
+    /// The query to be interpolated, with the axes along which it has been clamped
+    Clamped(Vec<f64>, Vec<bool>),
+    /// The query is outside of the grid along an axis with a constant value
+    Constant(f64),
+}
+
+impl<I> Extrapolated<I> {
+    /// Applies the policies of every axis, such that the result does not depend on their order:
+    /// an error along any axis wins over the constant policies
+    fn apply_policies(&self, query: &[f64]) -> Result<Policed, InterpolationError> {
+        let mut clamped = query.to_vec();
+        let mut clamped_axes = vec![false; query.len()];
+        let mut constant = None;
+
+        for (x, is_clamped, &[lower, upper], policy) in izip!(
+            &mut clamped,
+            &mut clamped_axes,
+            &self.bounds,
+            &self.policies
+        ) {
+            if *x >= lower && *x <= upper {
+                continue;
+            }
+            match *policy {
+                Extrapolation::Error if *x > upper => {
+                    return Err(InterpolationError::ExtrapolationAbove(*x))
+                }
+                Extrapolation::Error => return Err(InterpolationError::ExtrapolationBelow(*x)),
+                Extrapolation::Clamp => {
+                    *x = x.clamp(lower, upper);
+                    *is_clamped = true;
+                }
+                Extrapolation::Constant(value) => {
+                    constant.get_or_insert(value);
+                }
+            }
+        }
+
+        Ok(match constant {
+            Some(value) => Policed::Constant(value),
+            None => Policed::Clamped(clamped, clamped_axes),
+        })
+    }
+}
+
+impl<I> Interpolator<&[f64]> for Extrapolated<I>
+where
+    I: for<'a> Interpolator<&'a [f64]>,
+{
+    fn interpolate(&self, query: &[f64]) -> Result<f64, InterpolationError> {
+        match self.apply_policies(query)? {
+            Policed::Clamped(clamped, _) => self.interpolator.interpolate(&clamped),
+            Policed::Constant(value) => Ok(value),
+        }
+    }
+}
+
+impl<I> Differentiate<&[f64]> for Extrapolated<I>
+where
+    I: for<'a> Differentiate<&'a [f64]>,
+{
+    /// Outside of the grid the function is constant along the clamped axes, and everywhere for
+    /// the constant policy, so the corresponding derivatives vanish
+    fn value_and_gradient(
+        &self,
+        query: &[f64],
+        gradient: &mut [f64],
+    ) -> Result<f64, InterpolationError> {
+        match self.apply_policies(query)? {
+            Policed::Clamped(clamped, clamped_axes) => {
+                let value = self.interpolator.value_and_gradient(&clamped, gradient)?;
+                for (derivative, is_clamped) in gradient.iter_mut().zip(clamped_axes) {
+                    if is_clamped {
+                        *derivative = 0.;
+                    }
+                }
+                Ok(value)
+            }
+            Policed::Constant(value) => {
+                gradient[..self.bounds.len()].fill(0.);
+                Ok(value)
+            }
+        }
+    }
+}
+
+impl<I> Integrate<&[f64]> for Extrapolated<I>
+where
+    I: for<'a> Integrate<&'a [f64]>,
+{
+    /// The extrapolation policies do not apply to integrals: the bounds must lie within the grid
+    fn integrate(&self, lower: &[f64], upper: &[f64]) -> Result<f64, InterpolationError> {
+        self.interpolator.integrate(lower, upper)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::linear::Linear;
+    use crate::grid::Grid;
+    use ndarray::array;
+
+    #[test]
+    fn check_policies() {
+        let grid =
+            Grid::<2>::new(vec![vec![0., 1.], vec![0., 2.]], array![[0., 2.], [1., 3.]]).unwrap();
+        let xgrid = grid.xgrid.clone();
+        let extrapolated = Extrapolated::new(
+            Linear { grid },
+            &xgrid,
+            vec![Extrapolation::Clamp, Extrapolation::Constant(-1.)],
+        )
+        .unwrap();
+
+        assert_eq!(extrapolated.interpolate(&[0.5, 1.][..]).unwrap(), 1.5);
+        assert_eq!(extrapolated.interpolate(&[2., 1.][..]).unwrap(), 2.);
+        assert_eq!(extrapolated.interpolate(&[-1., 0.][..]).unwrap(), 0.);
+        assert_eq!(extrapolated.interpolate(&[0.5, 3.][..]).unwrap(), -1.);
+
+        let grid = Grid::<1>::new(vec![vec![0., 1.]], array![0., 1.]).unwrap();
+        let extrapolated =
+            Extrapolated::new(Linear { grid }, &[vec![0., 1.]], vec![Extrapolation::Error])
+                .unwrap();
+        assert!(matches!(
+            extrapolated.interpolate(&[1.5][..]),
+            Err(InterpolationError::ExtrapolationAbove(_))
+        ));
+        assert!(matches!(
+            extrapolated.interpolate(&[-0.5][..]),
+            Err(InterpolationError::ExtrapolationBelow(_))
+        ));
+
+        assert!(Extrapolated::new((), &[vec![0., 1.]], vec![]).is_err());
+    }
+
+    #[test]
+    fn check_policies_order() {
+        let grid =
+            Grid::<2>::new(vec![vec![0., 1.], vec![0., 2.]], array![[0., 2.], [1., 3.]]).unwrap();
+        let xgrid = grid.xgrid.clone();
+        let extrapolated = Extrapolated::new(
+            Linear { grid },
+            &xgrid,
+            vec![Extrapolation::Constant(-1.), Extrapolation::Error],
+        )
+        .unwrap();
+
+        // the error along the second axis wins over the constant along the first one
+        assert_eq!(extrapolated.interpolate(&[2., 1.][..]).unwrap(), -1.);
+        assert!(matches!(
+            extrapolated.interpolate(&[2., 3.][..]),
+            Err(InterpolationError::ExtrapolationAbove(x)) if x == 3.
+        ));
+        let mut gradient = [0.; 2];
+        assert!(extrapolated
+            .value_and_gradient(&[-1., -1.], &mut gradient)
+            .is_err());
+    }
+
+    #[test]
+    fn check_gradient() {
+        let grid =
+            Grid::<2>::new(vec![vec![0., 1.], vec![0., 2.]], array![[0., 2.], [1., 3.]]).unwrap();
+        let xgrid = grid.xgrid.clone();
+        let extrapolated = Extrapolated::new(
+            Linear { grid },
+            &xgrid,
+            vec![Extrapolation::Clamp, Extrapolation::Constant(-1.)],
+        )
+        .unwrap();
+
+        let mut gradient = [f64::NAN; 2];
+        assert_eq!(
+            extrapolated
+                .value_and_gradient(&[0.5, 1.], &mut gradient)
+                .unwrap(),
+            1.5
+        );
+        assert_eq!(gradient, [1., 1.]);
+        assert_eq!(
+            extrapolated
+                .value_and_gradient(&[2., 1.], &mut gradient)
+                .unwrap(),
+            2.
+        );
+        assert_eq!(gradient, [0., 1.]);
+        assert_eq!(
+            extrapolated
+                .value_and_gradient(&[0.5, 3.], &mut gradient)
+                .unwrap(),
+            -1.
+        );
+        assert_eq!(gradient, [0., 0.]);
+
+        assert_eq!(extrapolated.integrate(&[0., 0.], &[1., 2.]).unwrap(), 3.);
+        assert!(extrapolated.integrate(&[0., 0.], &[2., 2.]).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! Implements multilinear interpolation
+//!
+//! Within each cell of the grid the interpolated function is linear in each of the variables
+//! separately, i.e. it is the weighted average of the values at the 2^D corners of the cell.
+
+use crate::grid::{sort_bounds, DimensionHelper, GridBase, ToDimension};
+pub use crate::interpolate::Interpolator;
+use crate::interpolate::{Differentiate, Integrate, InterpolationError};
+use ndarray::{Axis, Data, Dimension, OwnedRepr, RawData};
+use std::fmt;
+
+/// Multilinear interpolation, available in any number of dimensions
+///
+/// By default the interpolator owns the grid, but it can also work on a grid borrowing its values
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "GridBase<S, D>: serde::Serialize",
+        deserialize = "GridBase<S, D>: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Linear<const D: usize, S = OwnedRepr<f64>>
+where
+    S: RawData<Elem = f64>,
+    DimensionHelper<D>: ToDimension,
+{
+    /// The grid object contains all necessary information to perform the interpolation
+    pub grid: GridBase<S, D>,
+}
+
+impl<S, const D: usize> fmt::Debug for Linear<D, S>
+where
+    S: Data<Elem = f64>,
+    DimensionHelper<D>: ToDimension,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Linear").field("grid", &self.grid).finish()
+    }
+}
+
+impl<S, const D: usize> Linear<D, S>
+where
+    S: Data<Elem = f64>,
+    DimensionHelper<D>: ToDimension,
+{
+    /// Interpolates at `query`, also storing the gradient in `gradient` if given
+    fn interpolate_with_gradient(
+        &self,
+        query: &[f64],
+        mut gradient: Option<&mut [f64]>,
+    ) -> Result<f64, InterpolationError> {
+        let idx = self.grid.closest_below(query)?;
+
+        // position of the query inside the cell, between 0 and 1 along each axis
+        let mut t = [0.; D];
+        let mut dx = [0.; D];
+        for d in 0..D {
+            let x = &self.grid.xgrid[d];
+            dx[d] = x[idx[d] + 1] - x[idx[d]];
+            t[d] = (query[d] - x[idx[d]]) / dx[d];
+        }
+
+        if let Some(gradient) = gradient.as_deref_mut() {
+            gradient[..D].fill(0.);
+        }
+
+        let mut index = <DimensionHelper<D> as ToDimension>::Dim::zeros(D);
+        let mut result = 0.;
+        // each bit of `corner` selects the lower or upper node along one axis
+        for corner in 0..(1 << D) {
+            let mut weights = [0.; D];
+            for d in 0..D {
+                let upper = (corner >> d) & 1;
+                index[d] = idx[d] + upper;
+                weights[d] = if upper == 1 { t[d] } else { 1. - t[d] };
+            }
+            let value = self.grid.values[index.clone()];
+            result += value * weights.iter().product::<f64>();
+
+            if let Some(gradient) = gradient.as_deref_mut() {
+                for d in 0..D {
+                    let sign = if (corner >> d) & 1 == 1 { 1. } else { -1. };
+                    let others: f64 = (0..D).filter(|&e| e != d).map(|e| weights[e]).product();
+                    gradient[d] += sign * value * others / dx[d];
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl<S, const D: usize> Interpolator<&[f64]> for Linear<D, S>
+where
+    S: Data<Elem = f64>,
+    DimensionHelper<D>: ToDimension,
+{
+    fn interpolate(&self, query: &[f64]) -> Result<f64, InterpolationError> {
+        self.interpolate_with_gradient(query, None)
+    }
+}
+
+impl<S, const D: usize> Differentiate<&[f64]> for Linear<D, S>
+where
+    S: Data<Elem = f64>,
+    DimensionHelper<D>: ToDimension,
+{
+    fn value_and_gradient(
+        &self,
+        query: &[f64],
+        gradient: &mut [f64],
+    ) -> Result<f64, InterpolationError> {
+        self.interpolate_with_gradient(query, Some(gradient))
+    }
+}
+
+impl<S, const D: usize> Integrate<&[f64]> for Linear<D, S>
+where
+    S: Data<Elem = f64>,
+    DimensionHelper<D>: ToDimension,
+{
+    /// The interpolated function is the sum of the values times a product of "hat" functions, one
+    /// per axis, so the integral is the contraction of the values with the integrals of the hat
+    /// functions along each axis
+    fn integrate(&self, lower: &[f64], upper: &[f64]) -> Result<f64, InterpolationError> {
+        let (lower, upper, sign) = sort_bounds::<D>(&self.grid.xgrid, lower, upper)?;
+
+        let mut contracted = self.grid.values.view().into_dyn().to_owned();
+        for d in 0..D {
+            let x = &self.grid.xgrid[d];
+            let mut weights = vec![0.; x.len()];
+            for (i, w) in x.windows(2).enumerate() {
+                let dx = w[1] - w[0];
+                let t0 = ((lower[d] - w[0]) / dx).clamp(0., 1.);
+                let t1 = ((upper[d] - w[0]) / dx).clamp(0., 1.);
+                let half_squares = 0.5 * (t1 * t1 - t0 * t0);
+                weights[i] += dx * (t1 - t0 - half_squares);
+                weights[i + 1] += dx * half_squares;
+            }
+            contracted = contracted.map_axis(Axis(0), |lane| {
+                lane.iter()
+                    .zip(&weights)
+                    .map(|(value, weight)| value * weight)
+                    .sum()
+            });
+        }
+
+        Ok(sign * contracted.sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use ndarray::{array, Array3};
+
+    #[test]
+    fn check_linear_1d() {
+        let linear = Linear::<1> {
+            grid: Grid::new(vec![vec![0., 1., 3.]], array![1., 3., 2.]).unwrap(),
+        };
+
+        assert_eq!(linear.interpolate(&[0.][..]).unwrap(), 1.);
+        assert_eq!(linear.interpolate(&[0.5][..]).unwrap(), 2.);
+        assert_eq!(linear.interpolate(&[2.][..]).unwrap(), 2.5);
+        assert_eq!(linear.interpolate(&[3.][..]).unwrap(), 2.);
+        assert!(matches!(
+            linear.interpolate(&[3.5][..]),
+            Err(InterpolationError::ExtrapolationAbove(_))
+        ));
+    }
+
+    #[test]
+    fn check_linear_3d() {
+        // multilinear functions are reproduced exactly
+        let f = |x: f64, y: f64, z: f64| 1. + 2. * x - y + 0.5 * x * y * z + 3. * y * z;
+        let x = vec![0., 0.5, 2.];
+        let y = vec![-1., 0., 1., 3.];
+        let z = vec![1., 2.];
+        let values = Array3::from_shape_fn((3, 4, 2), |(i, j, k)| f(x[i], y[j], z[k]));
+        let linear = Linear::<3> {
+            grid: Grid::new(vec![x, y, z], values).unwrap(),
+        };
+
+        for query in [
+            [0.2, 0.5, 1.5],
+            [1.9, -1., 1.],
+            [2., 3., 2.],
+            [0.5, 2.2, 1.1],
+        ] {
+            let expected = f(query[0], query[1], query[2]);
+            assert!((linear.interpolate(&query[..]).unwrap() - expected).abs() < 1e-12);
+        }
+
+        let mut gradient = [0.; 3];
+        let [x, y, z] = [0.2, 0.5, 1.5];
+        linear
+            .value_and_gradient(&[x, y, z], &mut gradient)
+            .unwrap();
+        let expected = [
+            2. + 0.5 * y * z,
+            -1. + 0.5 * x * z + 3. * z,
+            0.5 * x * y + 3. * y,
+        ];
+        for (g, e) in gradient.iter().zip(expected) {
+            assert!((g - e).abs() < 1e-12);
+        }
+
+        // integral of f over [0, 2] x [-1, 1] x [1, 2]
+        let integral = linear.integrate(&[0., -1., 1.], &[2., 1., 2.]).unwrap();
+        assert!((integral - 12.).abs() < 1e-12);
+        let reversed = linear.integrate(&[2., -1., 1.], &[0., 1., 2.]).unwrap();
+        assert!((reversed + 12.).abs() < 1e-12);
+        // integral over a box smaller than a cell
+        let integral = linear
+            .integrate(&[0.1, 0.2, 1.2], &[0.3, 0.4, 1.7])
+            .unwrap();
+        let expected = 0.2 * 0.2 * 0.5 * f(0.2, 0.3, 1.45);
+        assert!((integral - expected).abs() < 1e-12);
+        assert!(linear.integrate(&[0., -1., 1.], &[2., 1., 3.]).is_err());
+    }
+}
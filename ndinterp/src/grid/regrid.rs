@@ -0,0 +1,199 @@
+//! Resampling of interpolators onto a new grid
+//!
+//! Any interpolator can be evaluated at all the nodes of a new set of axes, producing a new
+//! [`Grid`] which can then be used with any of the grid interpolation algorithms.
+//!
+//! Nodes for which the interpolator fails (i.e., the node lies outside of the region covered by
+//! the original interpolator) are filled with `NaN` and reported back to the caller.
+
+use crate::grid::{DimensionHelper, Grid, ToDimension};
+use crate::interpolate::Interpolator;
+use itertools::izip;
+use ndarray::{Array, IxDyn};
+use rayon::prelude::*;
+
+/// The result of resampling an interpolator onto a new grid
+#[derive(Debug)]
+pub struct Regridded<const D: usize>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    /// The new grid, the values of the nodes listed in `extrapolated` are `NaN`
+    pub grid: Grid<D>,
+
+    /// Indices of the nodes of the new grid that would have required extrapolation
+    pub extrapolated: Vec<[usize; D]>,
+}
+
+/// Converts the index of a node in the flattened (row-major) grid into one index per axis
+fn unravel_index<const D: usize>(mut flat: usize, shape: &[usize; D]) -> [usize; D] {
+    let mut index = [0; D];
+    for (i, &n) in index.iter_mut().zip(shape).rev() {
+        *i = flat % n;
+        flat /= n;
+    }
+    index
+}
+
+/// Evaluates the interpolator at the node `flat` of the (flattened) new grid
+fn evaluate_node<const D: usize, I>(
+    interpolator: &I,
+    xgrid: &[Vec<f64>; D],
+    shape: &[usize; D],
+    flat: usize,
+) -> Result<f64, [usize; D]>
+where
+    I: for<'a> Interpolator<&'a [f64]>,
+{
+    let index = unravel_index(flat, shape);
+    let mut node = [0.0; D];
+    for (x, &i, axis) in izip!(&mut node, &index, xgrid) {
+        *x = axis[i];
+    }
+    interpolator.interpolate(&node).map_err(|_| index)
+}
+
+/// Collects the results of [`evaluate_node`] into a new grid
+fn collect_grid<const D: usize>(
+    xgrid: [Vec<f64>; D],
+    shape: [usize; D],
+    results: Vec<Result<f64, [usize; D]>>,
+) -> Regridded<D>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    let mut extrapolated = Vec::new();
+    let values = results
+        .into_iter()
+        .map(|res| {
+            res.unwrap_or_else(|index| {
+                extrapolated.push(index);
+                f64::NAN
+            })
+        })
+        .collect();
+
+    // the shape is given by the axes themselves, so neither conversion can fail
+    let values = Array::from_shape_vec(IxDyn(&shape), values)
+        .unwrap()
+        .into_dimensionality()
+        .unwrap();
+
+    Regridded {
+        grid: Grid {
+            xgrid: xgrid.into(),
+            values,
+        },
+        extrapolated,
+    }
+}
+
+/// Evaluates `interpolator` at every node of the grid defined by the axes `xgrid` and returns
+/// the resulting grid. Nodes are visited in row-major order, which is also the order of the
+/// indices in [`Regridded::extrapolated`].
+pub fn regrid<const D: usize, I>(interpolator: &I, xgrid: [Vec<f64>; D]) -> Regridded<D>
+where
+    DimensionHelper<D>: ToDimension,
+    I: for<'a> Interpolator<&'a [f64]>,
+{
+    let shape = xgrid.each_ref().map(Vec::len);
+    let results = (0..shape.iter().product())
+        .map(|flat| evaluate_node(interpolator, &xgrid, &shape, flat))
+        .collect();
+
+    collect_grid(xgrid, shape, results)
+}
+
+/// Parallel version of [`regrid`], the nodes are evaluated using the `rayon` thread pool.
+/// The result is identical to the one of [`regrid`].
+pub fn par_regrid<const D: usize, I>(interpolator: &I, xgrid: [Vec<f64>; D]) -> Regridded<D>
+where
+    DimensionHelper<D>: ToDimension,
+    I: for<'a> Interpolator<&'a [f64]> + Sync,
+{
+    let shape = xgrid.each_ref().map(Vec::len);
+    let results = (0..shape.iter().product())
+        .into_par_iter()
+        .map(|flat| evaluate_node(interpolator, &xgrid, &shape, flat))
+        .collect();
+
+    collect_grid(xgrid, shape, results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::cubic::Cubic;
+    use ndarray::{array, Array2};
+
+    fn gen_cubic2d() -> Cubic<2> {
+        let x1 = vec![0., 0.5, 1.5, 2., 3.];
+        let x2 = vec![-1., 0., 1., 2.5];
+        let values = Array2::from_shape_fn((x1.len(), x2.len()), |(i, j)| {
+            x1[i] * x1[i] - 2. * x2[j] + x1[i] * x2[j]
+        });
+
+        Cubic {
+            grid: Grid {
+                xgrid: vec![x1, x2],
+                values,
+            },
+        }
+    }
+
+    #[test]
+    fn check_regrid_same_nodes() {
+        let cubic = gen_cubic2d();
+        let xgrid = [cubic.grid.xgrid[0].clone(), cubic.grid.xgrid[1].clone()];
+        let result = regrid(&cubic, xgrid);
+
+        assert!(result.extrapolated.is_empty());
+        assert_eq!(result.grid.xgrid, cubic.grid.xgrid);
+        for (new, old) in result.grid.values.iter().zip(&cubic.grid.values) {
+            assert!((new - old).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn check_regrid_extrapolation() {
+        let cubic = Cubic {
+            grid: Grid {
+                xgrid: vec![vec![0., 1., 2., 3., 4.]],
+                values: array![4., 3., 2., 1., 1.],
+            },
+        };
+        let result = regrid(&cubic, [vec![-1., 0.5, 2.5, 5.]]);
+
+        assert_eq!(result.extrapolated, vec![[0], [3]]);
+        assert!(result.grid.values[0].is_nan());
+        assert!(result.grid.values[3].is_nan());
+        assert_eq!(
+            result.grid.values[1],
+            Interpolator::<f64>::interpolate(&cubic, 0.5).unwrap()
+        );
+        assert_eq!(
+            result.grid.values[2],
+            Interpolator::<f64>::interpolate(&cubic, 2.5).unwrap()
+        );
+    }
+
+    #[test]
+    fn check_par_regrid() {
+        let cubic = gen_cubic2d();
+        let xgrid = [
+            (0..40)
+                .map(|i| -0.25 + 0.125 * i as f64)
+                .collect::<Vec<_>>(),
+            (0..30).map(|i| -1.0 + 0.125 * i as f64).collect(),
+        ];
+        let serial = regrid(&cubic, xgrid.clone());
+        let parallel = par_regrid(&cubic, xgrid);
+
+        assert_eq!(serial.extrapolated, parallel.extrapolated);
+        assert_eq!(serial.extrapolated[0], [0, 0]);
+        assert_eq!(serial.extrapolated.len(), 40 * 30 - 25 * 29);
+        for (s, p) in serial.grid.values.iter().zip(&parallel.grid.values) {
+            assert!(s.to_bits() == p.to_bits());
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Serialization of grids through `serde`
+//!
+//! Grids are not serialized field by field: they go through [`GridRepr`], an explicitly
+//! versioned layout which is independent of the internal representation of [`Grid`] (and of the
+//! one of `ndarray`). Every change of the layout must bump [`FORMAT_VERSION`] and keep the older
+//! versions readable. Ensembles go through [`EnsembleRepr`] in the same way.
+
+use crate::grid::ensemble::{Ensemble, ErrorType};
+use crate::grid::{DimensionHelper, Grid, GridBase, ToDimension};
+use ndarray::{Array, ArrayBase, Data, Dimension, IxDyn};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+
+/// Version of the layout written by this version of the library
+pub(crate) const FORMAT_VERSION: u32 = 1;
+
+/// Serialized layout of a grid, version 1:
+///     - `xgrid`: the input vectors, one per dimension
+///     - `shape`: the shape of the values
+///     - `values`: the values of the grid, flattened in row-major order
+#[derive(Deserialize, Serialize)]
+struct GridRepr<'a> {
+    version: u32,
+    xgrid: Cow<'a, [Vec<f64>]>,
+    shape: Cow<'a, [usize]>,
+    values: Cow<'a, [f64]>,
+}
+
+/// Serialized layout of an ensemble, version 1:
+///     - `xgrid`: the input vectors, one per dimension
+///     - `shape`: the shape of the values, starting with the number of members
+///     - `values`: the values of all the members, flattened in row-major order
+///     - `error_type`: how the uncertainties are computed
+#[derive(Deserialize, Serialize)]
+struct EnsembleRepr<'a> {
+    version: u32,
+    xgrid: Cow<'a, [Vec<f64>]>,
+    shape: Cow<'a, [usize]>,
+    values: Cow<'a, [f64]>,
+    error_type: ErrorType,
+}
+
+/// Checks that data in the layout `version` can be read
+pub(crate) fn check_version<E: Error>(version: u32) -> Result<(), E> {
+    if !(1..=FORMAT_VERSION).contains(&version) {
+        return Err(E::custom(format!(
+            "unknown format version {version}, the supported versions are 1 to \
+             {FORMAT_VERSION}"
+        )));
+    }
+    Ok(())
+}
+
+/// The values of an array in row-major order, borrowed if they are already in that order
+fn flatten<S, D>(values: &ArrayBase<S, D>) -> Cow<'_, [f64]>
+where
+    S: Data<Elem = f64>,
+    D: Dimension,
+{
+    match values.as_slice() {
+        Some(values) => Cow::Borrowed(values),
+        None => Cow::Owned(values.iter().copied().collect()),
+    }
+}
+
+impl<S, const D: usize> Serialize for GridBase<S, D>
+where
+    S: Data<Elem = f64>,
+    DimensionHelper<D>: ToDimension,
+{
+    fn serialize<Se: Serializer>(&self, serializer: Se) -> Result<Se::Ok, Se::Error> {
+        GridRepr {
+            version: FORMAT_VERSION,
+            xgrid: Cow::Borrowed(&self.xgrid),
+            shape: Cow::Borrowed(self.values.shape()),
+            values: flatten(&self.values),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, const D: usize> Deserialize<'de> for Grid<D>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let repr = GridRepr::deserialize(deserializer)?;
+
+        check_version(repr.version)?;
+
+        if repr.xgrid.len() != D {
+            return Err(De::Error::invalid_length(
+                repr.xgrid.len(),
+                &"one axis per dimension",
+            ));
+        }
+
+        if repr
+            .xgrid
+            .iter()
+            .map(Vec::len)
+            .ne(repr.shape.iter().copied())
+        {
+            return Err(De::Error::custom(
+                "the shape of the values does not match the length of the axes",
+            ));
+        }
+
+        let values = Array::from_shape_vec(IxDyn(&repr.shape), repr.values.into_owned())
+            .map_err(De::Error::custom)?
+            .into_dimensionality()
+            .map_err(De::Error::custom)?;
+
+        Ok(Grid {
+            xgrid: repr.xgrid.into_owned(),
+            values,
+        })
+    }
+}
+
+impl<const D: usize> Serialize for Ensemble<D>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    fn serialize<Se: Serializer>(&self, serializer: Se) -> Result<Se::Ok, Se::Error> {
+        EnsembleRepr {
+            version: FORMAT_VERSION,
+            xgrid: Cow::Borrowed(&self.xgrid),
+            shape: Cow::Borrowed(self.values.shape()),
+            values: flatten(&self.values),
+            error_type: self.error_type,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, const D: usize> Deserialize<'de> for Ensemble<D>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let repr = EnsembleRepr::deserialize(deserializer)?;
+
+        check_version(repr.version)?;
+
+        if repr.shape.len() != D + 1 {
+            return Err(De::Error::invalid_length(
+                repr.shape.len(),
+                &"the number of members followed by one axis per dimension",
+            ));
+        }
+
+        let values = Array::from_shape_vec(IxDyn(&repr.shape), repr.values.into_owned())
+            .map_err(De::Error::custom)?
+            .into_dimensionality()
+            .map_err(De::Error::custom)?;
+
+        Ensemble::new(repr.xgrid.into_owned(), values, repr.error_type).map_err(De::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::cubic::{Cubic, Interpolator};
+    use ndarray::array;
+
+    #[test]
+    fn check_roundtrip() {
+        let cubic = Cubic::<2> {
+            grid: Grid {
+                xgrid: vec![vec![0., 1., 2.], vec![0., 1., 2., 3.]],
+                values: array![[1., 2., 3., 4.], [2., 3., 4., 5.], [4., 5., 6., 8.]],
+            },
+        };
+
+        let json = serde_json::to_string(&cubic).unwrap();
+        let loaded: Cubic<2> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.grid.xgrid, cubic.grid.xgrid);
+        assert_eq!(loaded.grid.values, cubic.grid.values);
+        assert_eq!(
+            loaded.interpolate(&[0.5, 2.5][..]).unwrap(),
+            cubic.interpolate(&[0.5, 2.5][..]).unwrap()
+        );
+    }
+
+    #[test]
+    fn check_transposed_values() {
+        let grid = Grid::<2> {
+            xgrid: vec![vec![0., 1., 2.], vec![0., 1.]],
+            values: array![[1., 2., 3.], [4., 5., 6.]].reversed_axes(),
+        };
+
+        let json = serde_json::to_string(&grid).unwrap();
+        let loaded: Grid<2> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.values, grid.values);
+    }
+
+    #[test]
+    fn check_version_1() {
+        // data written with version 1 of the layout must stay readable
+        let json = r#"{"version":1,"xgrid":[[0.0,1.0,2.0]],"shape":[3],"values":[4.0,3.0,1.0]}"#;
+        let grid: Grid<1> = serde_json::from_str(json).unwrap();
+
+        assert_eq!(grid.xgrid, vec![vec![0., 1., 2.]]);
+        assert_eq!(grid.values, array![4., 3., 1.]);
+    }
+
+    #[test]
+    fn check_invalid_data() {
+        let newer = r#"{"version":2,"xgrid":[[0.0,1.0]],"shape":[2],"values":[4.0,3.0]}"#;
+        assert!(serde_json::from_str::<Grid<1>>(newer).is_err());
+        let unknown = r#"{"version":0,"xgrid":[[0.0,1.0]],"shape":[2],"values":[4.0,3.0]}"#;
+        assert!(serde_json::from_str::<Grid<1>>(unknown).is_err());
+
+        let wrong_dim = r#"{"version":1,"xgrid":[[0.0,1.0]],"shape":[2],"values":[4.0,3.0]}"#;
+        assert!(serde_json::from_str::<Grid<2>>(wrong_dim).is_err());
+
+        let wrong_shape = r#"{"version":1,"xgrid":[[0.0,1.0]],"shape":[3],"values":[4.0,3.0]}"#;
+        assert!(serde_json::from_str::<Grid<1>>(wrong_shape).is_err());
+
+        let wrong_size = r#"{"version":1,"xgrid":[[0.0,1.0]],"shape":[2],"values":[4.0]}"#;
+        assert!(serde_json::from_str::<Grid<1>>(wrong_size).is_err());
+    }
+
+    #[test]
+    fn check_ensemble() {
+        let xgrid = vec![vec![0., 1., 2., 3.]];
+        let values = array![[1., 2., 3., 5.], [2., 3., 4., 6.], [0., 1., 2., 4.]];
+        let ensemble = Ensemble::<1>::new(xgrid, values, ErrorType::Hessian).unwrap();
+
+        let json = serde_json::to_string(&ensemble).unwrap();
+        let loaded: Ensemble<1> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.xgrid, ensemble.xgrid);
+        assert_eq!(loaded.values, ensemble.values);
+        assert_eq!(loaded.error_type, ErrorType::Hessian);
+        assert_eq!(
+            loaded.uncertainty(&[1.5]).unwrap(),
+            ensemble.uncertainty(&[1.5]).unwrap()
+        );
+
+        // data written with version 1 of the layout must stay readable
+        let json = r#"{"version":1,"xgrid":[[0.0,1.0]],"shape":[1,2],"values":[4.0,3.0],
+            "error_type":"Replicas"}"#;
+        let ensemble: Ensemble<1> = serde_json::from_str(json).unwrap();
+        assert_eq!(ensemble.values, array![[4., 3.]]);
+
+        // the checks of the constructor apply
+        let even = r#"{"version":1,"xgrid":[[0.0,1.0]],"shape":[2,2],"values":[4.0,3.0,1.0,2.0],
+            "error_type":"Hessian"}"#;
+        assert!(serde_json::from_str::<Ensemble<1>>(even).is_err());
+        let wrong_dim = r#"{"version":1,"xgrid":[[0.0,1.0]],"shape":[2],"values":[4.0,3.0],
+            "error_type":"Replicas"}"#;
+        assert!(serde_json::from_str::<Ensemble<1>>(wrong_dim).is_err());
+    }
+}
@@ -11,6 +11,31 @@ pub enum InterpolationError {
     /// Raised when the queried value is below the minimum
     #[error("The value queried ({0}) is below the minimum")]
     ExtrapolationBelow(f64),
+
+    /// Raised when the input vectors and values do not form a valid grid
+    #[error("Invalid grid: {0}")]
+    InvalidGrid(String),
+
+    /// Raised when the interpolator is not configured to answer a query, independently of its
+    /// data, e.g. because its finder of the neighbors is not set or finds none
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(String),
+
+    /// Raised when a query does not match the interpolator, e.g. because its number of
+    /// coordinates differs from the dimension of the data
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+}
+
+/// Checks that `query` has `dim` coordinates
+pub(crate) fn check_query(query: &[f64], dim: usize) -> Result<(), InterpolationError> {
+    if query.len() != dim {
+        return Err(InterpolationError::InvalidQuery(format!(
+            "the query has dimension {}, instead of {dim}",
+            query.len()
+        )));
+    }
+    Ok(())
 }
 
 /// Methods which all interpolator must implement
@@ -19,31 +44,17 @@ pub trait Interpolator<T> {
     fn interpolate(&self, query: T) -> Result<f64, InterpolationError>;
 }
 
-///// ---- deal with the stuff below later ----
-//pub trait Interpolate {
-//    type Point: Metric;
-//
-//    fn interpolate(&self, query: &Self::Point) -> f64;
-//}
-//
-//pub struct Input<Point: Metric> {
-//    pub point: Point,
-//    pub value: f64,
-//}
-//
-//impl<Point: Metric> From<(Point, f64)> for Input<Point> {
-//    fn from(item: (Point, f64)) -> Self {
-//        Self {
-//            point: item.0,
-//            value: item.1,
-//        }
-//    }
-//}
-//
-//impl<Point: Metric> Input<Point> {
-//    pub fn stack(points: Vec<Point>, values: Vec<f64>) -> Vec<Self> {
-//        zip(points.into_iter(), values.into_iter())
-//            .map(|t| t.into())
-//            .collect()
-//    }
-//}
+/// Interpolators which can also compute the derivatives of the interpolated function
+pub trait Differentiate<T>: Interpolator<T> {
+    /// Computes the interpolated value at `query`, storing its partial derivatives with respect to
+    /// each of the inputs in `gradient`
+    fn value_and_gradient(&self, query: T, gradient: &mut [f64])
+        -> Result<f64, InterpolationError>;
+}
+
+/// Interpolators which can also compute definite integrals of the interpolated function
+pub trait Integrate<T> {
+    /// Integrates the interpolated function over the box with corners `lower` and `upper`, which
+    /// must lie within the grid. Swapping the bounds along one axis flips the sign of the result
+    fn integrate(&self, lower: T, upper: T) -> Result<f64, InterpolationError>;
+}
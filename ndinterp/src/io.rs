@@ -0,0 +1,51 @@
+//! Import and export of grids from and to file formats produced by other tools
+
+pub mod binary;
+pub mod lhapdf;
+pub mod npy;
+
+#[cfg(test)]
+pub(crate) use tests::TempPath;
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::ops::Deref;
+    use std::path::{Path, PathBuf};
+
+    /// Path in the temporary directory, unique to the test process, that is removed on drop
+    /// together with whatever the test created there, even when the test fails
+    pub(crate) struct TempPath(PathBuf);
+
+    impl TempPath {
+        pub(crate) fn new(name: &str) -> Self {
+            Self(env::temp_dir().join(format!("ndinterp-{}-{name}", std::process::id())))
+        }
+    }
+
+    impl Deref for TempPath {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempPath {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            // the test may have failed before creating anything, so errors are ignored
+            let _ = if self.0.is_dir() {
+                fs::remove_dir_all(&self.0)
+            } else {
+                fs::remove_file(&self.0)
+            };
+        }
+    }
+}
@@ -0,0 +1,495 @@
+//! Compact binary format for grids
+//!
+//! The format is designed such that the values can be used directly from a memory-mapped file,
+//! without parsing nor copying them, see [`MappedGrid`]. Opening a file only reads and checks its
+//! header, which makes it cheap to open a large number of grids (e.g., all the members of a PDF
+//! set) at the same time.
+//!
+//! All numbers are stored in little-endian byte order, the layout (version 1) is:
+//!
+//! | offset | size        | content                                                  |
+//! |--------|-------------|----------------------------------------------------------|
+//! | 0      | 8           | the magic string `NDINTERP`                              |
+//! | 8      | 4           | version of the format (`u32`)                            |
+//! | 12     | 4           | number of dimensions `D` (`u32`)                         |
+//! | 16     | 8           | offset of the values (`u64`), a multiple of 64           |
+//! | 24     | 4           | CRC32 checksum of the header, excluding the checksums    |
+//! | 28     | 4           | CRC32 checksum of the values                             |
+//! | 32     | 8 D         | number of points along each dimension (`u64`)            |
+//! |        |             | metadata: number of entries (`u32`) followed by the keys |
+//! |        |             | and values, each as length (`u32`) and UTF-8 string      |
+//! |        | 8 sum(size) | the input vectors, one after the other (`f64`)           |
+//! |        |             | zero padding up to the offset of the values              |
+//! |        | 8 prod(size)| the values in row-major order (`f64`)                    |
+
+use crate::grid::{DimensionHelper, Grid, GridBase, GridView, ToDimension};
+use crate::interpolate::InterpolationError;
+use itertools::Itertools;
+use memmap2::Mmap;
+use ndarray::{Array, ArrayView, Data, IxDyn};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use thiserror::Error;
+
+/// Every file starts with this string
+const MAGIC: &[u8; 8] = b"NDINTERP";
+
+/// Version of the layout written by this version of the library
+const FORMAT_VERSION: u32 = 1;
+
+/// The values start at an offset which is a multiple of this size
+const ALIGNMENT: usize = 64;
+
+/// Position of the checksums in the header
+const CHECKSUMS: Range<usize> = 24..32;
+
+/// Metadata attached to a grid, as key-value pairs
+pub type Metadata = BTreeMap<String, String>;
+
+/// Errors encountered while reading or writing grid files
+#[derive(Debug, Error)]
+pub enum BinaryError {
+    /// Raised when reading from or writing to the underlying file fails
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// Raised when the content of the file does not follow the format
+    #[error("Invalid grid file: {0}")]
+    InvalidFile(String),
+
+    /// Raised for files written by a newer version of the library
+    #[error("Unsupported grid file version {0}")]
+    UnsupportedVersion(u32),
+
+    /// Raised when the content of the file does not match its checksum
+    #[error("Checksum mismatch in the {0} of the grid file")]
+    ChecksumMismatch(&'static str),
+
+    /// Raised when the content of the file does not form a valid grid
+    #[error(transparent)]
+    Grid(#[from] InterpolationError),
+}
+
+/// Position of the parts of a grid file and content of its header
+struct Layout<const D: usize> {
+    xgrid: Vec<Vec<f64>>,
+    metadata: Metadata,
+    shape: [usize; D],
+    values: Range<usize>,
+    values_crc: u32,
+}
+
+/// Reads the fields of the header one after the other
+struct Fields<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BinaryError> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.pos..end))
+            .ok_or_else(|| BinaryError::InvalidFile("the file is truncated".to_owned()))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, BinaryError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn usize(&mut self) -> Result<usize, BinaryError> {
+        let value = u64::from_le_bytes(self.take(8)?.try_into().unwrap());
+        usize::try_from(value)
+            .map_err(|_| BinaryError::InvalidFile(format!("size {value} is too large")))
+    }
+
+    fn string(&mut self) -> Result<String, BinaryError> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| BinaryError::InvalidFile("the metadata is not valid UTF-8".to_owned()))
+    }
+}
+
+fn decode(bytes: &[u8]) -> Vec<f64> {
+    bytes
+        .chunks_exact(8)
+        .map(|c| f64::from_le_bytes(c.try_into().unwrap()))
+        .collect()
+}
+
+fn values_len(shape: &[usize]) -> Result<usize, BinaryError> {
+    shape
+        .iter()
+        .try_fold(8_usize, |len, &n| len.checked_mul(n))
+        .ok_or_else(|| BinaryError::InvalidFile("the grid is too large".to_owned()))
+}
+
+/// Reads and checks the header, `bytes` must contain at least the header, but not necessarily
+/// the values
+fn parse_layout<const D: usize>(bytes: &[u8]) -> Result<Layout<D>, BinaryError> {
+    let mut fields = Fields { bytes, pos: 0 };
+
+    if fields.take(MAGIC.len())? != MAGIC {
+        return Err(BinaryError::InvalidFile("not a grid file".to_owned()));
+    }
+    let version = fields.u32()?;
+    if version > FORMAT_VERSION {
+        return Err(BinaryError::UnsupportedVersion(version));
+    }
+    let ndim = fields.u32()? as usize;
+    if ndim != D {
+        return Err(InterpolationError::InvalidGrid(format!(
+            "the file contains a {ndim}-dimensional grid instead of a {D}-dimensional one"
+        ))
+        .into());
+    }
+    let offset = fields.usize()?;
+    let header_crc = fields.u32()?;
+    let values_crc = fields.u32()?;
+
+    let mut shape = [0; D];
+    for n in &mut shape {
+        *n = fields.usize()?;
+    }
+
+    let mut metadata = Metadata::new();
+    for _ in 0..fields.u32()? {
+        let key = fields.string()?;
+        metadata.insert(key, fields.string()?);
+    }
+
+    let mut xgrid = Vec::with_capacity(D);
+    for &n in &shape {
+        xgrid.push(decode(fields.take(values_len(&[n])?)?));
+    }
+
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&bytes[..CHECKSUMS.start]);
+    hasher.update(&bytes[CHECKSUMS.end..fields.pos]);
+    if hasher.finalize() != header_crc {
+        return Err(BinaryError::ChecksumMismatch("header"));
+    }
+
+    let end = offset.checked_add(values_len(&shape)?);
+    let end = match end {
+        Some(end) if offset >= fields.pos && offset.is_multiple_of(ALIGNMENT) => end,
+        _ => {
+            return Err(BinaryError::InvalidFile(format!(
+                "invalid offset of the values {offset}"
+            )))
+        }
+    };
+
+    Ok(Layout {
+        xgrid,
+        metadata,
+        shape,
+        values: offset..end,
+        values_crc,
+    })
+}
+
+fn check_values(bytes: &[u8], layout_crc: u32) -> Result<(), BinaryError> {
+    if crc32fast::hash(bytes) == layout_crc {
+        Ok(())
+    } else {
+        Err(BinaryError::ChecksumMismatch("values"))
+    }
+}
+
+/// Writes the grid together with its metadata
+pub fn write_grid<S, const D: usize, W>(
+    grid: &GridBase<S, D>,
+    metadata: &Metadata,
+    mut writer: W,
+) -> Result<(), BinaryError>
+where
+    S: Data<Elem = f64>,
+    DimensionHelper<D>: ToDimension,
+    W: Write,
+{
+    let mut header = Vec::new();
+    header.extend(MAGIC);
+    header.extend(FORMAT_VERSION.to_le_bytes());
+    header.extend((D as u32).to_le_bytes());
+    // the offset of the values and the checksums are filled in below
+    header.extend([0; 16]);
+    for n in grid.values.shape() {
+        header.extend((*n as u64).to_le_bytes());
+    }
+    header.extend((metadata.len() as u32).to_le_bytes());
+    for string in metadata.iter().flat_map(|(k, v)| [k, v]) {
+        header.extend((string.len() as u32).to_le_bytes());
+        header.extend(string.as_bytes());
+    }
+    for x in grid.xgrid.iter().flatten() {
+        header.extend(x.to_le_bytes());
+    }
+
+    let offset = header.len().next_multiple_of(ALIGNMENT);
+    header[16..CHECKSUMS.start].copy_from_slice(&(offset as u64).to_le_bytes());
+
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&header[..CHECKSUMS.start]);
+    hasher.update(&header[CHECKSUMS.end..]);
+    header[CHECKSUMS.start..CHECKSUMS.start + 4].copy_from_slice(&hasher.finalize().to_le_bytes());
+
+    let mut hasher = crc32fast::Hasher::new();
+    for value in &grid.values {
+        hasher.update(&value.to_le_bytes());
+    }
+    header[CHECKSUMS.start + 4..CHECKSUMS.end].copy_from_slice(&hasher.finalize().to_le_bytes());
+
+    header.resize(offset, 0);
+    writer.write_all(&header)?;
+
+    // iterating over the array always visits the elements in row-major order
+    let mut buffer = Vec::with_capacity(8 * 1024);
+    for chunk in &grid.values.iter().chunks(1024) {
+        buffer.clear();
+        buffer.extend(chunk.flat_map(|v| v.to_le_bytes()));
+        writer.write_all(&buffer)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a grid together with its metadata, checking the integrity of the whole file
+pub fn read_grid<const D: usize, R>(mut reader: R) -> Result<(Grid<D>, Metadata), BinaryError>
+where
+    DimensionHelper<D>: ToDimension,
+    R: Read,
+{
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    let layout = parse_layout::<D>(&bytes)?;
+    let values = bytes
+        .get(layout.values.clone())
+        .ok_or_else(|| BinaryError::InvalidFile("the file is truncated".to_owned()))?;
+    check_values(values, layout.values_crc)?;
+
+    // the length of the values is given by the shape, so this cannot fail
+    let values = Array::from_shape_vec(IxDyn(&layout.shape), decode(values))
+        .unwrap()
+        .into_dimensionality()
+        .unwrap();
+
+    Ok((Grid::new(layout.xgrid, values)?, layout.metadata))
+}
+
+/// A grid file mapped into memory
+///
+/// The values are never copied, [`MappedGrid::view`] returns a grid reading them straight from
+/// the mapped file. Only the header is checked when opening the file, the integrity of the
+/// values can be checked with [`MappedGrid::verify`].
+///
+/// The values are stored in little-endian byte order, therefore this is only available on
+/// little-endian platforms.
+#[cfg(target_endian = "little")]
+pub struct MappedGrid<const D: usize> {
+    mmap: Mmap,
+    xgrid: Vec<Vec<f64>>,
+    metadata: Metadata,
+    shape: [usize; D],
+    values: Range<usize>,
+    values_crc: u32,
+}
+
+#[cfg(target_endian = "little")]
+impl<const D: usize> MappedGrid<D>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    /// Maps the file at `path` into memory and checks its header
+    ///
+    /// As for any memory-mapped file, the file must not be modified while it is open, otherwise
+    /// the values read from the grid are unspecified.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, BinaryError> {
+        let file = File::open(path)?;
+        // SAFETY: the mapped memory is only ever read as plain bytes or `f64`, for which every bit
+        // pattern is valid; the requirement of not modifying the file is documented above
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let layout = parse_layout::<D>(&mmap)?;
+        if layout.values.end > mmap.len() {
+            return Err(BinaryError::InvalidFile("the file is truncated".to_owned()));
+        }
+
+        let mut grid = Self {
+            mmap,
+            xgrid: Vec::new(),
+            metadata: layout.metadata,
+            shape: layout.shape,
+            values: layout.values,
+            values_crc: layout.values_crc,
+        };
+
+        // the mapping starts at a page boundary, so this only fails if the offset was not aligned
+        if !(grid.mmap[grid.values.clone()].as_ptr() as usize)
+            .is_multiple_of(std::mem::align_of::<f64>())
+        {
+            return Err(BinaryError::InvalidFile(
+                "the values are not aligned".to_owned(),
+            ));
+        }
+
+        grid.xgrid = GridBase::new(layout.xgrid, grid.values_view())?.xgrid;
+
+        Ok(grid)
+    }
+
+    fn values_view(&self) -> ArrayView<'_, f64, <DimensionHelper<D> as ToDimension>::Dim> {
+        let bytes = &self.mmap[self.values.clone()];
+        // SAFETY: `open` checked that the bytes are within the mapping and aligned for `f64`
+        let values =
+            unsafe { std::slice::from_raw_parts(bytes.as_ptr().cast::<f64>(), bytes.len() / 8) };
+
+        // the length of the values is given by the shape, so this cannot fail
+        ArrayView::from_shape(IxDyn(&self.shape), values)
+            .unwrap()
+            .into_dimensionality()
+            .unwrap()
+    }
+
+    /// Returns a grid reading the values from the mapped file
+    pub fn view(&self) -> GridView<'_, D> {
+        GridBase {
+            xgrid: self.xgrid.clone(),
+            values: self.values_view(),
+        }
+    }
+
+    /// Metadata stored in the file
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Checks the integrity of the values against the checksum stored in the file. This reads
+    /// every value, use it sparingly on large grids
+    pub fn verify(&self) -> Result<(), BinaryError> {
+        check_values(&self.mmap[self.values.clone()], self.values_crc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::cubic::{Cubic, Interpolator};
+    use crate::io::TempPath;
+    use ndarray::Array2;
+
+    fn gen_grid() -> Grid<2> {
+        let x1 = vec![0., 0.5, 1.5, 2., 3.];
+        let x2 = vec![-1., 0., 1., 2.5];
+        let values = Array2::from_shape_fn((x1.len(), x2.len()), |(i, j)| {
+            x1[i] * x1[i] - 2. * x2[j] + x1[i] * x2[j]
+        });
+
+        Grid::new(vec![x1, x2], values).unwrap()
+    }
+
+    fn gen_metadata() -> Metadata {
+        Metadata::from([
+            ("SetDesc".to_owned(), "Test grid".to_owned()),
+            ("Member".to_owned(), "0".to_owned()),
+        ])
+    }
+
+    #[test]
+    fn check_roundtrip() {
+        let grid = gen_grid();
+        let mut bytes = Vec::new();
+        write_grid(&grid, &gen_metadata(), &mut bytes).unwrap();
+
+        assert_eq!(&bytes[..8], MAGIC);
+        let offset = u64::from_le_bytes(bytes[16..24].try_into().unwrap());
+        assert_eq!(bytes.len() as u64, offset + 20 * 8);
+
+        let (loaded, metadata) = read_grid::<2, _>(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.xgrid, grid.xgrid);
+        assert_eq!(loaded.values, grid.values);
+        assert_eq!(metadata, gen_metadata());
+
+        // the dimension is part of the format
+        assert!(matches!(
+            read_grid::<1, _>(bytes.as_slice()),
+            Err(BinaryError::Grid(_))
+        ));
+    }
+
+    #[test]
+    fn check_corruption() {
+        let mut bytes = Vec::new();
+        write_grid(&gen_grid(), &gen_metadata(), &mut bytes).unwrap();
+
+        let mut corrupted = bytes.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            read_grid::<2, _>(corrupted.as_slice()),
+            Err(BinaryError::ChecksumMismatch("values"))
+        ));
+
+        let mut corrupted = bytes.clone();
+        corrupted[40] ^= 1;
+        assert!(matches!(
+            read_grid::<2, _>(corrupted.as_slice()),
+            Err(BinaryError::ChecksumMismatch("header"))
+        ));
+
+        let truncated = &bytes[..bytes.len() - 8];
+        assert!(matches!(
+            read_grid::<2, _>(truncated),
+            Err(BinaryError::InvalidFile(_))
+        ));
+
+        // an offset so large that the end of the values overflows, with a valid checksum
+        let header_len = 52
+            + gen_metadata()
+                .iter()
+                .map(|(k, v)| 8 + k.len() + v.len())
+                .sum::<usize>()
+            + 9 * 8;
+        let mut corrupted = bytes.clone();
+        let offset = usize::MAX - (ALIGNMENT - 1);
+        corrupted[16..CHECKSUMS.start].copy_from_slice(&(offset as u64).to_le_bytes());
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&corrupted[..CHECKSUMS.start]);
+        hasher.update(&corrupted[CHECKSUMS.end..header_len]);
+        corrupted[CHECKSUMS.start..CHECKSUMS.start + 4]
+            .copy_from_slice(&hasher.finalize().to_le_bytes());
+        assert!(matches!(
+            read_grid::<2, _>(corrupted.as_slice()),
+            Err(BinaryError::InvalidFile(message)) if message.contains("offset")
+        ));
+    }
+
+    #[test]
+    fn check_mapped_grid() {
+        let grid = gen_grid();
+        let path = TempPath::new("mapped.bin");
+        write_grid(&grid, &gen_metadata(), File::create(&path).unwrap()).unwrap();
+
+        let mapped = MappedGrid::<2>::open(&path).unwrap();
+        mapped.verify().unwrap();
+        assert_eq!(mapped.metadata(), &gen_metadata());
+
+        let view = mapped.view();
+        assert_eq!(view.xgrid, grid.xgrid);
+        assert_eq!(view.values, grid.values);
+
+        let query = [1.2, 0.3];
+        let from_file = Cubic { grid: view };
+        let from_memory = Cubic { grid };
+        assert_eq!(
+            from_file.interpolate(&query[..]).unwrap(),
+            from_memory.interpolate(&query[..]).unwrap()
+        );
+    }
+}
@@ -0,0 +1,541 @@
+//! Reading of PDF sets in the [LHAPDF6](https://lhapdf.hepforge.org) format
+//!
+//! A set is a folder, named after the set, containing a `<name>.info` file with the metadata and
+//! one `<name>_<member>.dat` file per member in the `lhagrid1` format. Sets are looked up by name
+//! in the folders listed in `LHAPDF_DATA_PATH` and `LHAPATH` (see [`data_paths`]), or can be
+//! given directly as a path.
+//!
+//! As done by LHAPDF, PDFs are interpolated with cubic splines in (ln x, ln Q²), and αs in ln Q².
+//! Grids are split into subgrids at flavour thresholds, where a node is repeated: queries exactly
+//! at a threshold use the subgrid above it.
+
+use crate::grid::cubic::Cubic;
+use crate::grid::Grid;
+use crate::interpolate::{InterpolationError, Interpolator};
+use ndarray::{Array1, Array3, Axis};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Folders searched for PDF sets after the ones given by the environment
+const DEFAULT_DATA_PATHS: [&str; 2] = ["/usr/local/share/LHAPDF", "/usr/share/LHAPDF"];
+
+/// Errors encountered while reading LHAPDF sets
+#[derive(Debug, Error)]
+pub enum LhapdfError {
+    /// Raised when reading the files of the set fails
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// Raised when the set is not found in any of the data folders
+    #[error("PDF set '{0}' not found in the LHAPDF data paths")]
+    SetNotFound(String),
+
+    /// Raised when the member requested is not part of the set
+    #[error("member {member} not found, the set has {members} members")]
+    MemberNotFound {
+        /// The member requested
+        member: usize,
+        /// The number of members of the set
+        members: usize,
+    },
+
+    /// Raised when a file of the set cannot be parsed
+    #[error("Invalid LHAPDF file: {0}")]
+    InvalidFile(String),
+
+    /// Raised for files or interpolation types that are not supported
+    #[error("Unsupported LHAPDF feature: {0}")]
+    Unsupported(String),
+
+    /// Raised when the tabulated values do not form a valid grid
+    #[error(transparent)]
+    Grid(#[from] InterpolationError),
+}
+
+/// Returns the folders searched for PDF sets: the ones in `LHAPDF_DATA_PATH`, the ones in the
+/// legacy `LHAPATH`, and finally the usual installation folders
+pub fn data_paths() -> Vec<PathBuf> {
+    ["LHAPDF_DATA_PATH", "LHAPATH"]
+        .iter()
+        .filter_map(env::var_os)
+        .flat_map(|paths| env::split_paths(&paths).collect::<Vec<_>>())
+        .chain(DEFAULT_DATA_PATHS.iter().map(PathBuf::from))
+        .collect()
+}
+
+/// Returns the folder of the set `name`, which is either the path of the folder itself or the name
+/// of a set found in one of the [`data_paths`]
+pub fn find_set(name: &str) -> Result<PathBuf, LhapdfError> {
+    find_set_in(name, &data_paths())
+}
+
+fn find_set_in(name: &str, paths: &[PathBuf]) -> Result<PathBuf, LhapdfError> {
+    let path = Path::new(name);
+    if path.is_dir() {
+        return Ok(path.to_owned());
+    }
+
+    paths
+        .iter()
+        .map(|dir| dir.join(name))
+        .find(|dir| dir.is_dir())
+        .ok_or_else(|| LhapdfError::SetNotFound(name.to_owned()))
+}
+
+/// Top-level entries of the YAML metadata of a set or member
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Info(BTreeMap<String, String>);
+
+impl Info {
+    /// Parses the `key: value` entries of a YAML document; lists may span several lines, while
+    /// nested mappings are ignored
+    pub fn parse(text: &str) -> Self {
+        let mut entries = BTreeMap::new();
+        let mut lines = text.lines();
+
+        while let Some(line) = lines.next() {
+            if line.starts_with([' ', '\t', '#', '-']) {
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+
+            let mut value = value.trim().to_owned();
+            if value.starts_with('[') {
+                while !value.contains(']') {
+                    match lines.next() {
+                        Some(next) => value.push_str(next.trim()),
+                        None => break,
+                    }
+                }
+            }
+            entries.insert(key.trim().to_owned(), value);
+        }
+
+        Self(entries)
+    }
+
+    /// Returns the value of `key`, without quotes
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|value| {
+            value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value)
+        })
+    }
+
+    /// Returns the value of `key`, which must be a list of numbers
+    pub fn get_floats(&self, key: &str) -> Result<Vec<f64>, LhapdfError> {
+        let value = self
+            .get(key)
+            .ok_or_else(|| LhapdfError::InvalidFile(format!("missing entry '{key}'")))?;
+        let list = value
+            .strip_prefix('[')
+            .and_then(|v| v.strip_suffix(']'))
+            .ok_or_else(|| LhapdfError::InvalidFile(format!("entry '{key}' is not a list")))?;
+
+        list.split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| LhapdfError::InvalidFile(format!("entry '{key}' contains '{v}'")))
+            })
+            .collect()
+    }
+
+    /// Merges the entries of `other` into these, overriding the ones already present
+    fn extend(&mut self, other: Info) {
+        self.0.extend(other.0);
+    }
+}
+
+/// A PDF set, whose members are loaded on demand
+#[derive(Clone, Debug)]
+pub struct PdfSet {
+    /// The name of the set
+    pub name: String,
+    /// The folder containing the files of the set
+    pub path: PathBuf,
+    /// The metadata of the set, read from the `.info` file
+    pub info: Info,
+}
+
+impl PdfSet {
+    /// Opens the set `name`, given either as a path or as a name to look up with [`find_set`]
+    pub fn open(name: &str) -> Result<Self, LhapdfError> {
+        let path = find_set(name)?;
+        let name = path
+            .canonicalize()?
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| LhapdfError::SetNotFound(name.to_owned()))?
+            .to_owned();
+        let info = Info::parse(&fs::read_to_string(path.join(format!("{name}.info")))?);
+
+        Ok(Self { name, path, info })
+    }
+
+    /// The number of members of the set, if given in the metadata
+    pub fn num_members(&self) -> Option<usize> {
+        self.info.get("NumMembers")?.parse().ok()
+    }
+
+    fn member_path(&self, member: usize) -> Result<PathBuf, LhapdfError> {
+        match self.num_members() {
+            Some(members) if member >= members => {
+                Err(LhapdfError::MemberNotFound { member, members })
+            }
+            _ => Ok(self.path.join(format!("{}_{member:04}.dat", self.name))),
+        }
+    }
+
+    /// The metadata of `member`: the entries of the set overridden by the ones in the header of
+    /// the member file
+    pub fn member_info(&self, member: usize) -> Result<Info, LhapdfError> {
+        let mut header = String::new();
+        for line in BufReader::new(File::open(self.member_path(member)?)?).lines() {
+            let line = line?;
+            if line.trim() == "---" {
+                break;
+            }
+            header.push_str(&line);
+            header.push('\n');
+        }
+
+        let mut info = self.info.clone();
+        info.extend(Info::parse(&header));
+        Ok(info)
+    }
+
+    /// Loads the grids of `member`
+    pub fn member(&self, member: usize) -> Result<Pdf, LhapdfError> {
+        Pdf::parse(&fs::read_to_string(self.member_path(member)?)?)
+    }
+
+    /// Loads the interpolation of αs for `member`
+    pub fn alphas(&self, member: usize) -> Result<AlphaS, LhapdfError> {
+        AlphaS::from_info(&self.member_info(member)?)
+    }
+}
+
+/// Returns the element of `subgrids` to use for `value`: the last one starting below it, so that
+/// the one above is chosen at thresholds
+fn select_subgrid<T>(subgrids: &[T], lower: impl Fn(&T) -> f64, value: f64) -> &T {
+    subgrids
+        .iter()
+        .rev()
+        .find(|subgrid| lower(subgrid) <= value)
+        .unwrap_or(&subgrids[0])
+}
+
+/// Interpolation of αs(Q²), with the `ipol` type of LHAPDF
+#[derive(Debug)]
+pub struct AlphaS {
+    subgrids: Vec<Cubic<1>>,
+}
+
+impl AlphaS {
+    /// Builds the interpolation from the `AlphaS_Qs` and `AlphaS_Vals` entries of `info`
+    pub fn from_info(info: &Info) -> Result<Self, LhapdfError> {
+        match info.get("AlphaS_Type") {
+            None | Some("ipol") => (),
+            Some(kind) => return Err(LhapdfError::Unsupported(format!("AlphaS_Type {kind}"))),
+        }
+
+        let qs = info.get_floats("AlphaS_Qs")?;
+        let values = info.get_floats("AlphaS_Vals")?;
+        if qs.len() != values.len() {
+            return Err(LhapdfError::InvalidFile(
+                "'AlphaS_Qs' and 'AlphaS_Vals' have different lengths".to_owned(),
+            ));
+        }
+
+        // repeated nodes mark the boundaries between subgrids
+        let mut subgrids = Vec::new();
+        let mut start = 0;
+        for end in 1..=qs.len() {
+            if end == qs.len() || qs[end] == qs[end - 1] {
+                let logq2 = qs[start..end].iter().map(|q| (q * q).ln()).collect();
+                let values = Array1::from(values[start..end].to_vec());
+                subgrids.push(Cubic {
+                    grid: Grid::new(vec![logq2], values)?,
+                });
+                start = end;
+            }
+        }
+        if subgrids.is_empty() {
+            return Err(LhapdfError::InvalidFile(
+                "'AlphaS_Qs' has no nodes".to_owned(),
+            ));
+        }
+
+        Ok(Self { subgrids })
+    }
+
+    /// Returns αs at the scale `q2`
+    pub fn alphas_q2(&self, q2: f64) -> Result<f64, InterpolationError> {
+        let logq2 = q2.ln();
+        select_subgrid(&self.subgrids, |cubic| cubic.grid.xgrid[0][0], logq2).interpolate(logq2)
+    }
+}
+
+/// The grids of one Q² subgrid, one interpolator per flavour
+#[derive(Debug)]
+struct Subgrid {
+    pids: Vec<i32>,
+    flavours: Vec<Cubic<2>>,
+    /// The lowest ln Q² of the subgrid
+    lower: f64,
+}
+
+/// A member of a PDF set, interpolating x f(x, Q²) for each flavour
+#[derive(Debug)]
+pub struct Pdf {
+    /// The metadata in the header of the member file
+    pub header: Info,
+    subgrids: Vec<Subgrid>,
+}
+
+/// LHAPDF accepts 0 as an alias of the gluon
+fn normalize_pid(pid: i32) -> i32 {
+    if pid == 0 {
+        21
+    } else {
+        pid
+    }
+}
+
+impl Pdf {
+    /// Parses the content of a member file in the `lhagrid1` format
+    pub fn parse(text: &str) -> Result<Self, LhapdfError> {
+        let mut blocks = text.split("\n---");
+        let header = Info::parse(blocks.next().unwrap_or_default());
+        match header.get("Format") {
+            None | Some("lhagrid1") => (),
+            Some(format) => return Err(LhapdfError::Unsupported(format!("format {format}"))),
+        }
+
+        let subgrids = blocks
+            .filter(|block| !block.trim().is_empty())
+            .map(Self::parse_subgrid)
+            .collect::<Result<Vec<_>, _>>()?;
+        if subgrids.is_empty() {
+            return Err(LhapdfError::InvalidFile(
+                "the member has no grid".to_owned(),
+            ));
+        }
+
+        Ok(Self { header, subgrids })
+    }
+
+    fn parse_subgrid(block: &str) -> Result<Subgrid, LhapdfError> {
+        let mut lines = block.lines().filter(|line| !line.trim().is_empty());
+        let mut next_line = |what: &str| {
+            lines
+                .next()
+                .map(str::split_whitespace)
+                .ok_or_else(|| LhapdfError::InvalidFile(format!("missing {what} in subgrid")))
+        };
+        let invalid = |token: &str| LhapdfError::InvalidFile(format!("invalid number '{token}'"));
+
+        let xs = next_line("x nodes")?
+            .map(|x| x.parse::<f64>().map(f64::ln).map_err(|_| invalid(x)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let logq2s = next_line("Q nodes")?
+            .map(|q| {
+                q.parse::<f64>()
+                    .map(|q| (q * q).ln())
+                    .map_err(|_| invalid(q))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let pids = next_line("flavours")?
+            .map(|pid| pid.parse().map(normalize_pid).map_err(|_| invalid(pid)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let values = lines
+            .flat_map(str::split_whitespace)
+            .map(|v| v.parse().map_err(|_| invalid(v)))
+            .collect::<Result<Vec<f64>, _>>()?;
+
+        let shape = (xs.len(), logq2s.len(), pids.len());
+        let values = Array3::from_shape_vec(shape, values).map_err(|_| {
+            LhapdfError::InvalidFile(format!(
+                "expected {} values for {} x nodes, {} Q nodes and {} flavours",
+                shape.0 * shape.1 * shape.2,
+                shape.0,
+                shape.1,
+                shape.2
+            ))
+        })?;
+
+        let lower = logq2s.first().copied().unwrap_or(f64::NAN);
+        let flavours = values
+            .axis_iter(Axis(2))
+            .map(|values| {
+                Grid::new(vec![xs.clone(), logq2s.clone()], values.to_owned())
+                    .map(|grid| Cubic { grid })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Subgrid {
+            pids,
+            flavours,
+            lower,
+        })
+    }
+
+    /// The flavours tabulated by the member, as PDG ids
+    pub fn pids(&self) -> &[i32] {
+        &self.subgrids[0].pids
+    }
+
+    /// Returns x f(x, Q²) for the flavour `pid`, which is zero for flavours that are not tabulated
+    /// as done by LHAPDF
+    pub fn xfx_q2(&self, pid: i32, x: f64, q2: f64) -> Result<f64, InterpolationError> {
+        let query = [x.ln(), q2.ln()];
+        let subgrid = select_subgrid(&self.subgrids, |subgrid| subgrid.lower, query[1]);
+
+        match subgrid.pids.iter().position(|&p| p == normalize_pid(pid)) {
+            Some(index) => subgrid.flavours[index].interpolate(&query[..]),
+            None => Ok(0.),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::TempPath;
+
+    const INFO: &str = "SetDesc: \"Test set\"
+Format: lhagrid1
+NumMembers: 1
+Flavors: [-1, 1, 21]
+AlphaS_Type: ipol
+AlphaS_Qs: [1.0, 2.0, 3.0,
+  3.0, 4.0, 5.0]
+AlphaS_Vals: [0.4, 0.3, 0.25, 0.26, 0.22, 0.2]
+";
+
+    /// Linear in ln x and ln Q², so that it is reproduced exactly by the interpolation
+    fn xf(pid: i32, x: f64, q2: f64) -> f64 {
+        f64::from(pid) * (1. - 0.1 * x.ln() + 0.5 * q2.ln())
+    }
+
+    fn subgrid(xs: &[f64], qs: &[f64], offset: f64) -> String {
+        let mut block = format!(
+            "{}\n{}\n-1 1 0\n",
+            xs.iter().map(f64::to_string).collect::<Vec<_>>().join(" "),
+            qs.iter().map(f64::to_string).collect::<Vec<_>>().join(" ")
+        );
+        for &x in xs {
+            for &q in qs {
+                let values: Vec<_> = [-1, 1, 21]
+                    .iter()
+                    .map(|&pid| (xf(pid, x, q * q) + offset).to_string())
+                    .collect();
+                block.push_str(&values.join(" "));
+                block.push('\n');
+            }
+        }
+        block
+    }
+
+    fn write_set(dir: &Path) {
+        let xs = [1e-5, 1e-3, 0.1, 0.5, 1.];
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("TestSet.info"), INFO).unwrap();
+        fs::write(
+            dir.join("TestSet_0000.dat"),
+            format!(
+                "PdfType: central\nFormat: lhagrid1\n---\n{}---\n{}---\n",
+                subgrid(&xs, &[1., 2., 3.], 0.),
+                subgrid(&xs, &[3., 4., 5., 6.], 100.)
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn check_info() {
+        let info = Info::parse(INFO);
+        assert_eq!(info.get("SetDesc"), Some("Test set"));
+        assert_eq!(info.get_floats("Flavors").unwrap(), [-1., 1., 21.]);
+        assert_eq!(info.get_floats("AlphaS_Qs").unwrap().len(), 6);
+        assert!(matches!(
+            info.get_floats("SetDesc"),
+            Err(LhapdfError::InvalidFile(_))
+        ));
+        assert!(matches!(
+            info.get_floats("Missing"),
+            Err(LhapdfError::InvalidFile(_))
+        ));
+        assert!(matches!(
+            AlphaS::from_info(&Info::parse("AlphaS_Qs: []\nAlphaS_Vals: []\n")),
+            Err(LhapdfError::InvalidFile(_))
+        ));
+    }
+
+    #[test]
+    fn check_set() {
+        let root = TempPath::new("lhapdf");
+        write_set(&root.join("TestSet"));
+
+        // lookup by name in the data paths and by path
+        let path = find_set_in("TestSet", &[root.join("missing"), root.to_path_buf()]).unwrap();
+        assert_eq!(path, root.join("TestSet"));
+        assert!(matches!(
+            find_set_in("OtherSet", &[root.to_path_buf()]),
+            Err(LhapdfError::SetNotFound(_))
+        ));
+        let set = PdfSet::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(set.name, "TestSet");
+        assert_eq!(set.num_members(), Some(1));
+        assert!(matches!(
+            set.member(1),
+            Err(LhapdfError::MemberNotFound {
+                member: 1,
+                members: 1
+            })
+        ));
+
+        let pdf = set.member(0).unwrap();
+        assert_eq!(pdf.header.get("PdfType"), Some("central"));
+        assert_eq!(pdf.pids(), [-1, 1, 21]);
+        for (x, q2) in [(1e-4, 1.5), (0.2, 4.), (0.7, 30.)] {
+            for pid in [-1, 1, 21] {
+                let result = pdf.xfx_q2(pid, x, q2).unwrap();
+                let offset = if q2 >= 9. { 100. } else { 0. };
+                assert!((result - xf(pid, x, q2) - offset).abs() < 1e-12);
+            }
+        }
+        // the gluon can also be given as 0, missing flavours vanish
+        assert_eq!(
+            pdf.xfx_q2(0, 0.2, 4.).unwrap(),
+            pdf.xfx_q2(21, 0.2, 4.).unwrap()
+        );
+        assert_eq!(pdf.xfx_q2(5, 0.2, 4.).unwrap(), 0.);
+        // the threshold belongs to the upper subgrid
+        let threshold = pdf.xfx_q2(1, 0.2, 9.).unwrap();
+        assert!((threshold - xf(1, 0.2, 9.) - 100.).abs() < 1e-12);
+        assert!(matches!(
+            pdf.xfx_q2(1, 0.2, 40.),
+            Err(InterpolationError::ExtrapolationAbove(_))
+        ));
+
+        let alphas = set.alphas(0).unwrap();
+        assert!((alphas.alphas_q2(4.).unwrap() - 0.3).abs() < 1e-12);
+        assert!((alphas.alphas_q2(9.).unwrap() - 0.26).abs() < 1e-12);
+        assert!((alphas.alphas_q2(8.99).unwrap() - 0.25).abs() < 1e-3);
+        assert!(matches!(
+            alphas.alphas_q2(0.5),
+            Err(InterpolationError::ExtrapolationBelow(_))
+        ));
+    }
+}
@@ -0,0 +1,478 @@
+//! Reading and writing of NumPy `.npy` and `.npz` files
+//!
+//! The [format](https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html) is
+//! implemented natively, so that no Python is needed at runtime. Arrays of 32 and 64 bit floating
+//! point numbers can be read in either byte order and either C or Fortran order, arrays are always
+//! written as little-endian 64 bit floats in C order.
+//!
+//! A grid is stored in a `.npz` archive with one entry per input vector, `x0`, `x1`, ... and one
+//! entry, `values`, for the values, as produced by
+//!     `numpy.savez(file, x0=x0, x1=x1, values=values)`
+//! Both compressed and uncompressed archives can be read.
+
+use crate::grid::{DimensionHelper, Grid, ToDimension};
+use crate::interpolate::InterpolationError;
+use itertools::Itertools;
+use ndarray::{ArrayBase, ArrayD, Data, Dimension, IxDyn, ShapeBuilder};
+use std::io::{Read, Seek, Write};
+use thiserror::Error;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Every `.npy` file starts with this string
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// The header, including magic string and header length, is padded to a multiple of this size
+const ALIGNMENT: usize = 64;
+
+/// Errors encountered while reading or writing `.npy` and `.npz` files
+#[derive(Debug, Error)]
+pub enum NpyError {
+    /// Raised when reading from or writing to the underlying file fails
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// Raised when the `.npz` archive cannot be read or written
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+
+    /// Raised when the file does not start with a valid `.npy` header
+    #[error("Invalid npy header: {0}")]
+    InvalidHeader(String),
+
+    /// Raised for arrays with a data type other than 32 or 64 bit floats
+    #[error("Unsupported dtype '{0}', only 32 and 64 bit floats are supported")]
+    UnsupportedDtype(String),
+
+    /// Raised when the arrays read from an archive do not form a valid grid
+    #[error(transparent)]
+    Grid(#[from] InterpolationError),
+}
+
+/// Byte order of the data stored in a `.npy` file
+enum ByteOrder {
+    Little,
+    Big,
+}
+
+/// The content of the header of a `.npy` file that is relevant for floating point arrays
+struct Header {
+    order: ByteOrder,
+    /// Size in bytes of each element, either 4 or 8
+    size: usize,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+/// Returns the text following `key` in the header, which is the literal of a python dictionary
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, NpyError> {
+    [format!("'{key}'"), format!("\"{key}\"")]
+        .iter()
+        .find_map(|k| header.find(k.as_str()).map(|i| &header[i + k.len()..]))
+        .and_then(|rest| rest.trim_start().strip_prefix(':'))
+        .map(str::trim_start)
+        .ok_or_else(|| NpyError::InvalidHeader(format!("missing entry '{key}'")))
+}
+
+fn parse_header(header: &str) -> Result<Header, NpyError> {
+    let descr = header_value(header, "descr")?;
+    let descr = descr
+        .chars()
+        .next()
+        .filter(|&q| q == '\'' || q == '"')
+        .and_then(|q| descr[1..].split(q).next())
+        .ok_or_else(|| NpyError::InvalidHeader("'descr' is not a string".to_owned()))?;
+
+    let (order, dtype) = match descr.as_bytes().first() {
+        Some(b'<') => (Some(ByteOrder::Little), &descr[1..]),
+        Some(b'>') => (Some(ByteOrder::Big), &descr[1..]),
+        Some(b'=' | b'|') => (None, &descr[1..]),
+        _ => (None, descr),
+    };
+    // without an explicit byte order the data is in the native order
+    let order = order.unwrap_or(if cfg!(target_endian = "big") {
+        ByteOrder::Big
+    } else {
+        ByteOrder::Little
+    });
+    let size = match dtype {
+        "f4" => 4,
+        "f8" => 8,
+        _ => return Err(NpyError::UnsupportedDtype(descr.to_owned())),
+    };
+
+    let fortran_order = header_value(header, "fortran_order")?;
+    let fortran_order = if fortran_order.starts_with("True") {
+        true
+    } else if fortran_order.starts_with("False") {
+        false
+    } else {
+        return Err(NpyError::InvalidHeader(
+            "'fortran_order' is not a boolean".to_owned(),
+        ));
+    };
+
+    let shape = header_value(header, "shape")?;
+    let shape = shape
+        .strip_prefix('(')
+        .and_then(|s| s.split(')').next())
+        .ok_or_else(|| NpyError::InvalidHeader("'shape' is not a tuple".to_owned()))?
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        // files written by python 2 might mark dimensions as long integers
+        .map(|s| s.trim_end_matches('L').parse())
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|err| NpyError::InvalidHeader(format!("invalid 'shape': {err}")))?;
+
+    Ok(Header {
+        order,
+        size,
+        fortran_order,
+        shape,
+    })
+}
+
+/// Converts the raw data into 64 bit floats
+fn decode(bytes: &[u8], size: usize, order: &ByteOrder) -> Vec<f64> {
+    match (size, order) {
+        (4, ByteOrder::Little) => bytes
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes(c.try_into().unwrap()).into())
+            .collect(),
+        (4, ByteOrder::Big) => bytes
+            .chunks_exact(4)
+            .map(|c| f32::from_be_bytes(c.try_into().unwrap()).into())
+            .collect(),
+        (_, ByteOrder::Little) => bytes
+            .chunks_exact(8)
+            .map(|c| f64::from_le_bytes(c.try_into().unwrap()))
+            .collect(),
+        (_, ByteOrder::Big) => bytes
+            .chunks_exact(8)
+            .map(|c| f64::from_be_bytes(c.try_into().unwrap()))
+            .collect(),
+    }
+}
+
+/// Reads an array of floating point numbers from a `.npy` file
+pub fn read_npy<R: Read>(mut reader: R) -> Result<ArrayD<f64>, NpyError> {
+    let mut preamble = [0; 8];
+    reader.read_exact(&mut preamble)?;
+    if &preamble[..MAGIC.len()] != MAGIC {
+        return Err(NpyError::InvalidHeader("not a npy file".to_owned()));
+    }
+
+    // version 1.0 stores the length of the header in two bytes, later versions in four
+    let header_len = match preamble[6] {
+        1 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            usize::from(u16::from_le_bytes(len))
+        }
+        2 | 3 => {
+            let mut len = [0; 4];
+            reader.read_exact(&mut len)?;
+            u32::from_le_bytes(len) as usize
+        }
+        version => {
+            return Err(NpyError::InvalidHeader(format!(
+                "unsupported format version {version}"
+            )))
+        }
+    };
+
+    let mut header = vec![0; header_len];
+    reader.read_exact(&mut header)?;
+    let header = String::from_utf8(header)
+        .map_err(|_| NpyError::InvalidHeader("the header is not valid text".to_owned()))?;
+    let header = parse_header(&header)?;
+
+    let len = header
+        .shape
+        .iter()
+        .try_fold(header.size, |len, &n| len.checked_mul(n))
+        .ok_or_else(|| NpyError::InvalidHeader("the size of the array overflows".to_owned()))?;
+    // the buffer grows with the data actually read, so that a corrupted shape does not allocate
+    // more memory than the size of the file
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    let data = decode(&bytes, header.size, &header.order);
+
+    // the amount of data read is given by the shape, so this cannot fail
+    let shape = IxDyn(&header.shape);
+    Ok(if header.fortran_order {
+        ArrayD::from_shape_vec(shape.f(), data)
+    } else {
+        ArrayD::from_shape_vec(shape, data)
+    }
+    .unwrap())
+}
+
+/// Writes an array into a `.npy` file, as little-endian 64 bit floats in C order
+pub fn write_npy<W, S, D>(mut writer: W, array: &ArrayBase<S, D>) -> Result<(), NpyError>
+where
+    W: Write,
+    S: Data<Elem = f64>,
+    D: Dimension,
+{
+    let shape = match array.shape() {
+        [n] => format!("({n},)"),
+        shape => format!("({})", shape.iter().join(", ")),
+    };
+    let mut header = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': {shape}, }}");
+
+    // the header is terminated by a newline and padded with spaces
+    let unpadded = MAGIC.len() + 2 + 2 + header.len() + 1;
+    let padding = unpadded.next_multiple_of(ALIGNMENT) - unpadded;
+    header.extend(std::iter::repeat_n(' ', padding));
+    header.push('\n');
+
+    let header_len = u16::try_from(header.len())
+        .map_err(|_| NpyError::InvalidHeader("too many dimensions".to_owned()))?;
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[1, 0])?;
+    writer.write_all(&header_len.to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+
+    // iterating over the array always visits the elements in C order
+    let data: Vec<u8> = array.iter().flat_map(|v| v.to_le_bytes()).collect();
+    writer.write_all(&data)?;
+
+    Ok(())
+}
+
+fn read_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<ArrayD<f64>, NpyError> {
+    read_npy(archive.by_name(&format!("{name}.npy"))?)
+}
+
+/// Reads a grid from a `.npz` archive with the input vectors stored as `x0`, `x1`, ... and the
+/// values stored as `values`
+pub fn read_npz<const D: usize, R>(reader: R) -> Result<Grid<D>, NpyError>
+where
+    DimensionHelper<D>: ToDimension,
+    R: Read + Seek,
+{
+    let axes: [String; D] = std::array::from_fn(|i| format!("x{i}"));
+    read_npz_entries(reader, &axes.each_ref().map(String::as_str), "values")
+}
+
+/// Reads a grid from a `.npz` archive with the input vectors and the values stored in the entries
+/// with the given names
+pub fn read_npz_entries<const D: usize, R>(
+    reader: R,
+    axes: &[&str; D],
+    values: &str,
+) -> Result<Grid<D>, NpyError>
+where
+    DimensionHelper<D>: ToDimension,
+    R: Read + Seek,
+{
+    let mut archive = ZipArchive::new(reader)?;
+
+    let mut xgrid = Vec::with_capacity(D);
+    for name in axes {
+        let axis = read_entry(&mut archive, name)?;
+        if axis.ndim() != 1 {
+            return Err(InterpolationError::InvalidGrid(format!(
+                "entry '{name}' has {} dimensions instead of one",
+                axis.ndim()
+            ))
+            .into());
+        }
+        xgrid.push(axis.into_iter().collect());
+    }
+
+    let array = read_entry(&mut archive, values)?;
+    let ndim = array.ndim();
+    let array = array
+        .as_standard_layout()
+        .into_owned()
+        .into_dimensionality()
+        .map_err(|_| {
+            InterpolationError::InvalidGrid(format!(
+                "entry '{values}' has {ndim} dimensions instead of {D}"
+            ))
+        })?;
+
+    Ok(Grid::new(xgrid, array)?)
+}
+
+/// Writes a grid into a `.npz` archive, with the input vectors stored as `x0`, `x1`, ... and the
+/// values stored as `values`
+pub fn write_npz<const D: usize, W>(grid: &Grid<D>, writer: W) -> Result<(), NpyError>
+where
+    DimensionHelper<D>: ToDimension,
+    W: Write + Seek,
+{
+    let axes: [String; D] = std::array::from_fn(|i| format!("x{i}"));
+    write_npz_entries(grid, writer, &axes.each_ref().map(String::as_str), "values")
+}
+
+/// Writes a grid into an uncompressed `.npz` archive, with the input vectors and the values
+/// stored in the entries with the given names
+pub fn write_npz_entries<const D: usize, W>(
+    grid: &Grid<D>,
+    writer: W,
+    axes: &[&str; D],
+    values: &str,
+) -> Result<(), NpyError>
+where
+    DimensionHelper<D>: ToDimension,
+    W: Write + Seek,
+{
+    let mut archive = ZipWriter::new(writer);
+    // same as `numpy.savez`, which stores the arrays without compression
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(grid.values.len() * 8 >= u32::MAX as usize);
+
+    for (name, axis) in axes.iter().zip(&grid.xgrid) {
+        archive.start_file(format!("{name}.npy"), options)?;
+        write_npy(&mut archive, &ndarray::aview1(axis))?;
+    }
+    archive.start_file(format!("{values}.npy"), options)?;
+    write_npy(&mut archive, &grid.values)?;
+
+    archive.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+    use std::io::Cursor;
+
+    /// Builds the content of a `.npy` file the same way `numpy` does
+    fn npy_bytes(descr: &str, fortran_order: bool, shape: &str, data: &[u8]) -> Vec<u8> {
+        let order = if fortran_order { "True" } else { "False" };
+        let mut header =
+            format!("{{'descr': '{descr}', 'fortran_order': {order}, 'shape': {shape}, }}");
+        while (header.len() + 11) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn check_write_npy() {
+        let mut bytes = Vec::new();
+        write_npy(&mut bytes, &array![1., 2., 3.]).unwrap();
+
+        let data: Vec<u8> = [1f64, 2., 3.]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        assert_eq!(bytes, npy_bytes("<f8", false, "(3,)", &data));
+        assert_eq!((bytes.len() - data.len()) % 64, 0);
+    }
+
+    #[test]
+    fn check_read_npy() {
+        // big-endian single precision in fortran order
+        let data: Vec<u8> = [1f32, 4., 2., 5., 3., 6.]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+        let bytes = npy_bytes(">f4", true, "(2, 3)", &data);
+
+        let array = read_npy(bytes.as_slice()).unwrap();
+        assert_eq!(array, array![[1., 2., 3.], [4., 5., 6.]].into_dyn());
+
+        // little-endian double precision in C order
+        let data: Vec<u8> = [1f64, 2., 3., 4.]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let bytes = npy_bytes("<f8", false, "(2, 2)", &data);
+
+        let array = read_npy(bytes.as_slice()).unwrap();
+        assert_eq!(array, array![[1., 2.], [3., 4.]].into_dyn());
+    }
+
+    #[test]
+    fn check_invalid_npy() {
+        let bytes = npy_bytes("<i8", false, "(1,)", &1i64.to_le_bytes());
+        assert!(matches!(
+            read_npy(bytes.as_slice()),
+            Err(NpyError::UnsupportedDtype(_))
+        ));
+
+        let bytes = npy_bytes("<f8", false, "(2,)", &1f64.to_le_bytes());
+        assert!(matches!(read_npy(bytes.as_slice()), Err(NpyError::Io(_))));
+
+        // shapes whose size overflows, or exceeds the data by far
+        let shape = format!("({}, {})", usize::MAX / 4, 3);
+        let bytes = npy_bytes("<f8", false, &shape, &1f64.to_le_bytes());
+        assert!(matches!(
+            read_npy(bytes.as_slice()),
+            Err(NpyError::InvalidHeader(_))
+        ));
+        let shape = format!("({},)", usize::MAX / 16);
+        let bytes = npy_bytes("<f8", false, &shape, &1f64.to_le_bytes());
+        assert!(matches!(read_npy(bytes.as_slice()), Err(NpyError::Io(_))));
+
+        assert!(matches!(
+            read_npy(&b"PK\x03\x04 not a npy file"[..]),
+            Err(NpyError::InvalidHeader(_))
+        ));
+    }
+
+    #[test]
+    fn check_npz_roundtrip() {
+        let grid = Grid::<2> {
+            xgrid: vec![vec![0., 1., 2.], vec![-1., 1.]],
+            values: array![[1., 2.], [3., 4.], [5., 6.]],
+        };
+
+        let mut file = Cursor::new(Vec::new());
+        write_npz(&grid, &mut file).unwrap();
+        file.set_position(0);
+        let loaded: Grid<2> = read_npz(file).unwrap();
+
+        assert_eq!(loaded.xgrid, grid.xgrid);
+        assert_eq!(loaded.values, grid.values);
+    }
+
+    #[test]
+    fn check_compressed_npz() {
+        let mut file = Cursor::new(Vec::new());
+        let mut archive = ZipWriter::new(&mut file);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        archive.start_file("q2.npy", options).unwrap();
+        write_npy(&mut archive, &array![1., 2., 4.]).unwrap();
+        archive.start_file("alphas.npy", options).unwrap();
+        write_npy(&mut archive, &array![0.3, 0.2, 0.1]).unwrap();
+        archive.finish().unwrap();
+
+        file.set_position(0);
+        let grid: Grid<1> = read_npz_entries(&mut file, &["q2"], "alphas").unwrap();
+        assert_eq!(grid.xgrid, vec![vec![1., 2., 4.]]);
+        assert_eq!(grid.values, array![0.3, 0.2, 0.1]);
+
+        // the entries do not form a 2-dimensional grid
+        file.set_position(0);
+        assert!(matches!(
+            read_npz_entries::<2, _>(&mut file, &["q2", "q2"], "alphas"),
+            Err(NpyError::Grid(_))
+        ));
+
+        // missing entries
+        file.set_position(0);
+        assert!(matches!(read_npz::<1, _>(&mut file), Err(NpyError::Zip(_))));
+    }
+}
@@ -1,8 +1,10 @@
 #![warn(clippy::all, clippy::cargo)]
+#![allow(clippy::multiple_crate_versions)]
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
 
 pub mod grid;
 pub mod interpolate;
-//pub mod metric;
-//pub mod scatter;
+pub mod io;
+pub mod metric;
+pub mod scatter;
@@ -1,14 +1,24 @@
 //! Define concept of metric
 use ndarray::prelude::*;
-use ndarray_linalg::norm::Norm;
 
+/// Spaces with a notion of distance between their points
+///
+/// The distance must be non-negative, symmetric and satisfy the triangle inequality, which the
+/// neighbor searches of [`crate::scatter::knn`] rely upon.
 pub trait Metric {
+    /// Distance between the points `a` and `b`
     fn distance(a: &Self, b: &Self) -> f64;
 }
 
+/// The Euclidean distance
 impl<D: Dimension> Metric for Array<f64, D> {
     fn distance(a: &Self, b: &Self) -> f64 {
-        (a - b).norm()
+        // without allocating the difference, since the neighbor searches compute many distances
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| (x - y) * (x - y))
+            .sum::<f64>()
+            .sqrt()
     }
 }
 
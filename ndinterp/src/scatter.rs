@@ -5,10 +5,25 @@
 //! Nevertheless, they are all developed and optimize for the Euclidean n-dimenional spaces (if
 //! you want to use for something, take care of differences, possibly not properly accounted for by
 //! the implemented algorithms).
+//!
+//! The interpolators are built from the scattered points and their values, stored in
+//! [`Commons`], and use a finder implementing [`knn::KNN`] to select the points contributing to
+//! each query. Among the finders, [`knn::All`], [`knn::HNSW`] and [`knn::VpTree`] only rely on
+//! the [`crate::metric::Metric`] of the points, therefore they work for any point type, while
+//! [`knn::KdTree`] requires vector points.
+//!
+//! With the `serde` feature [`ModifiedShepard`] can be serialized, to keep its fitted nodal
+//! functions. The other interpolators, [`Commons`] and the finders are not serializable: they have
+//! no state besides the points and values, and the finders are rebuilt from them by the
+//! constructors of the interpolators.
 
 pub mod commons;
 pub mod invdist;
 pub mod knn;
-pub mod utils;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod shepard;
 
-pub use invdist::{InvDist, InvDistAll};
+pub use commons::Commons;
+pub use invdist::{InvDist, InvDistAll, InvDistBase, InvDistKdTree};
+pub use shepard::ModifiedShepard;
@@ -1,16 +1,21 @@
-use std::rc::Rc;
+//! Data shared by all the scattered interpolators
+use std::sync::Arc;
 
 use ndarray::{s, Array1, Array2};
 
 use super::knn::KNN;
-use crate::{interpolate::Input, metric::Metric};
+use crate::{interpolate::InterpolationError, metric::Metric};
 
+/// The scattered points with their values, and the finder of the neighbors of the queries
+///
+/// The points are shared with the finder, which refers to them by their position in
+/// [`Commons::points`].
 pub struct Commons<Point, Finder>
 where
     Point: Metric,
     Finder: KNN<Point = Point>,
 {
-    pub(crate) points: Vec<Rc<Point>>,
+    pub(crate) points: Vec<Arc<Point>>,
     pub(crate) values: Vec<f64>,
     pub(crate) finder: Option<Finder>,
 }
@@ -20,20 +25,52 @@ where
     Point: Metric,
     Finder: KNN<Point = Point>,
 {
-    pub fn new(inputs: Vec<Input<Point>>) -> Self {
-        let values = inputs.iter().map(|i| i.value).collect();
-        let points = inputs.into_iter().map(|i| Rc::new(i.point)).collect();
+    /// Stores the `points` with their `values`, which must have the same length. The finder has
+    /// to be set with [`Commons::set_finder`] before interpolating
+    pub fn new(points: Vec<Point>, values: Vec<f64>) -> Result<Self, InterpolationError> {
+        if points.len() != values.len() {
+            return Err(InterpolationError::InvalidGrid(format!(
+                "{} points were given with {} values",
+                points.len(),
+                values.len()
+            )));
+        }
 
-        Self {
-            points,
+        Ok(Self {
+            points: points.into_iter().map(Arc::new).collect(),
             values,
             finder: None,
-        }
+        })
+    }
+
+    /// The points, in the order of their identifiers
+    pub fn points(&self) -> &[Arc<Point>] {
+        &self.points
+    }
+
+    /// The values at the points
+    pub fn values(&self) -> &[f64] {
+        &self.values
     }
 
+    /// The points paired with their identifiers, as accepted by the finders
+    pub fn identified_points(&self) -> Vec<(usize, Arc<Point>)> {
+        self.points.iter().cloned().enumerate().collect()
+    }
+
+    /// Sets the finder used to look up the neighbors of the queries
     pub fn set_finder(&mut self, finder: Finder) {
         self.finder = Some(finder)
     }
+
+    /// The finder, or an error if it has not been set
+    pub(crate) fn finder(&self) -> Result<&Finder, InterpolationError> {
+        self.finder.as_ref().ok_or_else(|| {
+            InterpolationError::InvalidConfiguration(
+                "the finder of the neighbors is not set".to_owned(),
+            )
+        })
+    }
 }
 
 fn split_2d(points: Array2<f64>) -> (Vec<Array1<f64>>, Vec<f64>) {
@@ -47,12 +84,55 @@ fn split_2d(points: Array2<f64>) -> (Vec<Array1<f64>>, Vec<f64>) {
     (points, values)
 }
 
-impl<Finder> From<Array2<f64>> for Commons<Array1<f64>, Finder>
+/// Builds from an array with one row per point, holding its coordinates followed by its value
+impl<Finder> TryFrom<Array2<f64>> for Commons<Array1<f64>, Finder>
 where
     Finder: KNN<Point = Array1<f64>>,
 {
-    fn from(inputs: Array2<f64>) -> Self {
+    type Error = InterpolationError;
+
+    fn try_from(inputs: Array2<f64>) -> Result<Self, Self::Error> {
+        if inputs.ncols() == 0 {
+            return Err(InterpolationError::InvalidGrid(
+                "the rows must hold the coordinates and the value of each point".to_owned(),
+            ));
+        }
         let (points, values) = split_2d(inputs);
-        Self::new(Input::stack(points, values))
+        Self::new(points, values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scatter::knn::All;
+    use ndarray::array;
+
+    #[test]
+    fn check_new() {
+        let points = vec![array![0., 0.], array![1., 0.], array![0., 1.]];
+        let commons = Commons::<_, All<_>>::new(points.clone(), vec![1., 2., 3.]).unwrap();
+        assert_eq!(commons.values(), [1., 2., 3.]);
+        assert_eq!(*commons.points()[2], array![0., 1.]);
+        assert!(matches!(
+            commons.finder(),
+            Err(InterpolationError::InvalidConfiguration(_))
+        ));
+
+        let identified = commons.identified_points();
+        assert_eq!(identified[1].0, 1);
+        assert!(Arc::ptr_eq(&identified[1].1, &commons.points()[1]));
+
+        assert!(Commons::<_, All<_>>::new(points, vec![1., 2.]).is_err());
+    }
+
+    #[test]
+    fn check_from_array() {
+        let inputs = array![[0., 0., 1.], [1., 0., 2.]];
+        let commons = Commons::<_, All<_>>::try_from(inputs).unwrap();
+        assert_eq!(commons.values(), [1., 2.]);
+        assert_eq!(*commons.points()[1], array![1., 0.]);
+
+        assert!(Commons::<_, All<_>>::try_from(Array2::zeros((3, 0))).is_err());
     }
 }
@@ -1,20 +1,29 @@
+//! Inverse distance weighting, also known as Shepard's method
 use super::{
     commons::Commons,
-    knn::{All, HNSW, KNN},
+    knn::{All, KdTree, Search, VpTree, HNSW, KNN},
 };
 use crate::{
-    interpolate::{Input, Interpolate},
+    interpolate::{InterpolationError, Interpolator},
     metric::Metric,
 };
 
 use ndarray::{Array1, Array2};
 
+/// Interpolates as the average of the values of the neighbors of the query, weighted by the
+/// inverse of their distances from it raised to the power `p`
+///
+/// Queries closer than the coincidence tolerance to one of their neighbors return its value.
+/// Since the weights never vanish the interpolant has flat spots at the points, see
+/// [`super::ModifiedShepard`] for a smoother and more accurate alternative.
 pub struct InvDistBase<Point, Finder>
 where
     Point: Metric,
     Finder: KNN<Point = Point>,
 {
     commons: Commons<Point, Finder>,
+    power: f64,
+    tolerance: f64,
 }
 
 impl<Point, Finder> InvDistBase<Point, Finder>
@@ -22,61 +31,352 @@ where
     Point: Metric,
     Finder: KNN<Point = Point>,
 {
-    pub fn new(inputs: Vec<Input<Point>>) -> Self {
+    /// Default power of the distances in the weights
+    pub const POWER: f64 = 1.;
+    /// Default distance below which a query coincides with a point
+    pub const TOLERANCE: f64 = 1e-10;
+
+    /// Interpolates the `values` at the `points`, see [`Commons::new`]
+    pub fn new(points: Vec<Point>, values: Vec<f64>) -> Result<Self, InterpolationError> {
+        Ok(Self::from_commons(Commons::new(points, values)?))
+    }
+
+    fn from_commons(commons: Commons<Point, Finder>) -> Self {
         Self {
-            commons: Commons::new(inputs),
+            commons,
+            power: Self::POWER,
+            tolerance: Self::TOLERANCE,
         }
     }
 
+    /// The points and their values
+    pub fn commons(&self) -> &Commons<Point, Finder> {
+        &self.commons
+    }
+
+    /// Sets the finder used to look up the neighbors of the queries
     pub fn set_finder(&mut self, finder: Finder) {
         self.commons.set_finder(finder)
     }
+
+    /// Power of the distances in the weights
+    pub fn power(&self) -> f64 {
+        self.power
+    }
+
+    /// Sets the power `p` of the distances, which must be positive and finite: the weights are
+    /// `1 / d^p`. Larger powers make the interpolant closer to the value of the nearest neighbor
+    pub fn set_power(&mut self, power: f64) -> Result<(), InterpolationError> {
+        if !(power > 0. && power.is_finite()) {
+            return Err(InterpolationError::InvalidConfiguration(format!(
+                "the power of the distances must be positive and finite, not {power}"
+            )));
+        }
+        self.power = power;
+        Ok(())
+    }
+
+    /// Distance below which a query coincides with a point
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    /// Sets the distance below which a query coincides with a point, and is given its value. The
+    /// tolerance must not be negative
+    pub fn set_tolerance(&mut self, tolerance: f64) -> Result<(), InterpolationError> {
+        self.tolerance = check_tolerance(tolerance)?;
+        Ok(())
+    }
+}
+
+/// Returns `tolerance` if it is a valid coincidence tolerance, i.e. not negative nor NaN
+pub(super) fn check_tolerance(tolerance: f64) -> Result<f64, InterpolationError> {
+    if tolerance >= 0. {
+        Ok(tolerance)
+    } else {
+        Err(InterpolationError::InvalidConfiguration(format!(
+            "the coincidence tolerance must not be negative, not {tolerance}"
+        )))
+    }
 }
 
-impl<Finder> From<Array2<f64>> for InvDistBase<Array1<f64>, Finder>
+impl<Point> InvDistBase<Point, All<Point>>
+where
+    Point: Metric,
+{
+    /// Interpolates the `values` at the `points`, using all of them for every query
+    pub fn with_all(points: Vec<Point>, values: Vec<f64>) -> Result<Self, InterpolationError> {
+        let mut interpolator = Self::new(points, values)?;
+        interpolator.set_finder(All::new(interpolator.commons.identified_points()));
+        Ok(interpolator)
+    }
+}
+
+impl<Point> InvDistBase<Point, HNSW<Point>>
+where
+    Point: Metric,
+{
+    /// Interpolates the `values` at the `points`, using the `k` nearest neighbors of every query
+    /// found by [`HNSW`]
+    pub fn with_hnsw(
+        points: Vec<Point>,
+        values: Vec<f64>,
+        k: u32,
+    ) -> Result<Self, InterpolationError> {
+        let mut interpolator = Self::new(points, values)?;
+        interpolator.set_finder(HNSW::build(k, interpolator.commons.identified_points()));
+        Ok(interpolator)
+    }
+}
+
+impl<Point> InvDistBase<Point, VpTree<Point>>
+where
+    Point: Metric,
+{
+    /// Interpolates the `values` at the `points`, using the neighbors of every query selected by
+    /// `search` on a [`VpTree`], which only relies on the distance between the points
+    pub fn with_vptree(
+        points: Vec<Point>,
+        values: Vec<f64>,
+        search: Search,
+    ) -> Result<Self, InterpolationError> {
+        let mut interpolator = Self::new(points, values)?;
+        let finder = VpTree::build(search, interpolator.commons.identified_points());
+        interpolator.set_finder(finder);
+        Ok(interpolator)
+    }
+}
+
+impl InvDistBase<Array1<f64>, KdTree> {
+    /// Interpolates the `values` at the `points`, using the neighbors of every query selected by
+    /// `search` on a [`KdTree`]
+    pub fn with_kdtree(
+        points: Vec<Array1<f64>>,
+        values: Vec<f64>,
+        search: Search,
+    ) -> Result<Self, InterpolationError> {
+        let mut interpolator = Self::new(points, values)?;
+        let finder = KdTree::build(search, interpolator.commons.identified_points())?;
+        interpolator.set_finder(finder);
+        Ok(interpolator)
+    }
+}
+
+/// Builds from an array with one row per point, holding its coordinates followed by its value
+impl<Finder> TryFrom<Array2<f64>> for InvDistBase<Array1<f64>, Finder>
 where
     Finder: KNN<Point = Array1<f64>>,
 {
-    fn from(inputs: Array2<f64>) -> Self {
-        Self {
-            commons: inputs.into(),
-        }
+    type Error = InterpolationError;
+
+    fn try_from(inputs: Array2<f64>) -> Result<Self, Self::Error> {
+        Ok(Self::from_commons(inputs.try_into()?))
     }
 }
 
-impl<Point, Finder> Interpolate for InvDistBase<Point, Finder>
+impl<Point, Finder> Interpolator<&Point> for InvDistBase<Point, Finder>
 where
     Point: Metric,
     Finder: KNN<Point = Point>,
 {
-    type Point = Point;
+    fn interpolate(&self, query: &Point) -> Result<f64, InterpolationError> {
+        let neighbors = self.commons.finder()?.neighbors(query);
+        if neighbors.is_empty() {
+            return Err(InterpolationError::InvalidConfiguration(
+                "no neighbors were found for the query".to_owned(),
+            ));
+        }
 
-    fn interpolate(&self, query: &Self::Point) -> f64 {
-        let mut value = 0.;
-        let mut norm = 0.;
+        let distances: Vec<_> = neighbors
+            .iter()
+            .map(|&nb_id| Point::distance(query, &self.commons.points[nb_id]))
+            .collect();
+        let (nearest, &min_dist) = distances
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap();
 
-        for nb_id in self
-            .commons
-            .finder
-            .as_ref()
-            .expect("Finder not set.")
-            .neighbors(query)
-        {
-            let dist = Point::distance(query, &self.commons.points[nb_id]);
-            let nb_value = self.commons.values[nb_id];
+        // In case of distance too close, early return the exact value
+        if min_dist <= self.tolerance {
+            return Ok(self.commons.values[neighbors[nearest]]);
+        }
 
-            // In case of distance too close, early return the exact value
-            if dist < 1e-10 {
-                return nb_value;
-            }
+        let mut value = 0.;
+        let mut norm = 0.;
 
-            value += nb_value / dist;
-            norm += 1. / dist;
+        // the weights are relative to the one of the nearest neighbor, so that they do not
+        // overflow when `d^p` underflows
+        for (&nb_id, dist) in neighbors.iter().zip(distances) {
+            let weight = (min_dist / dist).powf(self.power);
+            value += weight * self.commons.values[nb_id];
+            norm += weight;
         }
 
-        value / norm
+        Ok(value / norm)
     }
 }
 
+/// Inverse distance weighting using all the points for every query
 pub type InvDistAll = InvDistBase<Array1<f64>, All<Array1<f64>>>;
+/// Inverse distance weighting using the nearest neighbors found by [`HNSW`]
 pub type InvDist = InvDistBase<Array1<f64>, HNSW<Array1<f64>>>;
+/// Inverse distance weighting using the exact neighbors found by [`KdTree`]
+pub type InvDistKdTree = InvDistBase<Array1<f64>, KdTree>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scatter::knn::testing::Angle;
+    use ndarray::array;
+    use std::f64::consts::TAU;
+
+    fn square() -> InvDistAll {
+        let points = vec![
+            array![0., 0.],
+            array![1., 0.],
+            array![0., 1.],
+            array![1., 1.],
+        ];
+        InvDistAll::with_all(points, vec![1., 2., 3., 4.]).unwrap()
+    }
+
+    #[test]
+    fn check_nodes() {
+        let interpolator = square();
+        for (point, &value) in interpolator
+            .commons()
+            .points()
+            .iter()
+            .zip(&[1., 2., 3., 4.])
+        {
+            assert_eq!(interpolator.interpolate(&**point).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn check_weights() {
+        let interpolator = square();
+        // equidistant from all the points
+        assert!((interpolator.interpolate(&array![0.5, 0.5]).unwrap() - 2.5).abs() < 1e-12);
+
+        // distances 1 and 2 from the only two points
+        let interpolator =
+            InvDistAll::with_all(vec![array![0.], array![3.]], vec![3., 6.]).unwrap();
+        // (3 / 1 + 6 / 2) / (1 / 1 + 1 / 2)
+        assert!((interpolator.interpolate(&array![1.]).unwrap() - 4.).abs() < 1e-12);
+
+        // the result is a weighted average, therefore within the range of the values
+        let interpolator = square();
+        for query in [array![-3., 2.], array![0.2, 0.9], array![10., 10.]] {
+            let value = interpolator.interpolate(&query).unwrap();
+            assert!((1. ..=4.).contains(&value));
+        }
+    }
+
+    #[test]
+    fn check_settings() {
+        let mut interpolator =
+            InvDistAll::with_all(vec![array![0.], array![3.]], vec![3., 6.]).unwrap();
+        assert_eq!(interpolator.power(), 1.);
+        assert_eq!(interpolator.tolerance(), 1e-10);
+
+        // (3 / 1 + 6 / 4) / (1 / 1 + 1 / 4)
+        interpolator.set_power(2.).unwrap();
+        assert!((interpolator.interpolate(&array![1.]).unwrap() - 3.6).abs() < 1e-12);
+        // large powers approach the value of the nearest neighbor
+        interpolator.set_power(50.).unwrap();
+        assert!((interpolator.interpolate(&array![1.]).unwrap() - 3.).abs() < 1e-12);
+        // d^p underflows just above the tolerance
+        assert_eq!(interpolator.interpolate(&array![1e-7]).unwrap(), 3.);
+        interpolator.set_tolerance(0.).unwrap();
+        interpolator.set_power(2.).unwrap();
+        assert_eq!(interpolator.interpolate(&array![1e-160]).unwrap(), 3.);
+        interpolator.set_power(50.).unwrap();
+
+        interpolator.set_tolerance(0.5).unwrap();
+        assert_eq!(interpolator.interpolate(&array![2.5]).unwrap(), 6.);
+        interpolator.set_tolerance(0.).unwrap();
+        assert_eq!(interpolator.interpolate(&array![0.]).unwrap(), 3.);
+
+        // invalid settings are rejected, keeping the previous ones
+        for power in [0., -1., f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                interpolator.set_power(power),
+                Err(InterpolationError::InvalidConfiguration(_))
+            ));
+        }
+        assert_eq!(interpolator.power(), 50.);
+        for tolerance in [-1e-10, f64::NAN] {
+            assert!(interpolator.set_tolerance(tolerance).is_err());
+        }
+        assert_eq!(interpolator.tolerance(), 0.);
+    }
+
+    #[test]
+    fn check_hnsw() {
+        let points: Vec<_> = (0..400)
+            .map(|i| array![f64::from(i % 20), f64::from(i / 20)])
+            .collect();
+        let values: Vec<_> = points.iter().map(|p| p[0] + 2. * p[1]).collect();
+        let interpolator = InvDist::with_hnsw(points.clone(), values.clone(), 4).unwrap();
+
+        for (point, value) in points.iter().zip(&values).step_by(7) {
+            assert_eq!(interpolator.interpolate(point).unwrap(), *value);
+        }
+        // the four nearest neighbors are the corners of the cell, equidistant from the center
+        let center = interpolator.interpolate(&array![7.5, 3.5]).unwrap();
+        assert!((center - (7.5 + 2. * 3.5)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn check_kdtree() {
+        let points: Vec<_> = (0..400)
+            .map(|i| array![f64::from(i % 20), f64::from(i / 20)])
+            .collect();
+        let values: Vec<_> = points.iter().map(|p| p[0] + 2. * p[1]).collect();
+        let interpolator =
+            InvDistKdTree::with_kdtree(points.clone(), values.clone(), Search::Nearest(4)).unwrap();
+        // the four nearest neighbors are the corners of the cell, equidistant from the center
+        let center = interpolator.interpolate(&array![7.5, 3.5]).unwrap();
+        assert!((center - (7.5 + 2. * 3.5)).abs() < 1e-12);
+
+        let exact = InvDistAll::with_all(points.clone(), values.clone()).unwrap();
+        let interpolator =
+            InvDistKdTree::with_kdtree(points, values, Search::Within(100.)).unwrap();
+        let query = array![3.3, 12.7];
+        let expected = exact.interpolate(&query).unwrap();
+        assert!((interpolator.interpolate(&query).unwrap() - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn check_vptree() {
+        let points: Vec<_> = (0..36).map(|i| Angle(f64::from(i) * TAU / 36.)).collect();
+        let values: Vec<_> = points.iter().map(|angle| angle.0.cos()).collect();
+        let interpolator = InvDistBase::with_vptree(points, values, Search::Nearest(2)).unwrap();
+
+        assert!(interpolator.interpolate(&Angle(TAU / 4.)).unwrap().abs() < 1e-12);
+        // halfway between the last point and the first one, across the boundary of the angles
+        let value = interpolator.interpolate(&Angle(-TAU / 72.)).unwrap();
+        assert!((value - ((TAU / 36.).cos() + 1.) / 2.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn check_errors() {
+        let inputs = array![[0., 0., 1.], [1., 0., 2.]];
+        let mut interpolator = InvDistAll::try_from(inputs).unwrap();
+        assert!(matches!(
+            interpolator.interpolate(&array![0.5, 0.]),
+            Err(InterpolationError::InvalidConfiguration(message)) if message.contains("not set")
+        ));
+
+        interpolator.set_finder(All::new(vec![]));
+        assert!(matches!(
+            interpolator.interpolate(&array![0.5, 0.]),
+            Err(InterpolationError::InvalidConfiguration(message)) if message.contains("neighbors")
+        ));
+
+        interpolator.set_finder(All::new(interpolator.commons().identified_points()));
+        assert!((interpolator.interpolate(&array![0.5, 0.]).unwrap() - 1.5).abs() < 1e-12);
+    }
+}
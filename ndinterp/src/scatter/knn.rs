@@ -3,31 +3,46 @@
 //! To be used as part of a generic scattered interpolation algorithm.
 use crate::metric::Metric;
 
-use petgraph::graph::UnGraph;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use std::sync::Arc;
 
-use std::rc::{Rc, Weak};
+pub mod kdtree;
+#[cfg(test)]
+pub(crate) mod testing;
+pub mod vptree;
+
+pub use kdtree::KdTree;
+pub use vptree::VpTree;
 
+/// Finders of the points contributing to the interpolation at a query
 pub trait KNN {
+    /// Type of the points
     type Point;
 
-    // Returns points identifiers
+    /// Returns the identifiers of the neighbors of `query`
     fn neighbors(&self, query: &Self::Point) -> Vec<usize>;
 }
 
+/// Finder returning all the points, whatever the query
 pub struct All<Point> {
     identifiers: Vec<usize>,
-    points: Vec<Weak<Point>>,
+    points: Vec<Arc<Point>>,
 }
 
 impl<Point> All<Point> {
-    pub fn new(points: Vec<(usize, Rc<Point>)>) -> Self {
+    /// Builds the finder from the `points` paired with their identifiers, see
+    /// [`crate::scatter::Commons::identified_points`]
+    pub fn new(points: Vec<(usize, Arc<Point>)>) -> Self {
+        let (identifiers, points) = points.into_iter().unzip();
         Self {
-            identifiers: points.iter().map(|e| e.0).collect(),
-            points: points.into_iter().map(|e| Rc::downgrade(&e.1)).collect(),
+            identifiers,
+            points,
         }
     }
 
-    pub fn points(&self) -> &Vec<Weak<Point>> {
+    /// The points returned by the finder
+    pub fn points(&self) -> &[Arc<Point>] {
         &self.points
     }
 }
@@ -40,40 +55,442 @@ impl<Point> KNN for All<Point> {
     }
 }
 
+/// The neighbors returned by the tree finders, [`KdTree`] and [`VpTree`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Search {
+    /// The given number of nearest points
+    Nearest(usize),
+    /// The points within the given distance
+    Within(f64),
+}
+
+/// Collects the points found while descending a tree
+trait Found {
+    /// Distance beyond which the points are not collected, squared for [`KdTree`]
+    fn bound(&self) -> f64;
+
+    /// Collects `candidate`, closer than [`Found::bound`]
+    fn push(&mut self, candidate: Candidate);
+}
+
+/// The `k` nearest points, the furthest at the top
+struct Nearest {
+    k: usize,
+    heap: BinaryHeap<Candidate>,
+}
+
+impl Found for Nearest {
+    fn bound(&self) -> f64 {
+        if self.heap.len() < self.k {
+            f64::INFINITY
+        } else {
+            self.heap.peek().map_or(f64::INFINITY, |c| c.distance)
+        }
+    }
+
+    fn push(&mut self, candidate: Candidate) {
+        self.heap.push(candidate);
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+}
+
+/// The points within `bound`
+struct Within {
+    bound: f64,
+    found: Vec<Candidate>,
+}
+
+impl Found for Within {
+    fn bound(&self) -> f64 {
+        self.bound
+    }
+
+    fn push(&mut self, candidate: Candidate) {
+        self.found.push(candidate);
+    }
+}
+
+/// Pseudo-random generator drawing the layers of the nodes of [`HNSW`], seeded with a constant
+/// so that the graph only depends on the points and on the order of their insertion
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number uniformly distributed in (0, 1)
+    fn uniform(&mut self) -> f64 {
+        ((self.next() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+}
+
+/// A node of a search, ordered by its distance from the query
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Candidate {
+    distance: f64,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+/// A point of [`HNSW`] with its links to the other nodes, one list for each of its layers
+struct Node<Point> {
+    identifier: usize,
+    point: Arc<Point>,
+    links: Vec<Vec<usize>>,
+}
+
+/// Finder of the `k` nearest neighbors on a hierarchical navigable small world graph
+///
+/// The points are stored in a hierarchy of proximity graphs, each layer containing a random
+/// subset, exponentially smaller, of the points of the layer below. A query descends greedily
+/// from the top layer, and the neighbors are collected in the bottom one, keeping the `ef`
+/// closest candidates. The search is approximate: its recall, i.e. the fraction of the exact
+/// nearest neighbors found, increases with `ef`, `ef_construction` and `m`, at the price of slower
+/// queries and construction.
+///
+/// See Malkov and Yashunin, <https://arxiv.org/abs/1603.09320>.
 pub struct HNSW<Point: Metric> {
     k: u32,
-    graph: UnGraph<(usize, Weak<Point>), f64>,
+    m: usize,
+    ef_construction: usize,
+    ef: usize,
+    nodes: Vec<Node<Point>>,
+    entry: Option<usize>,
+    rng: SplitMix64,
 }
 
 impl<Point: Metric> HNSW<Point> {
+    /// Default maximum number of links of each node, above the bottom layer
+    pub const M: usize = 16;
+    /// Default number of candidates kept while inserting a point
+    pub const EF_CONSTRUCTION: usize = 100;
+    /// Default number of candidates kept while searching the neighbors of a query
+    pub const EF: usize = 64;
+
+    /// Creates an empty graph looking for `k` neighbors, with the default parameters
     pub fn new(k: u32) -> Self {
-        HNSW {
+        Self::with_parameters(k, Self::M, Self::EF_CONSTRUCTION)
+    }
+
+    /// Creates an empty graph looking for `k` neighbors, whose nodes have at most `m` links
+    /// above the bottom layer, and `2 * m` in it, chosen among the `ef_construction` closest
+    /// candidates. `m` is at least 2
+    pub fn with_parameters(k: u32, m: usize, ef_construction: usize) -> Self {
+        Self {
             k,
-            graph: UnGraph::<(usize, Weak<Point>), f64>::new_undirected(),
+            m: m.max(2),
+            ef_construction: ef_construction.max(1),
+            ef: Self::EF,
+            nodes: Vec::new(),
+            entry: None,
+            rng: SplitMix64(0),
         }
     }
 
+    /// Builds the graph of the `points` paired with their identifiers, see
+    /// [`crate::scatter::Commons::identified_points`], looking for `k` neighbors
+    pub fn build(k: u32, points: Vec<(usize, Arc<Point>)>) -> Self {
+        let mut hnsw = Self::new(k);
+        hnsw.extend(points);
+        hnsw
+    }
+
+    /// Number of neighbors looked for
     pub fn k(&self) -> u32 {
         self.k
     }
+
+    /// Number of candidates kept while searching, at least `k`
+    pub fn ef(&self) -> usize {
+        self.ef.max(self.k as usize)
+    }
+
+    /// Sets the number of candidates kept while searching, trading speed for recall
+    pub fn set_ef(&mut self, ef: usize) {
+        self.ef = ef;
+    }
+
+    /// Number of points in the graph
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the graph contains no points
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Inserts the `points` paired with their identifiers
+    pub fn extend(&mut self, points: impl IntoIterator<Item = (usize, Arc<Point>)>) {
+        for (identifier, point) in points {
+            self.insert(identifier, point);
+        }
+    }
+
+    /// Inserts `point` with the given `identifier`
+    pub fn insert(&mut self, identifier: usize, point: Arc<Point>) {
+        // the probability of reaching each layer is `1 / m` times the one of the layer below
+        let level = (-self.rng.uniform().ln() / (self.m as f64).ln()) as usize;
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            identifier,
+            point: Arc::clone(&point),
+            links: vec![Vec::new(); level + 1],
+        });
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(node);
+            return;
+        };
+        let top = self.nodes[entry].links.len() - 1;
+
+        let mut entries = vec![self.candidate(&point, entry)];
+        for layer in (level + 1..=top).rev() {
+            entries = self.search_layer(&point, &entries, 1, layer);
+        }
+        for layer in (0..=level.min(top)).rev() {
+            entries = self.search_layer(&point, &entries, self.ef_construction, layer);
+            let links = self.select(&entries, self.m);
+            for &neighbor in &links {
+                self.nodes[neighbor].links[layer].push(node);
+                self.prune(neighbor, layer);
+            }
+            self.nodes[node].links[layer] = links;
+        }
+
+        if level > top {
+            self.entry = Some(node);
+        }
+    }
+
+    fn candidate(&self, query: &Point, node: usize) -> Candidate {
+        Candidate {
+            distance: Point::distance(query, &self.nodes[node].point),
+            node,
+        }
+    }
+
+    /// Maximum number of links of each node in `layer`
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            2 * self.m
+        } else {
+            self.m
+        }
+    }
+
+    /// The `ef` nodes of `layer` closest to `query` found starting from `entries`, sorted by
+    /// their distance
+    fn search_layer(
+        &self,
+        query: &Point,
+        entries: &[Candidate],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Candidate> {
+        let mut visited: HashSet<_> = entries.iter().map(|entry| entry.node).collect();
+        let mut candidates: BinaryHeap<_> = entries.iter().copied().map(Reverse).collect();
+        let mut results: BinaryHeap<_> = entries.iter().copied().collect();
+        while results.len() > ef {
+            results.pop();
+        }
+
+        while let Some(Reverse(closest)) = candidates.pop() {
+            let furthest = results.peek().map_or(f64::INFINITY, |c| c.distance);
+            if closest.distance > furthest {
+                break;
+            }
+            for &neighbor in &self.nodes[closest.node].links[layer] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let candidate = self.candidate(query, neighbor);
+                let furthest = results.peek().map_or(f64::INFINITY, |c| c.distance);
+                if results.len() < ef || candidate.distance < furthest {
+                    candidates.push(Reverse(candidate));
+                    results.push(candidate);
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        results.into_sorted_vec()
+    }
+
+    /// Selects at most `m` links among the `candidates`, sorted by distance, preferring the ones
+    /// closer to the query than to the links already selected, which spreads the links in all
+    /// the directions
+    fn select(&self, candidates: &[Candidate], m: usize) -> Vec<usize> {
+        let mut selected: Vec<usize> = Vec::with_capacity(m);
+        let mut discarded = Vec::new();
+        for candidate in candidates {
+            if selected.len() == m {
+                break;
+            }
+            let point = &self.nodes[candidate.node].point;
+            if selected
+                .iter()
+                .all(|&other| Point::distance(point, &self.nodes[other].point) > candidate.distance)
+            {
+                selected.push(candidate.node);
+            } else {
+                discarded.push(candidate.node);
+            }
+        }
+
+        // fill the remaining links with the closest discarded candidates
+        let missing = m.saturating_sub(selected.len());
+        selected.extend(discarded.into_iter().take(missing));
+        selected
+    }
+
+    /// Reduces the links of `node` in `layer` to the maximum allowed, if exceeded
+    fn prune(&mut self, node: usize, layer: usize) {
+        let max = self.max_links(layer);
+        if self.nodes[node].links[layer].len() <= max {
+            return;
+        }
+
+        let point = &self.nodes[node].point;
+        let mut candidates: Vec<_> = self.nodes[node].links[layer]
+            .iter()
+            .map(|&link| self.candidate(point, link))
+            .collect();
+        candidates.sort_unstable();
+        self.nodes[node].links[layer] = self.select(&candidates, max);
+    }
 }
 
-impl<Point: Metric + Clone> KNN for HNSW<Point> {
+impl<Point: Metric> KNN for HNSW<Point> {
     type Point = Point;
 
-    fn neighbors(&self, _query: &Point) -> Vec<usize> {
-        self.graph.raw_nodes().iter().map(|n| n.weight.0).collect()
+    /// The identifiers of the `k` nearest neighbors found, from the closest
+    fn neighbors(&self, query: &Point) -> Vec<usize> {
+        let k = self.k as usize;
+        let Some(entry) = self.entry.filter(|_| k > 0) else {
+            return Vec::new();
+        };
+
+        let mut entries = vec![self.candidate(query, entry)];
+        for layer in (1..self.nodes[entry].links.len()).rev() {
+            entries = self.search_layer(query, &entries, 1, layer);
+        }
+        self.search_layer(query, &entries, self.ef(), 0)
+            .into_iter()
+            .take(k)
+            .map(|candidate| self.nodes[candidate.node].identifier)
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::testing::brute_force;
     use super::*;
+    use ndarray::Array1;
+
+    /// Uniformly distributed points in the unit hypercube of dimension `dim`
+    fn random_points(n: usize, dim: usize, seed: u64) -> Vec<Arc<Array1<f64>>> {
+        let mut rng = SplitMix64(seed);
+        (0..n)
+            .map(|_| Arc::new((0..dim).map(|_| rng.uniform()).collect()))
+            .collect()
+    }
 
     #[test]
     fn test_all() {
         let all = All::<f64>::new(vec![]);
 
         assert_eq!(all.neighbors(&10.).len(), 0);
+
+        let all = All::new(vec![(3, Arc::new(1.)), (5, Arc::new(2.))]);
+        assert_eq!(all.neighbors(&10.), [3, 5]);
+        assert_eq!(*all.points()[1], 2.);
+    }
+
+    #[test]
+    fn hnsw_small() {
+        let hnsw = HNSW::<Array1<f64>>::new(3);
+        assert!(hnsw.is_empty());
+        assert!(hnsw.neighbors(&Array1::zeros(2)).is_empty());
+
+        // fewer points than `k`, all found from the closest
+        let points = [0., 4., 1.].map(|x| (x as usize * 10, Arc::new(Array1::from_elem(1, x))));
+        let mut hnsw = HNSW::new(5);
+        hnsw.extend(points);
+        assert_eq!(hnsw.len(), 3);
+        assert_eq!(hnsw.neighbors(&Array1::from_elem(1, 3.)), [40, 10, 0]);
+
+        let hnsw = HNSW::build(0, vec![(0, Arc::new(Array1::zeros(1)))]);
+        assert!(hnsw.neighbors(&Array1::zeros(1)).is_empty());
+    }
+
+    #[test]
+    fn hnsw_recall() {
+        let k = 10;
+        let points = random_points(1000, 8, 1);
+        let identified: Vec<_> = points.iter().cloned().enumerate().collect();
+        let queries = random_points(100, 8, 2);
+
+        // fraction of the exact nearest neighbors found
+        let recall = |hnsw: &HNSW<Array1<f64>>| {
+            let found: usize = queries
+                .iter()
+                .map(|query| {
+                    let neighbors = hnsw.neighbors(query);
+                    assert_eq!(neighbors.len(), k as usize);
+                    let expected: Vec<_> = brute_force(&points, query)
+                        .into_iter()
+                        .take(k as usize)
+                        .map(|(_, id)| id)
+                        .collect();
+                    neighbors.iter().filter(|id| expected.contains(id)).count()
+                })
+                .sum();
+            found as f64 / (queries.len() * k as usize) as f64
+        };
+
+        let hnsw = HNSW::build(k, identified.clone());
+        let default = recall(&hnsw);
+        assert!(default > 0.98, "recall {default}");
+        // the points in the graph are their own nearest neighbors
+        for (id, point) in points.iter().enumerate().step_by(37) {
+            assert_eq!(hnsw.neighbors(point)[0], id);
+        }
+
+        // a sparse graph needs wider searches, which are always at least as wide as `k`
+        let mut sparse = HNSW::with_parameters(k, 4, 10);
+        sparse.extend(identified);
+        sparse.set_ef(1);
+        assert_eq!(sparse.ef(), k as usize);
+        let narrow = recall(&sparse);
+        sparse.set_ef(200);
+        let wide = recall(&sparse);
+        assert!(narrow < wide && narrow > 0.5, "recall {narrow} {wide}");
+        assert!(wide > 0.98, "recall {wide}");
     }
 }
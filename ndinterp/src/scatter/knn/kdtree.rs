@@ -0,0 +1,280 @@
+//! Exact neighbor searches on k-d trees
+use super::{Candidate, Found, Nearest, Search, Within, KNN};
+use crate::interpolate::InterpolationError;
+
+use ndarray::Array1;
+
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+
+/// Finder of the exact nearest neighbors of vector points, well suited to low dimensions
+///
+/// The tree is balanced and stored implicitly: the points are sorted such that the median of
+/// each range splits it, along the axis of largest spread, into the points below and above it.
+/// The tree is built in O(n log n), and each query descends it pruning the ranges which cannot
+/// contain neighbors, in O(log n) for uniformly distributed points. Since the number of ranges to
+/// visit grows exponentially with the dimension, [`super::HNSW`] is faster in high dimensions.
+pub struct KdTree {
+    search: Search,
+    dim: usize,
+    /// Coordinates of the points, one after the other in the order of the tree
+    coordinates: Vec<f64>,
+    identifiers: Vec<usize>,
+    /// Axis split by each point, in the order of the tree
+    axes: Vec<usize>,
+}
+
+/// Sorts `order` in place as required by [`KdTree`], setting the split axes in `axes`
+fn partition(points: &[Arc<Array1<f64>>], order: &mut [usize], axes: &mut [usize], dim: usize) {
+    if order.len() <= 1 {
+        axes.fill(0);
+        return;
+    }
+
+    let spread = |axis: usize| {
+        let (min, max) = order
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &i| {
+                (min.min(points[i][axis]), max.max(points[i][axis]))
+            });
+        max - min
+    };
+    let axis = (0..dim)
+        .max_by(|&a, &b| spread(a).total_cmp(&spread(b)))
+        .unwrap_or(0);
+
+    let mid = order.len() / 2;
+    order.select_nth_unstable_by(mid, |&i, &j| points[i][axis].total_cmp(&points[j][axis]));
+    axes[mid] = axis;
+
+    let (lower, upper) = order.split_at_mut(mid);
+    let (lower_axes, upper_axes) = axes.split_at_mut(mid);
+    partition(points, lower, lower_axes, dim);
+    partition(points, &mut upper[1..], &mut upper_axes[1..], dim);
+}
+
+impl KdTree {
+    /// Builds the tree of the `points` paired with their identifiers, see
+    /// [`crate::scatter::Commons::identified_points`], returning the neighbors selected by
+    /// `search` when used as a finder. The points must have the same dimension
+    pub fn build(
+        search: Search,
+        points: Vec<(usize, Arc<Array1<f64>>)>,
+    ) -> Result<Self, InterpolationError> {
+        let dim = points.first().map_or(0, |(_, point)| point.len());
+        if let Some((id, point)) = points.iter().find(|(_, point)| point.len() != dim) {
+            return Err(InterpolationError::InvalidGrid(format!(
+                "the point {id} has dimension {}, instead of {dim}",
+                point.len()
+            )));
+        }
+
+        let (ids, points): (Vec<_>, Vec<_>) = points.into_iter().unzip();
+        let mut order: Vec<_> = (0..points.len()).collect();
+        let mut axes = vec![0; points.len()];
+        partition(&points, &mut order, &mut axes, dim);
+
+        Ok(Self {
+            search,
+            dim,
+            coordinates: order
+                .iter()
+                .flat_map(|&i| points[i].iter())
+                .copied()
+                .collect(),
+            identifiers: order.iter().map(|&i| ids[i]).collect(),
+            axes,
+        })
+    }
+
+    /// The neighbors returned as a finder
+    pub fn search(&self) -> Search {
+        self.search
+    }
+
+    /// Sets the neighbors returned as a finder
+    pub fn set_search(&mut self, search: Search) {
+        self.search = search;
+    }
+
+    /// Number of points in the tree
+    pub fn len(&self) -> usize {
+        self.identifiers.len()
+    }
+
+    /// Whether the tree contains no points
+    pub fn is_empty(&self) -> bool {
+        self.identifiers.is_empty()
+    }
+
+    /// The identifiers of the `k` points nearest to `query`, with their distances, from the
+    /// closest. Ties are broken arbitrarily
+    pub fn nearest(&self, query: &[f64], k: usize) -> Vec<(usize, f64)> {
+        let mut found = Nearest {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        };
+        if k > 0 {
+            self.descend(query, 0, self.len(), &mut found);
+        }
+        self.sorted(found.heap.into_vec())
+    }
+
+    /// The identifiers of the points within `radius` from `query`, with their distances, from
+    /// the closest
+    pub fn within(&self, query: &[f64], radius: f64) -> Vec<(usize, f64)> {
+        let mut found = Within {
+            bound: radius * radius,
+            found: Vec::new(),
+        };
+        if radius >= 0. {
+            self.descend(query, 0, self.len(), &mut found);
+        }
+        self.sorted(found.found)
+    }
+
+    fn point(&self, node: usize) -> &[f64] {
+        &self.coordinates[node * self.dim..(node + 1) * self.dim]
+    }
+
+    fn sorted(&self, mut candidates: Vec<Candidate>) -> Vec<(usize, f64)> {
+        candidates.sort_unstable();
+        candidates
+            .into_iter()
+            .map(|c| (self.identifiers[c.node], c.distance.sqrt()))
+            .collect()
+    }
+
+    /// Collects in `found` the points of the subtree spanning the range from `start` to `end`,
+    /// visiting first the side of the split containing `query`
+    fn descend(&self, query: &[f64], start: usize, end: usize, found: &mut impl Found) {
+        if start >= end {
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let point = self.point(mid);
+
+        let distance = query
+            .iter()
+            .zip(point)
+            .map(|(x, y)| (x - y) * (x - y))
+            .sum();
+        if distance <= found.bound() {
+            found.push(Candidate {
+                distance,
+                node: mid,
+            });
+        }
+
+        let axis = self.axes[mid];
+        let offset = query[axis] - point[axis];
+        let (near, far) = if offset < 0. {
+            ((start, mid), (mid + 1, end))
+        } else {
+            ((mid + 1, end), (start, mid))
+        };
+        self.descend(query, near.0, near.1, found);
+        // the points beyond the split are at least `offset` away from the query
+        if offset * offset <= found.bound() {
+            self.descend(query, far.0, far.1, found);
+        }
+    }
+}
+
+impl KNN for KdTree {
+    type Point = Array1<f64>;
+
+    /// The identifiers of the neighbors selected by [`KdTree::search`], from the closest. Queries
+    /// whose dimension differs from the one of the points have no neighbors
+    fn neighbors(&self, query: &Array1<f64>) -> Vec<usize> {
+        if query.len() != self.dim {
+            return Vec::new();
+        }
+        let query = query.to_vec();
+        let found = match self.search {
+            Search::Nearest(k) => self.nearest(&query, k),
+            Search::Within(radius) => self.within(&query, radius),
+        };
+        found.into_iter().map(|(id, _)| id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::brute_force;
+    use super::super::SplitMix64;
+    use super::*;
+    use crate::metric::Metric;
+    use ndarray::array;
+
+    #[test]
+    fn exact() {
+        let mut rng = SplitMix64(3);
+        let mut uniform = |dim: usize| -> Array1<f64> { (0..dim).map(|_| rng.uniform()).collect() };
+        for dim in [2, 3, 6] {
+            let mut points: Vec<_> = (0..500).map(|_| Arc::new(uniform(dim))).collect();
+            // duplicated points and coordinates
+            points.push(Arc::clone(&points[7]));
+            points.push(Arc::new(Array1::from_elem(dim, points[3][0])));
+            let tree = KdTree::build(
+                Search::Nearest(1),
+                points.iter().cloned().enumerate().collect(),
+            )
+            .unwrap();
+            assert_eq!(tree.len(), points.len());
+
+            for _ in 0..50 {
+                let query = uniform(dim);
+                let expected = brute_force(&points, &query);
+
+                let nearest = tree.nearest(query.as_slice().unwrap(), 8);
+                assert_eq!(nearest.len(), 8);
+                for ((id, distance), (exact, _)) in nearest.iter().zip(&expected) {
+                    assert_eq!(distance, exact);
+                    assert_eq!(Array1::distance(&query, &points[*id]), *exact);
+                }
+
+                let radius = 0.2 * dim as f64;
+                let within = tree.within(query.as_slice().unwrap(), radius);
+                let expected: Vec<_> = expected
+                    .iter()
+                    .filter(|(distance, _)| *distance <= radius)
+                    .collect();
+                assert_eq!(within.len(), expected.len());
+                assert!(within.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+            }
+        }
+    }
+
+    #[test]
+    fn finder() {
+        let points = vec![
+            array![0., 0.],
+            array![1., 0.],
+            array![0., 2.],
+            array![3., 3.],
+        ];
+        let identified: Vec<_> = points.into_iter().map(Arc::new).enumerate().collect();
+        let mut tree = KdTree::build(Search::Nearest(2), identified.clone()).unwrap();
+        assert_eq!(tree.neighbors(&array![0.9, 0.1]), [1, 0]);
+        assert_eq!(tree.neighbors(&array![10., 10.]), [3, 2]);
+        assert!(tree.neighbors(&array![0.]).is_empty());
+
+        tree.set_search(Search::Within(1.2));
+        assert_eq!(tree.search(), Search::Within(1.2));
+        assert_eq!(tree.neighbors(&array![0., 0.5]), [0, 1]);
+        assert!(tree.neighbors(&array![10., 0.]).is_empty());
+
+        tree.set_search(Search::Nearest(10));
+        assert_eq!(tree.neighbors(&array![0., 0.]).len(), 4);
+        assert!(tree.nearest(&[0., 0.], 0).is_empty());
+
+        let empty = KdTree::build(Search::Nearest(3), vec![]).unwrap();
+        assert!(empty.is_empty());
+        assert!(empty.nearest(&[], 3).is_empty());
+
+        let mut mixed = identified;
+        mixed.push((4, Arc::new(array![1.])));
+        assert!(KdTree::build(Search::Nearest(1), mixed).is_err());
+    }
+}
@@ -0,0 +1,30 @@
+//! Helpers shared by the tests of the finders and of the interpolators using them
+use crate::metric::Metric;
+
+use std::f64::consts::TAU;
+use std::sync::Arc;
+
+/// A point on a circle, with the distance along the circle
+#[derive(Clone, Debug)]
+pub(crate) struct Angle(pub(crate) f64);
+
+impl Metric for Angle {
+    fn distance(a: &Self, b: &Self) -> f64 {
+        let difference = (a.0 - b.0).rem_euclid(TAU);
+        difference.min(TAU - difference)
+    }
+}
+
+/// The distances of `points` from `query`, with the positions of the points, sorted
+pub(crate) fn brute_force<Point: Metric>(
+    points: &[Arc<Point>],
+    query: &Point,
+) -> Vec<(f64, usize)> {
+    let mut all: Vec<_> = points
+        .iter()
+        .enumerate()
+        .map(|(id, point)| (Point::distance(query, point), id))
+        .collect();
+    all.sort_by(|a, b| a.0.total_cmp(&b.0));
+    all
+}
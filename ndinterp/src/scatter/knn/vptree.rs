@@ -0,0 +1,244 @@
+//! Exact neighbor searches on vantage-point trees
+use super::{Candidate, Found, Nearest, Search, Within, KNN};
+use crate::metric::Metric;
+
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+
+/// Finder of the exact nearest neighbors in any metric space
+///
+/// Unlike [`super::KdTree`], the tree only relies on [`Metric::distance`], therefore it works for
+/// points without coordinates, as long as their distance satisfies the triangle inequality.
+///
+/// The tree is balanced and stored implicitly: each range starts with its vantage point, followed
+/// by the half of the other points closest to it, within the median distance, and then by the
+/// farther ones. The tree is built in O(n log n) distance evaluations, and each query prunes the
+/// halves which, by the triangle inequality, cannot contain neighbors.
+pub struct VpTree<Point: Metric> {
+    search: Search,
+    identifiers: Vec<usize>,
+    points: Vec<Arc<Point>>,
+    /// Median distance from each vantage point of the points of its range, in the order of the
+    /// tree
+    thresholds: Vec<f64>,
+}
+
+/// Sorts `points` in place as required by [`VpTree`], setting the median distances in
+/// `thresholds`
+fn partition<Point: Metric>(points: &mut [(usize, Arc<Point>)], thresholds: &mut [f64]) {
+    let Some(((_, vantage), others)) = points.split_first_mut() else {
+        return;
+    };
+    if others.is_empty() {
+        thresholds[0] = 0.;
+        return;
+    }
+
+    let mut distances: Vec<_> = others
+        .iter()
+        .map(|(_, point)| Point::distance(vantage, point))
+        .zip(0..)
+        .collect();
+    let inner = others.len().div_ceil(2);
+    distances.select_nth_unstable_by(inner - 1, |a, b| a.0.total_cmp(&b.0));
+    thresholds[0] = distances[inner - 1].0;
+
+    // apply the permutation found by the selection
+    let sorted: Vec<_> = distances
+        .iter()
+        .map(|&(_, index)| others[index].clone())
+        .collect();
+    others.clone_from_slice(&sorted);
+
+    let (inside, outside) = others.split_at_mut(inner);
+    let (inside_thresholds, outside_thresholds) = thresholds[1..].split_at_mut(inner);
+    partition(inside, inside_thresholds);
+    partition(outside, outside_thresholds);
+}
+
+impl<Point: Metric> VpTree<Point> {
+    /// Builds the tree of the `points` paired with their identifiers, see
+    /// [`crate::scatter::Commons::identified_points`], returning the neighbors selected by
+    /// `search` when used as a finder
+    pub fn build(search: Search, mut points: Vec<(usize, Arc<Point>)>) -> Self {
+        let mut thresholds = vec![0.; points.len()];
+        partition(&mut points, &mut thresholds);
+        let (identifiers, points) = points.into_iter().unzip();
+
+        Self {
+            search,
+            identifiers,
+            points,
+            thresholds,
+        }
+    }
+
+    /// The neighbors returned as a finder
+    pub fn search(&self) -> Search {
+        self.search
+    }
+
+    /// Sets the neighbors returned as a finder
+    pub fn set_search(&mut self, search: Search) {
+        self.search = search;
+    }
+
+    /// Number of points in the tree
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Whether the tree contains no points
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The identifiers of the `k` points nearest to `query`, with their distances, from the
+    /// closest. Ties are broken arbitrarily
+    pub fn nearest(&self, query: &Point, k: usize) -> Vec<(usize, f64)> {
+        let mut found = Nearest {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        };
+        if k > 0 {
+            self.descend(query, 0, self.len(), &mut found);
+        }
+        self.sorted(found.heap.into_vec())
+    }
+
+    /// The identifiers of the points within `radius` from `query`, with their distances, from
+    /// the closest
+    pub fn within(&self, query: &Point, radius: f64) -> Vec<(usize, f64)> {
+        let mut found = Within {
+            bound: radius,
+            found: Vec::new(),
+        };
+        if radius >= 0. {
+            self.descend(query, 0, self.len(), &mut found);
+        }
+        self.sorted(found.found)
+    }
+
+    fn sorted(&self, mut candidates: Vec<Candidate>) -> Vec<(usize, f64)> {
+        candidates.sort_unstable();
+        candidates
+            .into_iter()
+            .map(|c| (self.identifiers[c.node], c.distance))
+            .collect()
+    }
+
+    /// Collects in `found` the points of the subtree spanning the range from `start` to `end`,
+    /// visiting first the half more likely to contain the neighbors of `query`
+    fn descend(&self, query: &Point, start: usize, end: usize, found: &mut impl Found) {
+        if start >= end {
+            return;
+        }
+        let distance = Point::distance(query, &self.points[start]);
+        if distance <= found.bound() {
+            found.push(Candidate {
+                distance,
+                node: start,
+            });
+        }
+
+        let threshold = self.thresholds[start];
+        let inner = (end - start - 1).div_ceil(2);
+        let inside = (start + 1, start + 1 + inner);
+        let outside = (start + 1 + inner, end);
+        // by the triangle inequality, the points inside are at least `distance - threshold` away
+        // from the query, and the ones outside at least `threshold - distance`
+        if distance < threshold {
+            self.descend(query, inside.0, inside.1, found);
+            if threshold - distance <= found.bound() {
+                self.descend(query, outside.0, outside.1, found);
+            }
+        } else {
+            self.descend(query, outside.0, outside.1, found);
+            if distance - threshold <= found.bound() {
+                self.descend(query, inside.0, inside.1, found);
+            }
+        }
+    }
+}
+
+impl<Point: Metric> KNN for VpTree<Point> {
+    type Point = Point;
+
+    /// The identifiers of the neighbors selected by [`VpTree::search`], from the closest
+    fn neighbors(&self, query: &Point) -> Vec<usize> {
+        let found = match self.search {
+            Search::Nearest(k) => self.nearest(query, k),
+            Search::Within(radius) => self.within(query, radius),
+        };
+        found.into_iter().map(|(id, _)| id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{brute_force, Angle};
+    use super::super::SplitMix64;
+    use super::*;
+    use ndarray::Array1;
+    use std::f64::consts::TAU;
+
+    fn check<Point: Metric>(points: Vec<Arc<Point>>, queries: &[Point], radius: f64) {
+        let tree = VpTree::build(
+            Search::Nearest(1),
+            points.iter().cloned().enumerate().collect(),
+        );
+        assert_eq!(tree.len(), points.len());
+
+        for query in queries {
+            let expected = brute_force(&points, query);
+            let nearest = tree.nearest(query, 7);
+            assert_eq!(nearest.len(), 7.min(points.len()));
+            for ((id, distance), (exact, _)) in nearest.iter().zip(&expected) {
+                assert_eq!(distance, exact);
+                assert_eq!(Point::distance(query, &points[*id]), *exact);
+            }
+
+            let within = tree.within(query, radius);
+            let count = expected.iter().filter(|(d, _)| *d <= radius).count();
+            assert_eq!(within.len(), count);
+            assert!(within
+                .iter()
+                .all(|&(id, d)| d <= radius && Point::distance(query, &points[id]) == d));
+        }
+    }
+
+    #[test]
+    fn exact() {
+        let mut rng = SplitMix64(5);
+
+        let angles: Vec<_> = (0..300)
+            .map(|_| Arc::new(Angle(TAU * rng.uniform())))
+            .collect();
+        let queries: Vec<_> = (0..30).map(|_| Angle(10. * rng.uniform() - 5.)).collect();
+        check(angles, &queries, 0.1);
+
+        for dim in [2, 5] {
+            let mut uniform = || -> Array1<f64> { (0..dim).map(|_| rng.uniform()).collect() };
+            let mut points: Vec<_> = (0..400).map(|_| Arc::new(uniform())).collect();
+            points.push(Arc::clone(&points[11]));
+            let queries: Vec<_> = (0..30).map(|_| uniform()).collect();
+            check(points, &queries, 0.3);
+        }
+
+        check(Vec::<Arc<Angle>>::new(), &[Angle(0.)], 1.);
+        check(vec![Arc::new(Angle(1.))], &[Angle(0.)], 1.);
+    }
+
+    #[test]
+    fn finder() {
+        let points = [0.1, 6.2, 3.0, 1.0].map(|x| Arc::new(Angle(x)));
+        let mut tree = VpTree::build(Search::Nearest(2), points.into_iter().enumerate().collect());
+        // the closest point to 0 is 6.2, across the boundary of the angles
+        assert_eq!(tree.neighbors(&Angle(0.)), [1, 0]);
+
+        tree.set_search(Search::Within(1.));
+        assert_eq!(tree.search(), Search::Within(1.));
+        assert_eq!(tree.neighbors(&Angle(0.5)), [0, 3, 1]);
+        assert!(tree.neighbors(&Angle(4.5)).is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+//! Serialization of the scattered interpolators through `serde`
+//!
+//! As for the grids, the interpolators go through an explicitly versioned layout, sharing the
+//! version of the grids, see [`crate::grid`].
+
+use super::invdist::check_tolerance;
+use super::knn::{KdTree, Search};
+use super::{Commons, ModifiedShepard};
+use crate::grid::serialization::{check_version, FORMAT_VERSION};
+use ndarray::Array1;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+
+/// Serialized layout of [`ModifiedShepard`], version 1:
+///     - `points`: the coordinates of the points
+///     - `values`: the values at the points
+///     - `nodal`: the coefficients of the nodal function of each point
+///     - `nw`: the number of nodal functions averaged at each query
+///     - `tolerance`: the distance below which a query coincides with a point
+#[derive(Deserialize, Serialize)]
+struct ShepardRepr<'a> {
+    version: u32,
+    points: Vec<Vec<f64>>,
+    values: Cow<'a, [f64]>,
+    nodal: Cow<'a, [Vec<f64>]>,
+    nw: usize,
+    tolerance: f64,
+}
+
+impl Serialize for ModifiedShepard {
+    fn serialize<Se: Serializer>(&self, serializer: Se) -> Result<Se::Ok, Se::Error> {
+        ShepardRepr {
+            version: FORMAT_VERSION,
+            points: self.commons.points().iter().map(|p| p.to_vec()).collect(),
+            values: Cow::Borrowed(self.commons.values()),
+            nodal: Cow::Borrowed(&self.nodal),
+            nw: self.nw,
+            tolerance: self.tolerance,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ModifiedShepard {
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let repr = ShepardRepr::deserialize(deserializer)?;
+
+        check_version(repr.version)?;
+
+        let points: Vec<_> = repr.points.into_iter().map(Array1::from).collect();
+        let dim = points.first().map_or(0, Array1::len);
+        if repr.nodal.len() != points.len() {
+            return Err(De::Error::invalid_length(
+                repr.nodal.len(),
+                &"one nodal function per point",
+            ));
+        }
+        // constant, linear or quadratic nodal functions
+        let sizes = [0, dim, dim * (dim + 3) / 2];
+        if let Some(coefficients) = repr.nodal.iter().find(|c| !sizes.contains(&c.len())) {
+            return Err(De::Error::custom(format!(
+                "a nodal function has {} coefficients in {dim} dimensions",
+                coefficients.len()
+            )));
+        }
+        if repr.nw == 0 {
+            return Err(De::Error::custom(
+                "at least one nodal function must be averaged",
+            ));
+        }
+        let tolerance = check_tolerance(repr.tolerance).map_err(De::Error::custom)?;
+
+        let mut commons =
+            Commons::new(points, repr.values.into_owned()).map_err(De::Error::custom)?;
+        let tree = KdTree::build(Search::Nearest(repr.nw), commons.identified_points())
+            .map_err(De::Error::custom)?;
+        commons.set_finder(tree);
+
+        Ok(ModifiedShepard {
+            commons,
+            nodal: repr.nodal.into_owned(),
+            dim,
+            nw: repr.nw,
+            tolerance,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpolate::Interpolator;
+    use ndarray::array;
+
+    #[test]
+    fn check_roundtrip() {
+        let points: Vec<_> = (0..50)
+            .map(|i| {
+                let i = f64::from(i);
+                array![
+                    (0.5 + i * 0.618_033_988_749_895) % 1.,
+                    (0.5 + i * 0.754_877_666_246_693) % 1.
+                ]
+            })
+            .collect();
+        let values = points.iter().map(|p| (3. * p[0]).sin() * p[1]).collect();
+        let mut interpolator = ModifiedShepard::new(points, values).unwrap();
+        interpolator.set_tolerance(1e-3).unwrap();
+
+        let json = serde_json::to_string(&interpolator).unwrap();
+        let loaded: ModifiedShepard = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.tolerance(), 1e-3);
+        for query in [array![0.31, 0.77], array![0.02, 0.5], array![0.9, 0.11]] {
+            let value = loaded.interpolate(&query).unwrap();
+            assert!((value - interpolator.interpolate(&query).unwrap()).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn check_invalid_data() {
+        // data written with version 1 of the layout must stay readable
+        let json = r#"{"version":1,"points":[[0.0],[1.0]],"values":[1.0,3.0],
+            "nodal":[[2.0],[]],"nw":1,"tolerance":0.0}"#;
+        let interpolator: ModifiedShepard = serde_json::from_str(json).unwrap();
+        // equidistant from both points, the average of their nodal functions 1 + 2 x and 3
+        assert!((interpolator.interpolate(&array![0.5]).unwrap() - 2.5).abs() < 1e-12);
+
+        for invalid in [
+            json.replace(r#""version":1"#, r#""version":0"#),
+            json.replace("[[2.0],[]]", "[[2.0]]"),
+            json.replace("[[2.0],[]]", "[[2.0, 1.0, 0.5],[]]"),
+            json.replace("[1.0,3.0]", "[1.0]"),
+            json.replace("[[0.0],[1.0]]", "[[0.0],[1.0,2.0]]"),
+            json.replace(r#""nw":1"#, r#""nw":0"#),
+            json.replace(r#""tolerance":0.0"#, r#""tolerance":-1.0"#),
+        ] {
+            assert!(
+                serde_json::from_str::<ModifiedShepard>(&invalid).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -0,0 +1,351 @@
+//! Modified Shepard's method, with local weights and quadratic nodal functions
+use super::{
+    commons::Commons,
+    invdist::check_tolerance,
+    knn::{KdTree, Search},
+};
+use crate::interpolate::{check_query, InterpolationError, Interpolator};
+
+use ndarray::Array1;
+
+/// Interpolates as the weighted average of quadratic nodal functions, one for each point
+///
+/// Each nodal function takes the value of its point there, and fits the values of its `nq`
+/// nearest neighbors by weighted least squares. The interpolant at a query averages the nodal
+/// functions of its `nw` nearest neighbors, with weights `((r - d) / (r d))^2` which vanish at
+/// the radius `r`, the distance of the following neighbor. Unlike [`super::InvDistBase`] the
+/// interpolant is smooth, local, and reproduces quadratic functions exactly.
+///
+/// The nodal functions fall back to linear ones, and then to the constant value of their point,
+/// when the neighbors do not determine the coefficients, e.g. if there are too few of them.
+///
+/// With the `serde` feature the interpolator can be serialized together with its fitted nodal
+/// functions, while the tree of the neighbors is rebuilt when it is deserialized.
+///
+/// See Franke and Nielson, <https://doi.org/10.1002/nme.1620151110>, and Renka,
+/// <https://doi.org/10.1145/45054.45055>.
+pub struct ModifiedShepard {
+    pub(super) commons: Commons<Array1<f64>, KdTree>,
+    /// Coefficients of the linear terms of the nodal functions, followed by the ones of the
+    /// quadratic terms, if determined
+    pub(super) nodal: Vec<Vec<f64>>,
+    pub(super) dim: usize,
+    pub(super) nw: usize,
+    pub(super) tolerance: f64,
+}
+
+/// Products of the pairs of coordinates of `dx`, in the order of the nodal coefficients
+fn quadratic(dx: &[f64]) -> impl Iterator<Item = f64> + '_ {
+    (0..dx.len()).flat_map(move |a| dx[a..].iter().map(move |x| dx[a] * x))
+}
+
+/// The neighbors among the first `count` of the non-empty `neighbors`, sorted by distance, with a
+/// positive weight, and the radius of the support of the weights
+///
+/// The radius is the distance of the neighbor following them, so that the neighbors tied with it
+/// have no weight, and the weights are continuous when the neighbors change. When there is no
+/// such neighbor, or all the neighbors are at the same distance, all of them are kept and the
+/// radius is slightly more than the distance of the furthest one.
+fn support(mut neighbors: Vec<(usize, f64)>, count: usize) -> (Vec<(usize, f64)>, f64) {
+    let count = count.min(neighbors.len());
+    match neighbors.get(count) {
+        Some(&(_, radius)) if radius > neighbors[0].1 => {
+            neighbors.retain(|&(_, distance)| distance < radius);
+            (neighbors, radius)
+        }
+        _ => {
+            neighbors.truncate(count + 1);
+            let radius = 1.1 * neighbors.iter().map(|n| n.1).fold(0., f64::max);
+            (neighbors, radius)
+        }
+    }
+}
+
+/// Solves the linear system with symmetric matrix `a`, with `n` rows, and right-hand side `b`,
+/// or returns `None` if it is numerically singular
+fn solve(mut a: Vec<f64>, mut b: Vec<f64>, n: usize) -> Option<Vec<f64>> {
+    let scale = (0..n).map(|i| a[i * n + i].abs()).fold(0., f64::max);
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&i, &j| a[i * n + column].abs().total_cmp(&a[j * n + column].abs()))?;
+        if a[pivot * n + column].abs() <= 1e-12 * scale {
+            return None;
+        }
+        for k in 0..n {
+            a.swap(column * n + k, pivot * n + k);
+        }
+        b.swap(column, pivot);
+
+        for row in column + 1..n {
+            let factor = a[row * n + column] / a[column * n + column];
+            for k in column..n {
+                a[row * n + k] -= factor * a[column * n + k];
+            }
+            b[row] -= factor * b[column];
+        }
+    }
+
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| a[row * n + k] * b[k]).sum();
+        b[row] = (b[row] - sum) / a[row * n + row];
+    }
+    Some(b)
+}
+
+impl ModifiedShepard {
+    /// Default number of neighbors fitted by each nodal function
+    pub const NQ: usize = 13;
+    /// Default number of nodal functions averaged at each query
+    pub const NW: usize = 19;
+    /// Default distance below which a query coincides with a point
+    pub const TOLERANCE: f64 = 1e-10;
+
+    /// Interpolates the `values` at the `points`, with the default numbers of neighbors
+    pub fn new(points: Vec<Array1<f64>>, values: Vec<f64>) -> Result<Self, InterpolationError> {
+        Self::with_neighbors(points, values, Self::NQ, Self::NW)
+    }
+
+    /// Interpolates the `values` at the `points`, fitting each nodal function to `nq` neighbors
+    /// and averaging `nw` nodal functions at each query. To determine the quadratic terms `nq`
+    /// must be at least `d (d + 3) / 2` in `d` dimensions.
+    pub fn with_neighbors(
+        points: Vec<Array1<f64>>,
+        values: Vec<f64>,
+        nq: usize,
+        nw: usize,
+    ) -> Result<Self, InterpolationError> {
+        let mut commons = Commons::new(points, values)?;
+        let tree = KdTree::build(Search::Nearest(nw), commons.identified_points())?;
+        let dim = commons.points().first().map_or(0, |point| point.len());
+
+        let nodal = (0..commons.points().len())
+            .map(|node| Self::fit(&commons, &tree, node, nq))
+            .collect();
+        commons.set_finder(tree);
+
+        Ok(Self {
+            commons,
+            nodal,
+            dim,
+            nw: nw.max(1),
+            tolerance: Self::TOLERANCE,
+        })
+    }
+
+    /// Coefficients of the nodal function of `node`, fitted to its `nq` nearest neighbors
+    fn fit(
+        commons: &Commons<Array1<f64>, KdTree>,
+        tree: &KdTree,
+        node: usize,
+        nq: usize,
+    ) -> Vec<f64> {
+        let point = &commons.points()[node];
+        let value = commons.values()[node];
+        let dim = point.len();
+        let neighbors: Vec<_> = tree
+            .nearest(&point.to_vec(), nq + 2)
+            .into_iter()
+            .filter(|&(id, _)| id != node)
+            .collect();
+        if neighbors.is_empty() || nq == 0 {
+            return Vec::new();
+        }
+        let (neighbors, radius) = support(neighbors, nq);
+
+        // the rows of the weighted least squares problem, with the linear terms first
+        let rows: Vec<(Vec<f64>, f64)> = neighbors
+            .iter()
+            .filter(|&&(_, distance)| distance > 0.)
+            .map(|&(id, distance)| {
+                let weight = (radius - distance) / (radius * distance);
+                let dx: Vec<_> = (&*commons.points()[id] - &**point).to_vec();
+                let terms = dx.iter().copied().chain(quadratic(&dx));
+                let row = terms.map(|term| weight * term).collect();
+                (row, weight * (commons.values()[id] - value))
+            })
+            .collect();
+
+        // quadratic, and otherwise linear, nodal function
+        for unknowns in [dim * (dim + 3) / 2, dim] {
+            if rows.len() < unknowns {
+                continue;
+            }
+            let mut a = vec![0.; unknowns * unknowns];
+            let mut b = vec![0.; unknowns];
+            for (row, rhs) in &rows {
+                for i in 0..unknowns {
+                    b[i] += row[i] * rhs;
+                    for j in 0..unknowns {
+                        a[i * unknowns + j] += row[i] * row[j];
+                    }
+                }
+            }
+            if let Some(coefficients) = solve(a, b, unknowns) {
+                return coefficients;
+            }
+        }
+        Vec::new()
+    }
+
+    /// Value of the nodal function of `node` at `query`
+    fn nodal(&self, node: usize, query: &[f64]) -> f64 {
+        let point = &self.commons.points()[node];
+        let dx: Vec<_> = query.iter().zip(point.iter()).map(|(x, y)| x - y).collect();
+        let coefficients = &self.nodal[node];
+        let terms = dx.iter().copied().chain(quadratic(&dx));
+        self.commons.values()[node]
+            + coefficients
+                .iter()
+                .zip(terms)
+                .map(|(c, term)| c * term)
+                .sum::<f64>()
+    }
+
+    /// The points and their values
+    pub fn commons(&self) -> &Commons<Array1<f64>, KdTree> {
+        &self.commons
+    }
+
+    /// Distance below which a query coincides with a point
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    /// Sets the distance below which a query coincides with a point, and is given its value. The
+    /// tolerance must not be negative
+    pub fn set_tolerance(&mut self, tolerance: f64) -> Result<(), InterpolationError> {
+        self.tolerance = check_tolerance(tolerance)?;
+        Ok(())
+    }
+}
+
+impl Interpolator<&Array1<f64>> for ModifiedShepard {
+    fn interpolate(&self, query: &Array1<f64>) -> Result<f64, InterpolationError> {
+        let query = query.to_vec();
+        check_query(&query, self.dim)?;
+        let neighbors = self.commons.finder()?.nearest(&query, self.nw + 1);
+        if neighbors.is_empty() {
+            return Err(InterpolationError::InvalidConfiguration(
+                "no neighbors were found for the query".to_owned(),
+            ));
+        }
+        let (neighbors, radius) = support(neighbors, self.nw);
+
+        let mut value = 0.;
+        let mut norm = 0.;
+        for (node, distance) in neighbors {
+            if distance <= self.tolerance {
+                return Ok(self.commons.values()[node]);
+            }
+            let weight = ((radius - distance) / (radius * distance)).powi(2);
+            value += weight * self.nodal(node, &query);
+            norm += weight;
+        }
+
+        Ok(value / norm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scatter::InvDistAll;
+    use ndarray::array;
+
+    /// Points scattered over the unit square, on a deterministic low-discrepancy sequence
+    fn scattered(n: usize) -> Vec<Array1<f64>> {
+        (0..n)
+            .map(|i| {
+                let i = i as f64;
+                array![
+                    (0.5 + i * 0.618_033_988_749_895) % 1.,
+                    (0.5 + i * 0.754_877_666_246_693) % 1.
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_quadratic() {
+        let f =
+            |x: &Array1<f64>| 1. + x[0] - 2. * x[1] + x[0] * x[0] + 0.5 * x[0] * x[1] - x[1] * x[1];
+        let points = scattered(200);
+        let values = points.iter().map(f).collect();
+        let interpolator = ModifiedShepard::new(points.clone(), values).unwrap();
+
+        for point in points.iter().step_by(17) {
+            assert_eq!(interpolator.interpolate(point).unwrap(), f(point));
+        }
+        // the nodal functions are exact, therefore also their average
+        for query in [array![0.31, 0.77], array![0.02, 0.5], array![0.9, 0.11]] {
+            let value = interpolator.interpolate(&query).unwrap();
+            assert!((value - f(&query)).abs() < 1e-9, "{value} {}", f(&query));
+        }
+    }
+
+    #[test]
+    fn check_accuracy() {
+        let f = |x: &Array1<f64>| (3. * x[0]).sin() * (2. * x[1]).cos();
+        let points = scattered(300);
+        let values: Vec<_> = points.iter().map(f).collect();
+        let shepard = ModifiedShepard::new(points.clone(), values.clone()).unwrap();
+        let mut invdist = InvDistAll::with_all(points, values).unwrap();
+        invdist.set_power(2.).unwrap();
+
+        let queries = scattered(350).split_off(300);
+        let error = |eval: &dyn Fn(&Array1<f64>) -> f64| {
+            queries
+                .iter()
+                .map(|query| (eval(query) - f(query)).abs())
+                .fold(0., f64::max)
+        };
+        let shepard = error(&|query| shepard.interpolate(query).unwrap());
+        let invdist = error(&|query| invdist.interpolate(query).unwrap());
+        assert!(shepard < 5e-3, "{shepard}");
+        assert!(shepard < invdist / 10., "{shepard} {invdist}");
+    }
+
+    #[test]
+    fn check_ties() {
+        let points: Vec<_> = (0..25)
+            .map(|i| array![f64::from(i % 5), f64::from(i / 5)])
+            .collect();
+        let values = points.iter().map(|p| p[0].sin() * p[1].cos()).collect();
+        let interpolator = ModifiedShepard::with_neighbors(points, values, 13, 3).unwrap();
+
+        // the third and fourth nearest points are at the same distance from the query, so the
+        // radius of the weights is their distance and the interpolant is continuous there
+        let value = interpolator.interpolate(&array![1.5, 1.3]).unwrap();
+        for shift in [-1e-9, 1e-9] {
+            let nearby = interpolator.interpolate(&array![1.5 + shift, 1.3]).unwrap();
+            assert!((value - nearby).abs() < 1e-6, "{value} {nearby}");
+        }
+    }
+
+    #[test]
+    fn check_few_points() {
+        // too few neighbors for quadratic, and then for linear, nodal functions
+        let points = vec![array![0., 0.], array![1., 0.], array![0., 1.]];
+        let mut interpolator =
+            ModifiedShepard::with_neighbors(points.clone(), vec![0., 1., 2.], 13, 2).unwrap();
+        let value = interpolator.interpolate(&array![0.2, 0.2]).unwrap();
+        assert!(value.is_finite());
+        // linear data is reproduced by linear nodal functions
+        assert!((value - (0.2 + 2. * 0.2)).abs() < 1e-12, "{value}");
+
+        interpolator.set_tolerance(0.3).unwrap();
+        assert!(interpolator.set_tolerance(-1.).is_err());
+        assert_eq!(interpolator.tolerance(), 0.3);
+        assert_eq!(interpolator.interpolate(&array![0.2, 0.2]).unwrap(), 0.);
+        assert!(matches!(
+            interpolator.interpolate(&array![0.2]),
+            Err(InterpolationError::InvalidQuery(_))
+        ));
+
+        let interpolator = ModifiedShepard::new(vec![array![0.5]], vec![4.]).unwrap();
+        assert_eq!(interpolator.interpolate(&array![0.7]).unwrap(), 4.);
+
+        let interpolator = ModifiedShepard::new(vec![], vec![]).unwrap();
+        assert!(interpolator.interpolate(&array![]).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+!> Fortran 2008 bindings for the C API of ndinterp
+!>
+!> The interpolators are wrapped in derived types owning the underlying C handle, which is released
+!> by a finalizer when the object goes out of scope. All the procedures accept an optional `status`
+!> argument: when it is present the status code of the C API is stored in it, otherwise any failure
+!> stops the program with the message of `ndinterp_last_error_message`.
+module ndinterp
+    use, intrinsic :: iso_c_binding, only: c_associated, c_char, c_double, c_f_pointer, c_int, &
+        c_loc, c_null_ptr, c_ptr, c_size_t
+    use, intrinsic :: iso_fortran_env, only: error_unit
+    implicit none
+    private
+
+    !> Status codes, mirroring `ndinterp_status` of the C API
+    integer(c_int), parameter, public :: NDINTERP_STATUS_OK = 0
+    integer(c_int), parameter, public :: NDINTERP_STATUS_EXTRAPOLATION_ABOVE = 1
+    integer(c_int), parameter, public :: NDINTERP_STATUS_EXTRAPOLATION_BELOW = 2
+    integer(c_int), parameter, public :: NDINTERP_STATUS_INVALID_GRID = 3
+    integer(c_int), parameter, public :: NDINTERP_STATUS_NULL_POINTER = 4
+    integer(c_int), parameter, public :: NDINTERP_STATUS_PANIC = 5
+    integer(c_int), parameter, public :: NDINTERP_STATUS_FILE_ERROR = 6
+    integer(c_int), parameter, public :: NDINTERP_STATUS_INVALID_HANDLE = 7
+    integer(c_int), parameter, public :: NDINTERP_STATUS_INVALID_ARGUMENT = 8
+
+    !> Cubic interpolator in one or two dimensions, wrapping `ndinterp_cubic`
+    !>
+    !> The object owns the C handle, therefore it cannot be copied: assigning it to another object
+    !> stops the program.
+    type, public :: ndinterp_cubic
+        private
+        type(c_ptr) :: ptr = c_null_ptr
+        integer(c_size_t) :: ndim = 0
+    contains
+        procedure, private :: init_1d => ndinterp_cubic_init_1d
+        procedure, private :: init_2d => ndinterp_cubic_init_2d
+        !> Creates the interpolator from the nodes of each axis and the values at the nodes
+        generic :: init => init_1d, init_2d
+        procedure, private :: eval_scalar => ndinterp_cubic_eval_scalar
+        procedure, private :: eval_array => ndinterp_cubic_eval_array
+        !> Interpolates at a point, given by a scalar in one dimension or an array in general
+        generic :: eval => eval_scalar, eval_array
+        !> Interpolates at a point, also computing the partial derivatives
+        procedure :: eval_gradient => ndinterp_cubic_eval_gradient
+        !> Integrates over the box with corners `lower` and `upper`
+        procedure :: integrate => ndinterp_cubic_integrate
+        !> Number of dimensions of the interpolator, zero if it has not been created
+        procedure :: dimensions => ndinterp_cubic_dimensions
+        !> Releases the C handle, the object can be initialized again afterwards
+        procedure :: delete => ndinterp_cubic_delete
+        procedure, private :: forbid_copy => ndinterp_cubic_forbid_copy
+        generic :: assignment(=) => forbid_copy
+        final :: ndinterp_cubic_final
+    end type ndinterp_cubic
+
+    public :: ndinterp_last_error_message
+
+    interface
+        function c_ndinterp_cubic_new(ndim, shape, axes, values, interpolator) result(status) &
+                bind(c, name="ndinterp_cubic_new")
+            import :: c_int, c_ptr, c_size_t
+            integer(c_size_t), value :: ndim
+            integer(c_size_t), intent(in) :: shape(*)
+            type(c_ptr), intent(in) :: axes(*)
+            type(c_ptr), value :: values
+            type(c_ptr), intent(out) :: interpolator
+            integer(c_int) :: status
+        end function c_ndinterp_cubic_new
+
+        function c_ndinterp_cubic_eval(interpolator, query, result) result(status) &
+                bind(c, name="ndinterp_cubic_eval")
+            import :: c_double, c_int, c_ptr
+            type(c_ptr), value :: interpolator
+            real(c_double), intent(in) :: query(*)
+            real(c_double), intent(out) :: result
+            integer(c_int) :: status
+        end function c_ndinterp_cubic_eval
+
+        function c_ndinterp_cubic_eval_gradient(interpolator, query, result, gradient) &
+                result(status) bind(c, name="ndinterp_cubic_eval_gradient")
+            import :: c_double, c_int, c_ptr
+            type(c_ptr), value :: interpolator
+            real(c_double), intent(in) :: query(*)
+            real(c_double), intent(out) :: result
+            real(c_double), intent(out) :: gradient(*)
+            integer(c_int) :: status
+        end function c_ndinterp_cubic_eval_gradient
+
+        function c_ndinterp_cubic_integrate(interpolator, lower, upper, result) result(status) &
+                bind(c, name="ndinterp_cubic_integrate")
+            import :: c_double, c_int, c_ptr
+            type(c_ptr), value :: interpolator
+            real(c_double), intent(in) :: lower(*)
+            real(c_double), intent(in) :: upper(*)
+            real(c_double), intent(out) :: result
+            integer(c_int) :: status
+        end function c_ndinterp_cubic_integrate
+
+        function c_ndinterp_cubic_delete(interpolator) result(status) &
+                bind(c, name="ndinterp_cubic_delete")
+            import :: c_int, c_ptr
+            type(c_ptr), value :: interpolator
+            integer(c_int) :: status
+        end function c_ndinterp_cubic_delete
+
+        function c_ndinterp_last_error_message() result(message) &
+                bind(c, name="ndinterp_last_error_message")
+            import :: c_ptr
+            type(c_ptr) :: message
+        end function c_ndinterp_last_error_message
+
+        function c_strlen(string) result(length) bind(c, name="strlen")
+            import :: c_ptr, c_size_t
+            type(c_ptr), value :: string
+            integer(c_size_t) :: length
+        end function c_strlen
+    end interface
+
+contains
+
+    !> Returns the description of the last error raised by the C API in the calling thread
+    function ndinterp_last_error_message() result(message)
+        character(len=:), allocatable :: message
+        type(c_ptr) :: c_message
+        character(kind=c_char), pointer :: chars(:)
+        integer(c_size_t) :: length, i
+
+        c_message = c_ndinterp_last_error_message()
+        length = c_strlen(c_message)
+        allocate(character(len=length) :: message)
+        if (length == 0) return
+
+        call c_f_pointer(c_message, chars, [length])
+        do i = 1, length
+            message(i:i) = chars(i)
+        end do
+    end function ndinterp_last_error_message
+
+    !> Stores `code` in `status` if present, otherwise stops the program if `code` is an error
+    subroutine check(code, status, message)
+        integer(c_int), intent(in) :: code
+        integer, intent(out), optional :: status
+        character(len=*), intent(in), optional :: message
+
+        if (present(status)) then
+            status = code
+        else if (code /= NDINTERP_STATUS_OK) then
+            if (present(message)) then
+                write(error_unit, '(a)') 'ndinterp: ' // message
+            else
+                write(error_unit, '(a)') 'ndinterp: ' // ndinterp_last_error_message()
+            end if
+            error stop 1
+        end if
+    end subroutine check
+
+    !> Checks that a query has one coordinate per dimension of the interpolator
+    logical function valid_query(self, query, status)
+        class(ndinterp_cubic), intent(in) :: self
+        real(c_double), intent(in) :: query(:)
+        integer, intent(out), optional :: status
+
+        valid_query = c_associated(self%ptr) .and. size(query, kind=c_size_t) == self%ndim
+        if (.not. c_associated(self%ptr)) then
+            call check(NDINTERP_STATUS_NULL_POINTER, status, 'the interpolator has not been created')
+        else if (.not. valid_query) then
+            call check(NDINTERP_STATUS_INVALID_GRID, status, &
+                'the query does not have one coordinate per dimension')
+        end if
+    end function valid_query
+
+    subroutine ndinterp_cubic_init_1d(self, x, values, status)
+        class(ndinterp_cubic), intent(inout) :: self
+        real(c_double), intent(in) :: x(:)
+        real(c_double), intent(in) :: values(:)
+        integer, intent(out), optional :: status
+        real(c_double), allocatable, target :: x_c(:), values_c(:)
+        type(c_ptr) :: axes(1)
+        integer(c_size_t) :: shape(1)
+
+        call self%delete()
+        if (size(values) /= size(x)) then
+            call check(NDINTERP_STATUS_INVALID_GRID, status, &
+                'the number of values does not match the number of nodes')
+            return
+        end if
+
+        x_c = x
+        values_c = values
+        shape = [size(x, kind=c_size_t)]
+        axes = [c_loc(x_c)]
+        call check(c_ndinterp_cubic_new(1_c_size_t, shape, axes, c_loc(values_c), self%ptr), status)
+        if (c_associated(self%ptr)) self%ndim = 1
+    end subroutine ndinterp_cubic_init_1d
+
+    subroutine ndinterp_cubic_init_2d(self, x1, x2, values, status)
+        class(ndinterp_cubic), intent(inout) :: self
+        real(c_double), intent(in) :: x1(:)
+        real(c_double), intent(in) :: x2(:)
+        !> The value at (`x1(i)`, `x2(j)`) is `values(i, j)`
+        real(c_double), intent(in) :: values(:, :)
+        integer, intent(out), optional :: status
+        real(c_double), allocatable, target :: x1_c(:), x2_c(:), values_c(:, :)
+        type(c_ptr) :: axes(2)
+        integer(c_size_t) :: shape(2)
+
+        call self%delete()
+        if (size(values, 1) /= size(x1) .or. size(values, 2) /= size(x2)) then
+            call check(NDINTERP_STATUS_INVALID_GRID, status, &
+                'the shape of the values does not match the number of nodes')
+            return
+        end if
+
+        x1_c = x1
+        x2_c = x2
+        ! the C API expects the values in row-major order
+        values_c = transpose(values)
+        shape = [size(x1, kind=c_size_t), size(x2, kind=c_size_t)]
+        axes = [c_loc(x1_c), c_loc(x2_c)]
+        call check(c_ndinterp_cubic_new(2_c_size_t, shape, axes, c_loc(values_c), self%ptr), status)
+        if (c_associated(self%ptr)) self%ndim = 2
+    end subroutine ndinterp_cubic_init_2d
+
+    function ndinterp_cubic_eval_scalar(self, x, status) result(value)
+        class(ndinterp_cubic), intent(in) :: self
+        real(c_double), intent(in) :: x
+        integer, intent(out), optional :: status
+        real(c_double) :: value
+
+        value = ndinterp_cubic_eval_array(self, [x], status)
+    end function ndinterp_cubic_eval_scalar
+
+    function ndinterp_cubic_eval_array(self, query, status) result(value)
+        class(ndinterp_cubic), intent(in) :: self
+        real(c_double), intent(in) :: query(:)
+        integer, intent(out), optional :: status
+        real(c_double) :: value
+
+        value = 0
+        if (.not. valid_query(self, query, status)) return
+        call check(c_ndinterp_cubic_eval(self%ptr, query, value), status)
+    end function ndinterp_cubic_eval_array
+
+    function ndinterp_cubic_eval_gradient(self, query, gradient, status) result(value)
+        class(ndinterp_cubic), intent(in) :: self
+        real(c_double), intent(in) :: query(:)
+        !> Must have one entry per dimension
+        real(c_double), intent(out) :: gradient(:)
+        integer, intent(out), optional :: status
+        real(c_double) :: value
+        real(c_double) :: gradient_c(size(query))
+
+        value = 0
+        gradient = 0
+        if (.not. valid_query(self, query, status)) return
+        if (size(gradient) /= size(query)) then
+            call check(NDINTERP_STATUS_INVALID_GRID, status, &
+                'the gradient does not have one entry per dimension')
+            return
+        end if
+
+        call check(c_ndinterp_cubic_eval_gradient(self%ptr, query, value, gradient_c), status)
+        gradient = gradient_c
+    end function ndinterp_cubic_eval_gradient
+
+    function ndinterp_cubic_integrate(self, lower, upper, status) result(integral)
+        class(ndinterp_cubic), intent(in) :: self
+        real(c_double), intent(in) :: lower(:)
+        real(c_double), intent(in) :: upper(:)
+        integer, intent(out), optional :: status
+        real(c_double) :: integral
+
+        integral = 0
+        if (.not. valid_query(self, lower, status)) return
+        if (.not. valid_query(self, upper, status)) return
+        call check(c_ndinterp_cubic_integrate(self%ptr, lower, upper, integral), status)
+    end function ndinterp_cubic_integrate
+
+    integer function ndinterp_cubic_dimensions(self)
+        class(ndinterp_cubic), intent(in) :: self
+
+        ndinterp_cubic_dimensions = int(self%ndim)
+    end function ndinterp_cubic_dimensions
+
+    subroutine ndinterp_cubic_delete(self)
+        class(ndinterp_cubic), intent(inout) :: self
+        integer(c_int) :: code
+
+        if (c_associated(self%ptr)) then
+            code = c_ndinterp_cubic_delete(self%ptr)
+        end if
+        self%ptr = c_null_ptr
+        self%ndim = 0
+    end subroutine ndinterp_cubic_delete
+
+    subroutine ndinterp_cubic_forbid_copy(lhs, rhs)
+        class(ndinterp_cubic), intent(inout) :: lhs
+        class(ndinterp_cubic), intent(in) :: rhs
+
+        if (c_associated(lhs%ptr) .or. c_associated(rhs%ptr)) then
+            write(error_unit, '(a)') 'ndinterp: ndinterp_cubic objects cannot be copied'
+            error stop 1
+        end if
+    end subroutine ndinterp_cubic_forbid_copy
+
+    subroutine ndinterp_cubic_final(self)
+        type(ndinterp_cubic), intent(inout) :: self
+
+        call self%delete()
+    end subroutine ndinterp_cubic_final
+
+end module ndinterp
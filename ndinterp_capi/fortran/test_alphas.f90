@@ -0,0 +1,68 @@
+!> Checks the Fortran bindings against the alpha_s values of NNPDF40_nnlo_as_01180 computed with
+!> LHAPDF6, the same used by the `alphas.rs` example of ndinterp
+program test_alphas
+    use, intrinsic :: iso_c_binding, only: c_double
+    use, intrinsic :: iso_fortran_env, only: error_unit
+    use ndinterp
+    implicit none
+
+    real(c_double), parameter :: q2s(12) = [ &
+        2.7225_c_double, 3.19493746_c_double, 3.774881_c_double, 4.49174997_c_double, &
+        5.38430257_c_double, 6.50400153_c_double, 7.91973571_c_double, 9.72449465_c_double, &
+        12.04490818_c_double, 15.05498278_c_double, 18.99610035_c_double, 24.2064_c_double]
+    real(c_double), parameter :: alphas_vals(12) = [ &
+        0.33074891_c_double, 0.3176246_c_double, 0.30507081_c_double, 0.29305875_c_double, &
+        0.28156114_c_double, 0.27055221_c_double, 0.26000761_c_double, 0.24990438_c_double, &
+        0.24022086_c_double, 0.23093662_c_double, 0.22203241_c_double, 0.21377883_c_double]
+    real(c_double), parameter :: example_q(6) = [ &
+        1.7_c_double, 1.8_c_double, 2.6_c_double, 3.4_c_double, 4.1_c_double, 4.5_c_double]
+    real(c_double), parameter :: lhapdf_res(6) = [ &
+        0.32580476_c_double, 0.31652747_c_double, 0.26841305_c_double, 0.24201896_c_double, &
+        0.22660515_c_double, 0.21978229_c_double]
+
+    type(ndinterp_cubic) :: alphas
+    real(c_double) :: value, gradient(1)
+    integer :: i, status, failures
+
+    failures = 0
+
+    ! LHAPDF does the interpolation in alphas in a logarithmic manner
+    call alphas%init(log(q2s), alphas_vals)
+    if (alphas%dimensions() /= 1) call fail('wrong number of dimensions')
+
+    do i = 1, size(example_q)
+        value = alphas%eval(log(example_q(i)**2))
+        if (abs(value - lhapdf_res(i)) > 1e-7_c_double * lhapdf_res(i)) then
+            write(error_unit, '(a, f4.1, a, es15.8, a, es15.8)') 'alpha_s(', example_q(i), &
+                ') = ', value, ' instead of ', lhapdf_res(i)
+            failures = failures + 1
+        end if
+    end do
+
+    ! alpha_s decreases with the scale
+    value = alphas%eval_gradient([log(example_q(3)**2)], gradient)
+    if (gradient(1) >= 0) call fail('the derivative of alpha_s is not negative')
+
+    value = alphas%eval(log(10.0_c_double**2), status)
+    if (status /= NDINTERP_STATUS_EXTRAPOLATION_ABOVE) call fail('no extrapolation error above')
+    if (len(ndinterp_last_error_message()) == 0) call fail('no error message')
+
+    value = alphas%eval(log(1.0_c_double**2), status)
+    if (status /= NDINTERP_STATUS_EXTRAPOLATION_BELOW) call fail('no extrapolation error below')
+
+    call alphas%init([1.0_c_double, 0.0_c_double], [1.0_c_double, 2.0_c_double], status)
+    if (status /= NDINTERP_STATUS_INVALID_GRID) call fail('unsorted nodes accepted')
+
+    if (failures > 0) error stop 1
+    write(*, '(a)') 'All tested points agreed with LHAPDF'
+
+contains
+
+    subroutine fail(message)
+        character(len=*), intent(in) :: message
+
+        write(error_unit, '(a)') message
+        failures = failures + 1
+    end subroutine fail
+
+end program test_alphas
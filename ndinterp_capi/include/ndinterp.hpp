@@ -0,0 +1,458 @@
+// Header-only C++ wrapper of the ndinterp C API
+//
+// Every interpolator owns its C handle, released by the destructor: objects can be moved but not
+// copied, so that handles can be neither leaked nor freed twice. Failures of the C API are thrown
+// as exceptions derived from `ndinterp::error`, carrying the status code and the message of
+// `ndinterp_last_error_message`.
+
+#ifndef NDINTERP_HPP
+#define NDINTERP_HPP
+
+// older versions of the C header rely on `size_t` and `uint32_t` without including their
+// definitions
+#include <stddef.h>
+#include <stdint.h>
+
+#include "ndinterp_capi.h"
+
+#include <array>
+#include <cstddef>
+#include <cstdint>
+#include <initializer_list>
+#include <memory>
+#include <stdexcept>
+#include <string>
+#include <vector>
+
+#if __cplusplus >= 202002L && __has_include(<span>)
+#include <span>
+#define NDINTERP_HAS_SPAN 1
+#endif
+
+namespace ndinterp {
+
+// Base class of the exceptions raised by failures of the C API
+class error : public std::runtime_error {
+public:
+    error(ndinterp_status status, const std::string& message)
+        : std::runtime_error(message), status_(status) {}
+
+    // The status code returned by the C API
+    ndinterp_status status() const noexcept { return status_; }
+
+private:
+    ndinterp_status status_;
+};
+
+// The value queried is above the maximum of the grid
+class extrapolation_above : public error {
+public:
+    using error::error;
+};
+
+// The value queried is below the minimum of the grid
+class extrapolation_below : public error {
+public:
+    using error::error;
+};
+
+// The input vectors and values do not form a valid grid
+class invalid_grid : public error {
+public:
+    using error::error;
+};
+
+// A file could not be found, read or parsed
+class file_error : public error {
+public:
+    using error::error;
+};
+
+namespace detail {
+
+// Throws the exception corresponding to `status`, if it is not `NDINTERP_STATUS_OK`
+inline void check(ndinterp_status status) {
+    if (status == NDINTERP_STATUS_OK) {
+        return;
+    }
+
+    std::string message = ndinterp_last_error_message();
+    switch (status) {
+    case NDINTERP_STATUS_EXTRAPOLATION_ABOVE:
+        throw extrapolation_above(status, message);
+    case NDINTERP_STATUS_EXTRAPOLATION_BELOW:
+        throw extrapolation_below(status, message);
+    case NDINTERP_STATUS_INVALID_GRID:
+        throw invalid_grid(status, message);
+    case NDINTERP_STATUS_FILE_ERROR:
+        throw file_error(status, message);
+    default:
+        throw error(status, message);
+    }
+}
+
+inline void check_size(std::size_t size, std::size_t expected, const char* what) {
+    if (size != expected) {
+        throw std::invalid_argument(std::string(what) + " has " + std::to_string(size) +
+                                    " elements instead of " + std::to_string(expected));
+    }
+}
+
+// Releases the C handles, used by `std::unique_ptr`
+struct deleter {
+    void operator()(::cubic1d* handle) const noexcept { delete_cubic_interpolator1d(handle); }
+    void operator()(::cubic2d* handle) const noexcept { delete_cubic_interpolator2d(handle); }
+    void operator()(::ndinterp_cubic* handle) const noexcept { ndinterp_cubic_delete(handle); }
+    void operator()(::ndinterp_interpolator* handle) const noexcept {
+        ndinterp_interpolator_delete(handle);
+    }
+    void operator()(::ndinterp_pdf* handle) const noexcept { ndinterp_pdf_delete(handle); }
+    void operator()(::ndinterp_alphas* handle) const noexcept { ndinterp_alphas_delete(handle); }
+};
+
+template <typename T>
+using handle = std::unique_ptr<T, deleter>;
+
+} // namespace detail
+
+// Non-owning view over contiguous doubles, built from a `std::vector`, a `std::span` (C++20) or
+// an initializer list; it must not outlive the object it views
+class array_view {
+public:
+    array_view(const std::vector<double>& values) noexcept
+        : data_(values.data()), size_(values.size()) {}
+#if defined(__GNUC__) && !defined(__clang__)
+#pragma GCC diagnostic push
+// the list lives until the end of the full expression, which is enough for a call argument
+#pragma GCC diagnostic ignored "-Winit-list-lifetime"
+#endif
+    array_view(std::initializer_list<double> values) noexcept
+        : data_(values.begin()), size_(values.size()) {}
+#if defined(__GNUC__) && !defined(__clang__)
+#pragma GCC diagnostic pop
+#endif
+#ifdef NDINTERP_HAS_SPAN
+    array_view(std::span<const double> values) noexcept
+        : data_(values.data()), size_(values.size()) {}
+#endif
+
+    const double* data() const noexcept { return data_; }
+    std::size_t size() const noexcept { return size_; }
+
+private:
+    const double* data_;
+    std::size_t size_;
+};
+
+// Tag selecting the constructors that borrow the values of the grid instead of copying them: the
+// memory viewed must then stay valid and unmodified for the whole lifetime of the interpolator.
+// These constructors only accept an lvalue `std::vector` or a `std::span` (C++20), temporaries and
+// initializer lists would be destroyed at the end of the statement and are therefore rejected
+struct borrow_values_t {
+    explicit borrow_values_t() = default;
+};
+
+inline constexpr borrow_values_t borrow_values{};
+
+// One-dimensional cubic interpolator, wrapping `cubic1d`
+class cubic_1d {
+public:
+    // The grid is given by the nodes `x` and the `values` at the nodes, which are copied
+    cubic_1d(array_view x, array_view values) {
+        detail::check_size(values.size(), x.size(), "values");
+        ::cubic1d* raw = nullptr;
+        detail::check(create_cubic_interpolator1d(x.data(), values.data(), x.size(), &raw));
+        handle_.reset(raw);
+    }
+
+    double operator()(double x) const {
+        double result;
+        detail::check(interpolate_cubic_1d(handle_.get(), x, &result));
+        return result;
+    }
+
+    // Interpolates all the `queries`, throwing the error of the first one failing
+    std::vector<double> operator()(array_view queries) const {
+        std::vector<double> results(queries.size());
+        detail::check(interpolate_cubic_1d_batch(handle_.get(), queries.data(), queries.size(),
+                                                 results.data(), nullptr));
+        return results;
+    }
+
+    // Derivative of the interpolated function at `x`
+    double derivative(double x) const {
+        double result, derivative;
+        detail::check(derivative_cubic_1d(handle_.get(), x, &result, &derivative));
+        return derivative;
+    }
+
+    // Integral of the interpolated function between `lower` and `upper`
+    double integrate(double lower, double upper) const {
+        double result;
+        detail::check(integrate_cubic_1d(handle_.get(), lower, upper, &result));
+        return result;
+    }
+
+private:
+    detail::handle<::cubic1d> handle_;
+};
+
+// Two-dimensional cubic interpolator, wrapping `cubic2d`
+class cubic_2d {
+public:
+    // The `values` are given in row-major order, i.e. the value at (`x1[i]`, `x2[j]`) is
+    // `values[i * x2.size() + j]`
+    cubic_2d(array_view x1, array_view x2, array_view values) {
+        init(create_cubic_interpolator2d, x1, x2, values);
+    }
+
+    // Like the constructor above, but sharing `values` with the caller, see `borrow_values`
+    cubic_2d(borrow_values_t, array_view x1, array_view x2, const std::vector<double>& values) {
+        init(create_cubic_interpolator2d_borrowed, x1, x2, values);
+    }
+#ifdef NDINTERP_HAS_SPAN
+    cubic_2d(borrow_values_t, array_view x1, array_view x2, std::span<const double> values) {
+        init(create_cubic_interpolator2d_borrowed, x1, x2, values);
+    }
+#endif
+    cubic_2d(borrow_values_t, array_view, array_view, std::vector<double>&&) = delete;
+    cubic_2d(borrow_values_t, array_view, array_view, std::initializer_list<double>) = delete;
+
+    double operator()(double x1, double x2) const {
+        double result;
+        detail::check(interpolate_cubic_2d(handle_.get(), x1, x2, &result));
+        return result;
+    }
+
+    // Interpolates at the points (`x1[i]`, `x2[i]`), throwing the error of the first one failing
+    std::vector<double> operator()(array_view x1, array_view x2) const {
+        detail::check_size(x2.size(), x1.size(), "x2");
+        std::vector<double> results(x1.size());
+        detail::check(interpolate_cubic_2d_batch(handle_.get(), x1.data(), x2.data(), x1.size(),
+                                                 results.data(), nullptr));
+        return results;
+    }
+
+    // Partial derivatives of the interpolated function at (`x1`, `x2`)
+    std::array<double, 2> gradient(double x1, double x2) const {
+        double result;
+        std::array<double, 2> gradient;
+        detail::check(
+            gradient_cubic_2d(handle_.get(), x1, x2, &result, &gradient[0], &gradient[1]));
+        return gradient;
+    }
+
+    // Integral of the interpolated function over [`lower1`, `upper1`] x [`lower2`, `upper2`]
+    double integrate(double lower1, double upper1, double lower2, double upper2) const {
+        double result;
+        detail::check(integrate_cubic_2d(handle_.get(), lower1, upper1, lower2, upper2, &result));
+        return result;
+    }
+
+private:
+    template <typename Create>
+    void init(Create create, array_view x1, array_view x2, array_view values) {
+        detail::check_size(values.size(), x1.size() * x2.size(), "values");
+        ::cubic2d* raw = nullptr;
+        detail::check(
+            create(x1.data(), x2.data(), values.data(), x1.size(), x2.size(), &raw));
+        handle_.reset(raw);
+    }
+
+    detail::handle<::cubic2d> handle_;
+};
+
+namespace detail {
+
+// Common implementation of the N-dimensional interpolators, `Api` provides the C functions
+template <typename T, typename Api>
+class nd_base {
+public:
+    std::size_t ndim() const noexcept { return ndim_; }
+
+    double operator()(array_view query) const {
+        check_size(query.size(), ndim_, "query");
+        double result;
+        check(Api::eval(handle_.get(), query.data(), &result));
+        return result;
+    }
+
+    // Interpolates the points whose coordinates are stored one after the other in `queries`,
+    // throwing the error of the first one failing
+    std::vector<double> eval_batch(array_view queries) const {
+        if (queries.size() % ndim_ != 0) {
+            throw std::invalid_argument("the number of coordinates is not a multiple of ndim");
+        }
+        std::vector<double> results(queries.size() / ndim_);
+        check(Api::eval_batch(handle_.get(), queries.data(), results.size(), results.data(),
+                              nullptr));
+        return results;
+    }
+
+    // Partial derivatives of the interpolated function at `query`
+    std::vector<double> gradient(array_view query) const {
+        check_size(query.size(), ndim_, "query");
+        double result;
+        std::vector<double> gradient(ndim_);
+        check(Api::eval_gradient(handle_.get(), query.data(), &result, gradient.data()));
+        return gradient;
+    }
+
+    // Integral of the interpolated function over the box with corners `lower` and `upper`
+    double integrate(array_view lower, array_view upper) const {
+        check_size(lower.size(), ndim_, "lower");
+        check_size(upper.size(), ndim_, "upper");
+        double result;
+        check(Api::integrate(handle_.get(), lower.data(), upper.data(), &result));
+        return result;
+    }
+
+protected:
+    // Checks the shape of the grid, filling `shape` and `pointers` for the C constructors
+    static void prepare(const std::vector<std::vector<double>>& axes, array_view values,
+                        std::vector<std::size_t>& shape, std::vector<const double*>& pointers) {
+        std::size_t size = 1;
+        for (const auto& axis : axes) {
+            shape.push_back(axis.size());
+            pointers.push_back(axis.data());
+            size *= axis.size();
+        }
+        check_size(values.size(), size, "values");
+    }
+
+    handle<T> handle_;
+    std::size_t ndim_ = 0;
+};
+
+struct cubic_api {
+    static constexpr auto eval = ndinterp_cubic_eval;
+    static constexpr auto eval_batch = ndinterp_cubic_eval_batch;
+    static constexpr auto eval_gradient = ndinterp_cubic_eval_gradient;
+    static constexpr auto integrate = ndinterp_cubic_integrate;
+};
+
+struct interpolator_api {
+    static constexpr auto eval = ndinterp_interpolator_eval;
+    static constexpr auto eval_batch = ndinterp_interpolator_eval_batch;
+    static constexpr auto eval_gradient = ndinterp_interpolator_eval_gradient;
+    static constexpr auto integrate = ndinterp_interpolator_integrate;
+};
+
+} // namespace detail
+
+// N-dimensional cubic interpolator, wrapping `ndinterp_cubic`
+class cubic_nd : public detail::nd_base<::ndinterp_cubic, detail::cubic_api> {
+public:
+    // The grid is given by the nodes along each axis and the `values` in row-major order
+    cubic_nd(const std::vector<std::vector<double>>& axes, array_view values) {
+        init(ndinterp_cubic_new, axes, values);
+    }
+
+    // Like the constructor above, but sharing `values` with the caller, see `borrow_values`
+    cubic_nd(borrow_values_t, const std::vector<std::vector<double>>& axes,
+             const std::vector<double>& values) {
+        init(ndinterp_cubic_new_borrowed, axes, values);
+    }
+#ifdef NDINTERP_HAS_SPAN
+    cubic_nd(borrow_values_t, const std::vector<std::vector<double>>& axes,
+             std::span<const double> values) {
+        init(ndinterp_cubic_new_borrowed, axes, values);
+    }
+#endif
+    cubic_nd(borrow_values_t, const std::vector<std::vector<double>>&,
+             std::vector<double>&&) = delete;
+    cubic_nd(borrow_values_t, const std::vector<std::vector<double>>&,
+             std::initializer_list<double>) = delete;
+
+private:
+    template <typename Create>
+    void init(Create create, const std::vector<std::vector<double>>& axes, array_view values) {
+        std::vector<std::size_t> shape;
+        std::vector<const double*> pointers;
+        prepare(axes, values, shape, pointers);
+        ::ndinterp_cubic* raw = nullptr;
+        detail::check(create(axes.size(), shape.data(), pointers.data(), values.data(), &raw));
+        handle_.reset(raw);
+        ndim_ = axes.size();
+    }
+};
+
+// Interpolator with configurable method and extrapolation, wrapping `ndinterp_interpolator`
+class interpolator : public detail::nd_base<::ndinterp_interpolator, detail::interpolator_api> {
+public:
+    // The grid is given by the nodes along each axis and the `values` in row-major order; the
+    // `extrapolation` policies are either empty, to throw for every query outside of the grid,
+    // or one per axis
+    interpolator(const std::vector<std::vector<double>>& axes, array_view values,
+                 ndinterp_method method = NDINTERP_METHOD_CUBIC,
+                 const std::vector<ndinterp_extrapolation_policy>& extrapolation = {}) {
+        std::vector<std::size_t> shape;
+        std::vector<const double*> pointers;
+        prepare(axes, values, shape, pointers);
+        if (!extrapolation.empty()) {
+            detail::check_size(extrapolation.size(), axes.size(), "extrapolation");
+        }
+        ndinterp_config config = {static_cast<std::uint32_t>(method),
+                                  extrapolation.empty() ? nullptr : extrapolation.data()};
+        ::ndinterp_interpolator* raw = nullptr;
+        detail::check(ndinterp_interpolator_new(&config, axes.size(), shape.data(),
+                                                pointers.data(), values.data(), &raw));
+        handle_.reset(raw);
+        ndim_ = axes.size();
+    }
+};
+
+// A member of an LHAPDF set, wrapping `ndinterp_pdf`
+class pdf {
+public:
+    // Loads `member` of the set `set`, given by name or path
+    pdf(const std::string& set, std::size_t member) {
+        ::ndinterp_pdf* raw = nullptr;
+        detail::check(ndinterp_pdf_load(set.c_str(), member, &raw));
+        handle_.reset(raw);
+    }
+
+    // x f(x, Q²) for the flavour `pid`, zero for flavours not in the set
+    double xfxq2(int pid, double x, double q2) const {
+        double result;
+        detail::check(ndinterp_pdf_xfxq2(handle_.get(), pid, x, q2, &result));
+        return result;
+    }
+
+    // x f(x, Q²) for the flavour `pid` at the points (`x[i]`, `q2[i]`), throwing the error of the
+    // first one failing
+    std::vector<double> xfxq2(int pid, array_view x, array_view q2) const {
+        detail::check_size(q2.size(), x.size(), "q2");
+        std::vector<double> results(x.size());
+        detail::check(ndinterp_pdf_xfxq2_batch(handle_.get(), pid, x.data(), q2.data(), x.size(),
+                                               results.data(), nullptr));
+        return results;
+    }
+
+private:
+    detail::handle<::ndinterp_pdf> handle_;
+};
+
+// The αs interpolation of a member of an LHAPDF set, wrapping `ndinterp_alphas`
+class alphas {
+public:
+    // Loads the αs interpolation of `member` of the set `set`, given by name or path
+    alphas(const std::string& set, std::size_t member) {
+        ::ndinterp_alphas* raw = nullptr;
+        detail::check(ndinterp_alphas_load(set.c_str(), member, &raw));
+        handle_.reset(raw);
+    }
+
+    double alphasq2(double q2) const {
+        double result;
+        detail::check(ndinterp_alphas_alphasq2(handle_.get(), q2, &result));
+        return result;
+    }
+
+private:
+    detail::handle<::ndinterp_alphas> handle_;
+};
+
+} // namespace ndinterp
+
+#endif // NDINTERP_HPP
@@ -0,0 +1,51 @@
+//! Evaluation of many points with a single call
+
+use crate::error::{Error, Status};
+use core::slice;
+use ndinterp::interpolate::InterpolationError;
+
+/// Evaluates `eval` on the points `0..npoints`, storing the values in `results` and, if it is not
+/// NULL, the status of each point in `statuses`. The points that fail are set to NaN and the error
+/// of the first of them is returned.
+///
+/// # Safety
+///
+/// `results` must be valid for `npoints` writes and `statuses`, if not NULL, too.
+pub(crate) unsafe fn evaluate_batch(
+    npoints: usize,
+    results: *mut f64,
+    statuses: *mut Status,
+    eval: impl Fn(usize) -> Result<f64, InterpolationError>,
+) -> Result<(), Error> {
+    if results.is_null() {
+        return Err(Error::NullPointer("results"));
+    }
+    let results = slice::from_raw_parts_mut(results, npoints);
+    let mut statuses = if statuses.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts_mut(statuses, npoints))
+    };
+
+    let mut first_error = None;
+    for (i, result) in results.iter_mut().enumerate() {
+        let status = match eval(i) {
+            Ok(value) => {
+                *result = value;
+                Status::Ok
+            }
+            Err(err) => {
+                *result = f64::NAN;
+                let err = Error::from(err);
+                let status = err.status();
+                first_error.get_or_insert(err);
+                status
+            }
+        };
+        if let Some(statuses) = statuses.as_mut() {
+            statuses[i] = status;
+        }
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
@@ -0,0 +1,571 @@
+//! N-dimensional cubic interpolation
+//!
+//! A single opaque type, [`CubicNd`], serves every dimension supported by the Rust library: the
+//! dimension is given at construction time, together with the length and the nodes of each axis.
+
+use crate::batch::evaluate_batch;
+use crate::error::{array_len, as_mut_slice, as_slice, ffi_call, write_out, Error, Status};
+use crate::handle::{as_handle, delete_handle, write_handle};
+use core::slice;
+use ndarray::{ArrayView, CowRepr, IxDyn};
+use ndinterp::grid::cubic::Cubic;
+use ndinterp::grid::{CowGrid, DimensionHelper, Grid, GridView, ToDimension};
+use ndinterp::interpolate::{Differentiate, Integrate, InterpolationError, Interpolator};
+
+/// Dimensions supported by [`CubicNd`]
+const SUPPORTED_DIMENSIONS: [usize; 2] = [1, 2];
+
+/// Values of a grid, either copied from or borrowed from the caller
+pub(crate) type Values = CowRepr<'static, f64>;
+
+#[derive(Debug)]
+enum Kind {
+    D1(Cubic<1, Values>),
+    D2(Cubic<2, Values>),
+}
+
+/// Cubic interpolator of any supported dimension
+#[derive(Debug)]
+pub struct CubicNd(Kind);
+
+/// Views the grid given as C arrays, checking that it is consistent. The axes are copied, since
+/// they are much smaller than the values, while the values are borrowed.
+///
+/// # Safety
+///
+/// Every non-NULL entry of `axes` must point to an array of length equal to the corresponding
+/// entry of `shape`, and `values` must point to an array with as many elements as the product of
+/// `shape`, which stays valid and unmodified as long as the returned grid is used.
+unsafe fn grid_view_from_raw<const D: usize>(
+    shape: &[usize],
+    axes: &[*const f64],
+    values: *const f64,
+) -> Result<GridView<'static, D>, Error>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    if values.is_null() {
+        return Err(Error::NullPointer("values"));
+    }
+    // the axes and the values must be addressable, even if some axes are empty
+    for &len in shape {
+        array_len::<f64>(&[len], "axes")?;
+    }
+    array_len::<f64>(shape, "values")?;
+
+    let mut xgrid = Vec::with_capacity(axes.len());
+    for (&axis, &len) in axes.iter().zip(shape) {
+        if axis.is_null() {
+            return Err(Error::NullPointer("axes"));
+        }
+        xgrid.push(slice::from_raw_parts(axis, len).to_vec());
+    }
+
+    let values = ArrayView::from_shape_ptr(IxDyn(shape), values)
+        .into_dimensionality()
+        .map_err(|err| InterpolationError::InvalidGrid(err.to_string()))?;
+
+    Ok(GridView::new(xgrid, values)?)
+}
+
+/// Copies the grid given as C arrays, checking that it is consistent
+///
+/// # Safety
+///
+/// Every non-NULL entry of `axes` must point to an array of length equal to the corresponding
+/// entry of `shape`, and `values` must point to an array with as many elements as the product of
+/// `shape`.
+pub(crate) unsafe fn grid_from_raw<const D: usize>(
+    shape: &[usize],
+    axes: &[*const f64],
+    values: *const f64,
+) -> Result<Grid<D>, Error>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    Ok(grid_view_from_raw(shape, axes, values)?.to_owned())
+}
+
+/// Copies the grid given as C arrays, or only its axes if `borrow` is true, see
+/// [`grid_view_from_raw`]
+///
+/// # Safety
+///
+/// See [`grid_view_from_raw`], the values must stay valid only if `borrow` is true.
+pub(crate) unsafe fn cow_grid_from_raw<const D: usize>(
+    shape: &[usize],
+    axes: &[*const f64],
+    values: *const f64,
+    borrow: bool,
+) -> Result<CowGrid<'static, D>, Error>
+where
+    DimensionHelper<D>: ToDimension,
+{
+    let view = grid_view_from_raw(shape, axes, values)?;
+    Ok(if borrow {
+        view.into()
+    } else {
+        view.to_owned().into()
+    })
+}
+
+impl CubicNd {
+    /// Number of dimensions of the interpolator
+    pub(crate) fn ndim(&self) -> usize {
+        match &self.0 {
+            Kind::D1(_) => 1,
+            Kind::D2(_) => 2,
+        }
+    }
+
+    /// Interpolates at `query`, whose length must be equal to [`CubicNd::ndim`]
+    pub(crate) fn interpolate(&self, query: &[f64]) -> Result<f64, InterpolationError> {
+        match &self.0 {
+            Kind::D1(cubic) => cubic.interpolate(query[0]),
+            Kind::D2(cubic) => cubic.interpolate(query),
+        }
+    }
+
+    /// Interpolates at `query`, storing the partial derivatives in `gradient`
+    pub(crate) fn value_and_gradient(
+        &self,
+        query: &[f64],
+        gradient: &mut [f64],
+    ) -> Result<f64, InterpolationError> {
+        match &self.0 {
+            Kind::D1(cubic) => cubic.value_and_gradient(query, gradient),
+            Kind::D2(cubic) => cubic.value_and_gradient(query, gradient),
+        }
+    }
+
+    /// Integrates over the box with corners `lower` and `upper`
+    pub(crate) fn integrate(
+        &self,
+        lower: &[f64],
+        upper: &[f64],
+    ) -> Result<f64, InterpolationError> {
+        match &self.0 {
+            Kind::D1(cubic) => cubic.integrate(lower, upper),
+            Kind::D2(cubic) => cubic.integrate(lower, upper),
+        }
+    }
+}
+
+/// Creates a cubic interpolator, see [`ndinterp_cubic_new`] and [`ndinterp_cubic_new_borrowed`]
+///
+/// # Safety
+///
+/// See [`ndinterp_cubic_new_borrowed`], the values must stay valid only if `borrow` is true.
+unsafe fn new_cubic(
+    ndim: usize,
+    shape: *const usize,
+    axes: *const *const f64,
+    values: *const f64,
+    interpolator: *mut *mut CubicNd,
+    borrow: bool,
+) -> Result<(), Error> {
+    if shape.is_null() {
+        return Err(Error::NullPointer("shape"));
+    }
+    if axes.is_null() {
+        return Err(Error::NullPointer("axes"));
+    }
+    let shape = slice::from_raw_parts(shape, ndim);
+    let axes = slice::from_raw_parts(axes, ndim);
+
+    let kind = match ndim {
+        1 => Kind::D1(Cubic {
+            grid: cow_grid_from_raw(shape, axes, values, borrow)?,
+        }),
+        2 => Kind::D2(Cubic {
+            grid: cow_grid_from_raw(shape, axes, values, borrow)?,
+        }),
+        _ => {
+            return Err(InterpolationError::InvalidGrid(format!(
+                "{ndim}-dimensional cubic interpolation is not supported, the supported \
+                 dimensions are {SUPPORTED_DIMENSIONS:?}"
+            ))
+            .into())
+        }
+    };
+
+    write_handle(interpolator, CubicNd(kind), "interpolator")
+}
+
+/// Creates a cubic interpolator with `ndim` dimensions and stores it in `interpolator`.
+///
+/// The length of the i-th axis is `shape[i]` and its nodes are `axes[i]`, while `values` holds
+/// the values of the function at the nodes in row-major (C) order, i.e. with the last axis
+/// running fastest. All the arrays are copied.
+///
+/// # Safety
+///
+/// `shape` and `axes` must be arrays of length `ndim`, each `axes[i]` must be an array of length
+/// `shape[i]` and `values` must be an array with as many elements as the product of the entries
+/// of `shape`. `interpolator` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_cubic_new(
+    ndim: usize,
+    shape: *const usize,
+    axes: *const *const f64,
+    values: *const f64,
+    interpolator: *mut *mut CubicNd,
+) -> Status {
+    ffi_call(|| new_cubic(ndim, shape, axes, values, interpolator, false))
+}
+
+/// Creates a cubic interpolator like [`ndinterp_cubic_new`], but borrowing `values` instead of
+/// copying them: the caller and the interpolator share a single copy of the values, while the
+/// axes, which are much smaller, are still copied.
+///
+/// # Safety
+///
+/// Besides the requirements of [`ndinterp_cubic_new`], the array `values` must stay valid and
+/// must not be modified until the interpolator is deleted with [`ndinterp_cubic_delete`].
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_cubic_new_borrowed(
+    ndim: usize,
+    shape: *const usize,
+    axes: *const *const f64,
+    values: *const f64,
+    interpolator: *mut *mut CubicNd,
+) -> Status {
+    ffi_call(|| new_cubic(ndim, shape, axes, values, interpolator, true))
+}
+
+/// Interpolates at `query`, an array with one coordinate per dimension, storing the value in
+/// `result`.
+///
+/// # Safety
+///
+/// `interpolator` must have been created by [`ndinterp_cubic_new`], `query` must be an array
+/// with as many elements as the dimensions of the interpolator and `result` must be valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_cubic_eval(
+    interpolator: *const CubicNd,
+    query: *const f64,
+    result: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let interpolator = as_handle(interpolator, "interpolator")?;
+        if query.is_null() {
+            return Err(Error::NullPointer("query"));
+        }
+        let query = slice::from_raw_parts(query, interpolator.ndim());
+
+        write_out(result, interpolator.interpolate(query)?, "result")
+    })
+}
+
+/// Interpolates at the `npoints` points of `queries`, storing the values in `results` and, unless
+/// it is NULL, the status of each point in `statuses`.
+///
+/// The coordinates of the i-th point are `queries[i * ndim]`, ..., `queries[i * ndim + ndim - 1]`
+/// where `ndim` is the number of dimensions of the interpolator. The points outside the grid are
+/// set to NaN, and the status of the first of them is returned.
+///
+/// # Safety
+///
+/// `interpolator` must have been created by [`ndinterp_cubic_new`], `queries` must be an array of
+/// length `npoints * ndim`, `results` an array of length `npoints`, and so must be `statuses`
+/// unless it is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_cubic_eval_batch(
+    interpolator: *const CubicNd,
+    queries: *const f64,
+    npoints: usize,
+    results: *mut f64,
+    statuses: *mut Status,
+) -> Status {
+    ffi_call(|| {
+        let interpolator = as_handle(interpolator, "interpolator")?;
+        if queries.is_null() {
+            return Err(Error::NullPointer("queries"));
+        }
+        let ndim = interpolator.ndim();
+        let queries =
+            slice::from_raw_parts(queries, array_len::<f64>(&[npoints, ndim], "queries")?);
+
+        evaluate_batch(npoints, results, statuses, |i| {
+            interpolator.interpolate(&queries[i * ndim..(i + 1) * ndim])
+        })
+    })
+}
+
+/// Interpolates at `query`, an array with one coordinate per dimension, storing the value in
+/// `result` and its partial derivatives in `gradient`.
+///
+/// # Safety
+///
+/// `interpolator` must have been created by [`ndinterp_cubic_new`], `query` and `gradient` must be
+/// arrays with as many elements as the dimensions of the interpolator and `result` must be valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_cubic_eval_gradient(
+    interpolator: *const CubicNd,
+    query: *const f64,
+    result: *mut f64,
+    gradient: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let interpolator = as_handle(interpolator, "interpolator")?;
+        let query = as_slice(query, interpolator.ndim(), "query")?;
+        let gradient = as_mut_slice(gradient, interpolator.ndim(), "gradient")?;
+
+        let value = interpolator.value_and_gradient(query, gradient)?;
+        write_out(result, value, "result")
+    })
+}
+
+/// Integrates the interpolated function over the box with corners `lower` and `upper`, which
+/// must lie within the grid, storing the integral in `result`. Swapping the bounds along one axis
+/// flips the sign of the result.
+///
+/// # Safety
+///
+/// `interpolator` must have been created by [`ndinterp_cubic_new`], `lower` and `upper` must be
+/// arrays with as many elements as the dimensions of the interpolator and `result` must be valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_cubic_integrate(
+    interpolator: *const CubicNd,
+    lower: *const f64,
+    upper: *const f64,
+    result: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let interpolator = as_handle(interpolator, "interpolator")?;
+        let lower = as_slice(lower, interpolator.ndim(), "lower")?;
+        let upper = as_slice(upper, interpolator.ndim(), "upper")?;
+
+        write_out(result, interpolator.integrate(lower, upper)?, "result")
+    })
+}
+
+/// Stores the number of dimensions of `interpolator` in `ndim`.
+///
+/// # Safety
+///
+/// `interpolator` must have been created by [`ndinterp_cubic_new`] and `ndim` must be valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_cubic_ndim(
+    interpolator: *const CubicNd,
+    ndim: *mut usize,
+) -> Status {
+    ffi_call(|| {
+        write_out(
+            ndim,
+            as_handle(interpolator, "interpolator")?.ndim(),
+            "ndim",
+        )
+    })
+}
+
+/// Deletes an object created by [`ndinterp_cubic_new`]. Deleting a NULL pointer does nothing.
+///
+/// # Safety
+///
+/// The object given to this function must have been created by [`ndinterp_cubic_new`] and this
+/// function must not have been called with it before.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_cubic_delete(interpolator: *mut CubicNd) -> Status {
+    ffi_call(|| delete_handle(interpolator, "interpolator"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn check_nd() {
+        let x1 = [0., 1., 2.];
+        let x2 = [0., 1., 2., 3.];
+        let values = [1., 2., 3., 4., 2., 3., 4., 5., 4., 5., 6., 8.];
+        let mut cubic = ptr::null_mut();
+        let mut result = 0.;
+        let mut ndim = 0;
+
+        unsafe {
+            assert_eq!(
+                ndinterp_cubic_new(
+                    2,
+                    [3, 4].as_ptr(),
+                    [x1.as_ptr(), x2.as_ptr()].as_ptr(),
+                    values.as_ptr(),
+                    &mut cubic
+                ),
+                Status::Ok
+            );
+            assert_eq!(ndinterp_cubic_ndim(cubic, &mut ndim), Status::Ok);
+            assert_eq!(ndim, 2);
+            assert_eq!(
+                ndinterp_cubic_eval(cubic, [2., 3.].as_ptr(), &mut result),
+                Status::Ok
+            );
+            assert_eq!(result, 8.);
+            assert_eq!(
+                ndinterp_cubic_eval(cubic, [2., 3.5].as_ptr(), &mut result),
+                Status::ExtrapolationAbove
+            );
+
+            let mut results = [0.; 2];
+            let mut statuses = [Status::Ok; 2];
+            assert_eq!(
+                ndinterp_cubic_eval_batch(
+                    cubic,
+                    [2., 3., 2., 3.5].as_ptr(),
+                    2,
+                    results.as_mut_ptr(),
+                    statuses.as_mut_ptr()
+                ),
+                Status::ExtrapolationAbove
+            );
+            assert_eq!(results[0], 8.);
+            assert!(results[1].is_nan());
+            assert_eq!(statuses, [Status::Ok, Status::ExtrapolationAbove]);
+            // the number of coordinates of the queries overflows
+            assert_eq!(
+                ndinterp_cubic_eval_batch(
+                    cubic,
+                    [2., 3.].as_ptr(),
+                    usize::MAX / 2 + 1,
+                    results.as_mut_ptr(),
+                    statuses.as_mut_ptr()
+                ),
+                Status::InvalidArgument
+            );
+            assert_eq!(ndinterp_cubic_delete(cubic), Status::Ok);
+
+            assert_eq!(
+                ndinterp_cubic_new(
+                    1,
+                    [4].as_ptr(),
+                    [x2.as_ptr()].as_ptr(),
+                    values.as_ptr(),
+                    &mut cubic
+                ),
+                Status::Ok
+            );
+            assert_eq!(
+                ndinterp_cubic_eval(cubic, [1.].as_ptr(), &mut result),
+                Status::Ok
+            );
+            assert_eq!(result, 2.);
+
+            let mut gradient = [0.];
+            assert_eq!(
+                ndinterp_cubic_eval_gradient(
+                    cubic,
+                    [1.].as_ptr(),
+                    &mut result,
+                    gradient.as_mut_ptr()
+                ),
+                Status::Ok
+            );
+            assert_eq!((result, gradient[0]), (2., 1.));
+            assert_eq!(
+                ndinterp_cubic_integrate(cubic, [0.].as_ptr(), [1.].as_ptr(), &mut result),
+                Status::Ok
+            );
+            assert!((result - 1.5).abs() < 1e-12);
+            assert_eq!(
+                ndinterp_cubic_integrate(cubic, [0.].as_ptr(), [4.].as_ptr(), &mut result),
+                Status::ExtrapolationAbove
+            );
+            assert_eq!(ndinterp_cubic_delete(cubic), Status::Ok);
+        }
+    }
+
+    #[test]
+    fn check_borrowed_nd() {
+        let x = [0., 1., 2., 3.];
+        let values = [1., 2., 4., 8.];
+        let mut cubic = ptr::null_mut();
+        let mut result = 0.;
+
+        unsafe {
+            assert_eq!(
+                ndinterp_cubic_new_borrowed(
+                    1,
+                    [4].as_ptr(),
+                    [x.as_ptr()].as_ptr(),
+                    values.as_ptr(),
+                    &mut cubic
+                ),
+                Status::Ok
+            );
+            match &as_handle(cubic, "cubic").unwrap().0 {
+                Kind::D1(cubic) => assert_eq!(cubic.grid.values.as_ptr(), values.as_ptr()),
+                Kind::D2(_) => unreachable!(),
+            }
+            assert_eq!(
+                ndinterp_cubic_eval(cubic, [2.].as_ptr(), &mut result),
+                Status::Ok
+            );
+            assert_eq!(result, 4.);
+            assert_eq!(ndinterp_cubic_delete(cubic), Status::Ok);
+
+            assert_eq!(
+                ndinterp_cubic_new_borrowed(
+                    3,
+                    [4, 4, 4].as_ptr(),
+                    [x.as_ptr(); 3].as_ptr(),
+                    values.as_ptr(),
+                    &mut cubic
+                ),
+                Status::InvalidGrid
+            );
+        }
+    }
+
+    #[test]
+    fn check_invalid_nd() {
+        let x = [0., 1., 2.];
+        let values = [0.; 27];
+        let mut cubic = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(
+                ndinterp_cubic_new(
+                    3,
+                    [3, 3, 3].as_ptr(),
+                    [x.as_ptr(); 3].as_ptr(),
+                    values.as_ptr(),
+                    &mut cubic
+                ),
+                Status::InvalidGrid
+            );
+            assert_eq!(
+                ndinterp_cubic_new(
+                    2,
+                    [3, 3].as_ptr(),
+                    [x.as_ptr(), ptr::null()].as_ptr(),
+                    values.as_ptr(),
+                    &mut cubic
+                ),
+                Status::NullPointer
+            );
+            // shapes whose number of elements overflows, or that cannot be addressed
+            for shape in [
+                [3, usize::MAX / 2],
+                [2, isize::MAX as usize / 8 + 1],
+                [0, usize::MAX],
+            ] {
+                assert_eq!(
+                    ndinterp_cubic_new(
+                        2,
+                        shape.as_ptr(),
+                        [x.as_ptr(); 2].as_ptr(),
+                        values.as_ptr(),
+                        &mut cubic
+                    ),
+                    Status::InvalidArgument
+                );
+            }
+            assert!(cubic.is_null());
+        }
+    }
+}
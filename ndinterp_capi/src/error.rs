@@ -0,0 +1,212 @@
+//! Error reporting across the C boundary
+//!
+//! No function of the C API aborts the host process: every entry point returns a [`Status`],
+//! writing its results through out-parameters, and Rust panics are caught before they reach C. A
+//! human-readable description of the last failure of the calling thread is available through
+//! [`ndinterp_last_error_message`].
+
+use ndinterp::interpolate::InterpolationError;
+use ndinterp::io::lhapdf::LhapdfError;
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+use thiserror::Error;
+
+/// Status code returned by every function of the C API, the error kinds mirror
+/// [`InterpolationError`]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The call succeeded
+    Ok = 0,
+    /// The value queried is above the maximum of the grid
+    ExtrapolationAbove = 1,
+    /// The value queried is below the minimum of the grid
+    ExtrapolationBelow = 2,
+    /// The input vectors and values do not form a valid grid
+    InvalidGrid = 3,
+    /// A pointer given to the function is NULL
+    NullPointer = 4,
+    /// An unexpected Rust panic was caught, this is always a bug of the library
+    Panic = 5,
+    /// A file could not be found, read or parsed
+    FileError = 6,
+    /// A handle given to the function is not a live object of the expected type, for instance
+    /// because it has already been deleted
+    InvalidHandle = 7,
+    /// An argument given to the function has a value outside of the allowed ones, for instance
+    /// an unknown interpolation method
+    InvalidArgument = 8,
+}
+
+/// Errors raised by the functions of the C API
+#[derive(Debug, Error)]
+pub(crate) enum Error {
+    /// An error raised by the interpolation library
+    #[error(transparent)]
+    Interpolation(#[from] InterpolationError),
+
+    /// A pointer given to the function is NULL
+    #[error("the argument `{0}` is a NULL pointer")]
+    NullPointer(&'static str),
+
+    /// An error raised while loading an LHAPDF set
+    #[error(transparent)]
+    Lhapdf(#[from] LhapdfError),
+
+    /// A handle given to the function is not a live object of the expected type
+    #[error("{0}")]
+    InvalidHandle(String),
+
+    /// An argument given to the function has a value outside of the allowed ones
+    #[error("{0}")]
+    InvalidArgument(String),
+}
+
+impl Error {
+    /// The status code reported to C for this error
+    pub(crate) fn status(&self) -> Status {
+        match self {
+            Error::Interpolation(InterpolationError::ExtrapolationAbove(_))
+            | Error::Lhapdf(LhapdfError::Grid(InterpolationError::ExtrapolationAbove(_))) => {
+                Status::ExtrapolationAbove
+            }
+            Error::Interpolation(InterpolationError::ExtrapolationBelow(_))
+            | Error::Lhapdf(LhapdfError::Grid(InterpolationError::ExtrapolationBelow(_))) => {
+                Status::ExtrapolationBelow
+            }
+            Error::Interpolation(InterpolationError::InvalidGrid(_))
+            | Error::Lhapdf(LhapdfError::Grid(InterpolationError::InvalidGrid(_))) => {
+                Status::InvalidGrid
+            }
+            Error::Interpolation(
+                InterpolationError::InvalidConfiguration(_) | InterpolationError::InvalidQuery(_),
+            )
+            | Error::Lhapdf(LhapdfError::Grid(
+                InterpolationError::InvalidConfiguration(_) | InterpolationError::InvalidQuery(_),
+            )) => Status::InvalidArgument,
+            Error::NullPointer(_) => Status::NullPointer,
+            Error::Lhapdf(_) => Status::FileError,
+            Error::InvalidHandle(_) => Status::InvalidHandle,
+            Error::InvalidArgument(_) => Status::InvalidArgument,
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn set_last_error(message: String) {
+    // a message with an interior NUL would be truncated by C anyway
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic payload");
+
+    format!("panic in ndinterp: {message}")
+}
+
+/// Runs the body of a C entry point, converting its errors and panics into a [`Status`]
+pub(crate) fn ffi_call(body: impl FnOnce() -> Result<(), Error>) -> Status {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => Status::Ok,
+        Ok(Err(err)) => {
+            set_last_error(err.to_string());
+            err.status()
+        }
+        Err(payload) => {
+            set_last_error(panic_message(payload.as_ref()));
+            Status::Panic
+        }
+    }
+}
+
+/// Dereferences `ptr`, raising an error if it is NULL
+///
+/// # Safety
+///
+/// If not NULL, `ptr` must point to a valid object of type `T` for the lifetime `'a`.
+pub(crate) unsafe fn as_ref<'a, T>(ptr: *const T, name: &'static str) -> Result<&'a T, Error> {
+    ptr.as_ref().ok_or(Error::NullPointer(name))
+}
+
+/// Number of elements of an array of `T` with the given `dimensions`, raising an error if it
+/// cannot be addressed, i.e. if its size in bytes overflows `isize`
+pub(crate) fn array_len<T>(dimensions: &[usize], name: &'static str) -> Result<usize, Error> {
+    dimensions
+        .iter()
+        .try_fold(1_usize, |len, &dimension| len.checked_mul(dimension))
+        .filter(|&len| len <= isize::MAX as usize / mem::size_of::<T>().max(1))
+        .ok_or_else(|| {
+            Error::InvalidArgument(format!(
+                "the array `{name}` with dimensions {dimensions:?} is too large"
+            ))
+        })
+}
+
+/// Views `ptr` as a slice of length `len`, raising an error if it is NULL
+///
+/// # Safety
+///
+/// If not NULL, `ptr` must point to an array of at least `len` elements valid for the lifetime
+/// `'a`.
+pub(crate) unsafe fn as_slice<'a, T>(
+    ptr: *const T,
+    len: usize,
+    name: &'static str,
+) -> Result<&'a [T], Error> {
+    if ptr.is_null() {
+        return Err(Error::NullPointer(name));
+    }
+    Ok(slice::from_raw_parts(ptr, len))
+}
+
+/// Views `ptr` as a mutable slice of length `len`, raising an error if it is NULL
+///
+/// # Safety
+///
+/// If not NULL, `ptr` must point to an array of at least `len` elements valid for reads and writes
+/// for the lifetime `'a`.
+pub(crate) unsafe fn as_mut_slice<'a, T>(
+    ptr: *mut T,
+    len: usize,
+    name: &'static str,
+) -> Result<&'a mut [T], Error> {
+    if ptr.is_null() {
+        return Err(Error::NullPointer(name));
+    }
+    Ok(slice::from_raw_parts_mut(ptr, len))
+}
+
+/// Writes `value` into the out-parameter `ptr`, raising an error if it is NULL
+///
+/// # Safety
+///
+/// If not NULL, `ptr` must be valid for writes.
+pub(crate) unsafe fn write_out<T>(ptr: *mut T, value: T, name: &'static str) -> Result<(), Error> {
+    if ptr.is_null() {
+        return Err(Error::NullPointer(name));
+    }
+    ptr.write(value);
+    Ok(())
+}
+
+/// Returns the description of the last error raised in the calling thread, or an empty string if
+/// no error has been raised yet.
+///
+/// The returned string is owned by the library and stays valid until the next failing call in the
+/// same thread: it must not be freed by the caller.
+#[no_mangle]
+pub extern "C" fn ndinterp_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
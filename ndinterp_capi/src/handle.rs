@@ -0,0 +1,255 @@
+//! Validation of the handles given out to C
+//!
+//! The addresses of the live objects created by the C API are kept in a registry, together with
+//! a tag identifying their type, which is checked before every use of a handle without touching
+//! its memory: passing a handle of the wrong type, a handle which has already been deleted, or a
+//! pointer which is not a handle at all reports
+//! [`Status::InvalidHandle`](crate::Status::InvalidHandle) instead of reading the memory. The
+//! registry is behind a read-write lock, so that concurrent evaluations do not block each other.
+//!
+//! A deleted handle can only go unnoticed if its address has been reused by a new object of the
+//! same type, in which case the new object is used.
+
+use crate::cubic::CubicNd;
+use crate::error::Error;
+use crate::interpolator::NdInterpolator;
+use crate::lhapdf::{AlphaS, Pdf};
+use crate::{Cubic1d, Cubic2d};
+use std::collections::BTreeMap;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// A type whose objects are given out to C as handles
+pub(crate) trait Handle: Sized {
+    /// Tag identifying the type in the registry of the live objects
+    const TAG: u64;
+    /// Name of the type in the C API
+    const NAME: &'static str;
+}
+
+/// FNV-1a hash of `name`, used to derive the tags
+const fn tag(name: &str) -> u64 {
+    let bytes = name.as_bytes();
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+macro_rules! handles {
+    ($($handle:ty => $name:literal),* $(,)?) => {
+        $(
+            impl Handle for $handle {
+                const TAG: u64 = tag($name);
+                const NAME: &'static str = $name;
+            }
+        )*
+
+        /// Tags and names of all the handle types, to describe the wrong ones
+        const HANDLES: &[(u64, &str)] = &[$((tag($name), $name)),*];
+    };
+}
+
+handles! {
+    Cubic1d => "cubic1d",
+    Cubic2d => "cubic2d",
+    CubicNd => "ndinterp_cubic",
+    NdInterpolator => "ndinterp_interpolator",
+    Pdf => "ndinterp_pdf",
+    AlphaS => "ndinterp_alphas",
+}
+
+/// Addresses of the live handles together with their tags
+static LIVE: RwLock<BTreeMap<usize, u64>> = RwLock::new(BTreeMap::new());
+
+fn live() -> RwLockReadGuard<'static, BTreeMap<usize, u64>> {
+    LIVE.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn live_mut() -> RwLockWriteGuard<'static, BTreeMap<usize, u64>> {
+    LIVE.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn type_name(tag: u64) -> Option<&'static str> {
+    HANDLES
+        .iter()
+        .find_map(|&(known, name)| (known == tag).then_some(name))
+}
+
+/// Checks that `ptr` is a live handle of type `T`, looking up its address in `live`
+fn check_live<T: Handle>(
+    live: &BTreeMap<usize, u64>,
+    ptr: *const T,
+    name: &'static str,
+) -> Result<(), Error> {
+    match live.get(&(ptr as usize)) {
+        Some(&tag) if tag == T::TAG => Ok(()),
+        Some(&tag) => Err(Error::InvalidHandle(format!(
+            "`{name}` is a `{}`, not a `{}`",
+            type_name(tag).unwrap_or("unknown type"),
+            T::NAME
+        ))),
+        None => Err(Error::InvalidHandle(format!(
+            "`{name}` is not a live `{}`, it has already been deleted or was not created by \
+             ndinterp",
+            T::NAME
+        ))),
+    }
+}
+
+/// Moves `value` to the heap and writes its handle into `out`, which is checked first so that
+/// nothing is leaked
+///
+/// # Safety
+///
+/// If not NULL, `out` must be valid for writes.
+pub(crate) unsafe fn write_handle<T: Handle>(
+    out: *mut *mut T,
+    value: T,
+    name: &'static str,
+) -> Result<(), Error> {
+    if out.is_null() {
+        return Err(Error::NullPointer(name));
+    }
+
+    let ptr = Box::into_raw(Box::new(value));
+    live_mut().insert(ptr as usize, T::TAG);
+    out.write(ptr);
+    Ok(())
+}
+
+/// Dereferences the handle `ptr`, checking that it is not NULL and that it is a live handle of
+/// type `T`, before accessing its memory
+///
+/// # Safety
+///
+/// The handle must not be deleted while the returned reference is in use.
+pub(crate) unsafe fn as_handle<'a, T: Handle>(
+    ptr: *const T,
+    name: &'static str,
+) -> Result<&'a T, Error> {
+    if ptr.is_null() {
+        return Err(Error::NullPointer(name));
+    }
+    check_live(&live(), ptr, name)?;
+    Ok(&*ptr)
+}
+
+/// Deletes the handle `ptr`, doing nothing if it is NULL and raising an error, without accessing
+/// the memory, if it is not a live handle of type `T`
+///
+/// # Safety
+///
+/// No other reference to the object may be in use.
+pub(crate) unsafe fn delete_handle<T: Handle>(
+    ptr: *mut T,
+    name: &'static str,
+) -> Result<(), Error> {
+    if ptr.is_null() {
+        return Ok(());
+    }
+
+    let mut live = live_mut();
+    check_live(&live, ptr, name)?;
+    live.remove(&(ptr as usize));
+    drop(live);
+
+    drop(Box::from_raw(ptr));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Status;
+    use crate::{create_cubic_interpolator1d, create_cubic_interpolator2d};
+    use crate::{delete_cubic_interpolator1d, delete_cubic_interpolator2d, interpolate_cubic_1d};
+    use std::ptr;
+
+    #[test]
+    fn check_tags() {
+        for (i, &(tag, _)) in HANDLES.iter().enumerate() {
+            assert!(HANDLES[i + 1..].iter().all(|&(other, _)| other != tag));
+        }
+    }
+
+    #[test]
+    fn check_misuse() {
+        let x = [0., 1., 2.];
+        let values = [0., 1., 4., 1., 2., 5., 2., 3., 6.];
+        let mut cubic1d = ptr::null_mut();
+        let mut cubic2d = ptr::null_mut();
+        let mut result = 0.;
+
+        unsafe {
+            create_cubic_interpolator1d(x.as_ptr(), values.as_ptr(), 3, &mut cubic1d);
+            create_cubic_interpolator2d(
+                x.as_ptr(),
+                x.as_ptr(),
+                values.as_ptr(),
+                3,
+                3,
+                &mut cubic2d,
+            );
+            assert_eq!(interpolate_cubic_1d(cubic1d, 1.5, &mut result), Status::Ok);
+
+            // NULL handles
+            assert_eq!(
+                interpolate_cubic_1d(ptr::null(), 1.5, &mut result),
+                Status::NullPointer
+            );
+            assert_eq!(delete_cubic_interpolator1d(ptr::null_mut()), Status::Ok);
+
+            // handles of the wrong type
+            let wrong = cubic2d.cast::<Cubic1d>();
+            assert!(matches!(
+                as_handle(wrong, "interpolator"),
+                Err(Error::InvalidHandle(message)) if message.contains("`cubic2d`")
+            ));
+            assert_eq!(
+                interpolate_cubic_1d(wrong, 1.5, &mut result),
+                Status::InvalidHandle
+            );
+            assert_eq!(delete_cubic_interpolator1d(wrong), Status::InvalidHandle);
+            assert_eq!(
+                delete_cubic_interpolator2d(cubic1d.cast()),
+                Status::InvalidHandle
+            );
+
+            // pointers that are not handles at all are never freed
+            let mut not_a_handle = [0_u64; 8];
+            let fake = not_a_handle.as_mut_ptr().cast::<Cubic1d>();
+            assert_eq!(
+                interpolate_cubic_1d(fake, 1.5, &mut result),
+                Status::InvalidHandle
+            );
+            assert_eq!(delete_cubic_interpolator1d(fake), Status::InvalidHandle);
+            assert_eq!(
+                interpolate_cubic_1d(fake.cast::<u8>().add(1).cast(), 1.5, &mut result),
+                Status::InvalidHandle
+            );
+
+            // the handles are still usable after the failed deletions
+            assert_eq!(interpolate_cubic_1d(cubic1d, 1.5, &mut result), Status::Ok);
+
+            // double deletions
+            assert_eq!(delete_cubic_interpolator1d(cubic1d), Status::Ok);
+            assert_eq!(delete_cubic_interpolator1d(cubic1d), Status::InvalidHandle);
+
+            // uses after deletion, without reading the freed memory
+            assert!(matches!(
+                as_handle(cubic1d, "interpolator"),
+                Err(Error::InvalidHandle(message)) if message.contains("already been deleted")
+            ));
+            assert_eq!(
+                interpolate_cubic_1d(cubic1d, 1.5, &mut result),
+                Status::InvalidHandle
+            );
+            assert_eq!(delete_cubic_interpolator2d(cubic2d), Status::Ok);
+            assert_eq!(delete_cubic_interpolator2d(cubic2d), Status::InvalidHandle);
+        }
+    }
+}
@@ -0,0 +1,613 @@
+//! Interpolators of any kind behind a single opaque type
+//!
+//! An [`NdInterpolator`] is created from a [`Config`], selecting the interpolation method and the
+//! extrapolation policy along each axis, and the same functions evaluate and delete it whatever
+//! its kind: adding a new method only requires a new [`Method`] variant.
+
+use crate::batch::evaluate_batch;
+use crate::cubic::grid_from_raw;
+use crate::error::{array_len, as_mut_slice, as_ref, as_slice, ffi_call, write_out, Error, Status};
+use crate::handle::{as_handle, delete_handle, write_handle};
+use core::slice;
+use ndinterp::grid::cubic::Cubic;
+use ndinterp::grid::extrapolation::{Extrapolated, Extrapolation};
+use ndinterp::grid::linear::Linear;
+use ndinterp::grid::{DimensionHelper, ToDimension};
+use ndinterp::interpolate::{Differentiate, Integrate, InterpolationError, Interpolator};
+
+/// Interpolation method
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    /// Cubic interpolation as done by LHAPDF, available in 1 and 2 dimensions
+    Cubic = 0,
+    /// Multilinear interpolation, available in 1 to 6 dimensions
+    Linear = 1,
+}
+
+/// Converts the value stored in [`Config::method`], returning it back if it is not valid
+impl TryFrom<u32> for Method {
+    type Error = u32;
+
+    fn try_from(method: u32) -> Result<Self, u32> {
+        match method {
+            0 => Ok(Method::Cubic),
+            1 => Ok(Method::Linear),
+            _ => Err(method),
+        }
+    }
+}
+
+/// What to do with queries outside of the grid along one axis
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtrapolationKind {
+    /// Report [`Status::ExtrapolationAbove`] or [`Status::ExtrapolationBelow`]
+    Error = 0,
+    /// Move the query to the closest boundary of the grid
+    Clamp = 1,
+    /// Return the value given in the policy
+    Constant = 2,
+}
+
+/// Converts the value stored in [`ExtrapolationPolicy::kind`], returning it back if it is not
+/// valid
+impl TryFrom<u32> for ExtrapolationKind {
+    type Error = u32;
+
+    fn try_from(kind: u32) -> Result<Self, u32> {
+        match kind {
+            0 => Ok(ExtrapolationKind::Error),
+            1 => Ok(ExtrapolationKind::Clamp),
+            2 => Ok(ExtrapolationKind::Constant),
+            _ => Err(kind),
+        }
+    }
+}
+
+/// Extrapolation policy along one axis
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ExtrapolationPolicy {
+    /// What to do with queries outside of the grid, one of the values of [`ExtrapolationKind`].
+    /// It is stored as an integer since C may set any value, which is checked before use
+    pub kind: u32,
+    /// The value returned by [`ExtrapolationKind::Constant`], ignored otherwise
+    pub value: f64,
+}
+
+/// Configuration of an interpolator. A zero-initialized configuration selects cubic
+/// interpolation and reports an error for every query outside of the grid.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    /// The interpolation method, one of the values of [`Method`]. It is stored as an integer
+    /// since C may set any value, which is checked before use
+    pub method: u32,
+    /// Array with the extrapolation policy along each axis, or NULL to report an error for every
+    /// query outside of the grid
+    pub extrapolation: *const ExtrapolationPolicy,
+}
+
+/// Converts the policy, returning its kind if it is not valid
+impl TryFrom<ExtrapolationPolicy> for Extrapolation {
+    type Error = u32;
+
+    fn try_from(policy: ExtrapolationPolicy) -> Result<Self, u32> {
+        Ok(match ExtrapolationKind::try_from(policy.kind)? {
+            ExtrapolationKind::Error => Extrapolation::Error,
+            ExtrapolationKind::Clamp => Extrapolation::Clamp,
+            ExtrapolationKind::Constant => Extrapolation::Constant(policy.value),
+        })
+    }
+}
+
+/// What every interpolator behind an [`NdInterpolator`] must implement
+trait Kernel:
+    for<'a> Interpolator<&'a [f64]>
+    + for<'a> Differentiate<&'a [f64]>
+    + for<'a> Integrate<&'a [f64]>
+    + Send
+    + Sync
+{
+}
+
+impl<T> Kernel for T where
+    T: for<'a> Interpolator<&'a [f64]>
+        + for<'a> Differentiate<&'a [f64]>
+        + for<'a> Integrate<&'a [f64]>
+        + Send
+        + Sync
+{
+}
+
+type DynInterpolator = Box<dyn Kernel>;
+
+/// Interpolator of any method and dimension
+pub struct NdInterpolator {
+    interpolator: DynInterpolator,
+    ndim: usize,
+}
+
+/// Copies the grid given as C arrays and builds the interpolator `new(grid)` on it, applying the
+/// extrapolation `policies` if given
+///
+/// # Safety
+///
+/// See [`grid_from_raw`].
+unsafe fn build<const D: usize, I>(
+    new: impl FnOnce(ndinterp::grid::Grid<D>) -> I,
+    shape: &[usize],
+    axes: &[*const f64],
+    values: *const f64,
+    policies: Option<Vec<Extrapolation>>,
+) -> Result<DynInterpolator, Error>
+where
+    DimensionHelper<D>: ToDimension,
+    I: Kernel + 'static,
+{
+    let grid = grid_from_raw::<D>(shape, axes, values)?;
+
+    Ok(match policies {
+        Some(policies) => {
+            let xgrid = grid.xgrid.clone();
+            Box::new(Extrapolated::new(new(grid), &xgrid, policies)?)
+        }
+        None => Box::new(new(grid)),
+    })
+}
+
+impl NdInterpolator {
+    /// Interpolates at `query`, whose length must be equal to the dimension of the interpolator
+    pub(crate) fn interpolate(&self, query: &[f64]) -> Result<f64, InterpolationError> {
+        self.interpolator.interpolate(query)
+    }
+}
+
+/// Creates an interpolator with `ndim` dimensions, configured by `config`, and stores it in
+/// `interpolator`.
+///
+/// The length of the i-th axis is `shape[i]` and its nodes are `axes[i]`, while `values` holds
+/// the values of the function at the nodes in row-major (C) order, i.e. with the last axis
+/// running fastest.
+///
+/// # Safety
+///
+/// `config` must point to a configuration, whose `extrapolation` is either NULL or an array
+/// of length `ndim`. `shape` and `axes` must be arrays of length `ndim`, each `axes[i]` must be an
+/// array of length `shape[i]` and `values` must be an array with as many elements as the product
+/// of the entries of `shape`. `interpolator` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_interpolator_new(
+    config: *const Config,
+    ndim: usize,
+    shape: *const usize,
+    axes: *const *const f64,
+    values: *const f64,
+    interpolator: *mut *mut NdInterpolator,
+) -> Status {
+    ffi_call(|| {
+        let config = as_ref(config, "config")?;
+        if shape.is_null() {
+            return Err(Error::NullPointer("shape"));
+        }
+        if axes.is_null() {
+            return Err(Error::NullPointer("axes"));
+        }
+        let shape = slice::from_raw_parts(shape, ndim);
+        let axes = slice::from_raw_parts(axes, ndim);
+        let policies = if config.extrapolation.is_null() {
+            None
+        } else {
+            Some(
+                slice::from_raw_parts(config.extrapolation, ndim)
+                    .iter()
+                    .map(|&policy| policy.try_into())
+                    .collect::<Result<_, _>>()
+                    .map_err(|kind| {
+                        Error::InvalidArgument(format!("{kind} is not a valid extrapolation kind"))
+                    })?,
+            )
+        };
+
+        let method = Method::try_from(config.method).map_err(|method| {
+            Error::InvalidArgument(format!("{method} is not a valid interpolation method"))
+        })?;
+
+        let dyn_interpolator = match (method, ndim) {
+            (Method::Cubic, 1) => {
+                build::<1, _>(|grid| Cubic { grid }, shape, axes, values, policies)?
+            }
+            (Method::Cubic, 2) => {
+                build::<2, _>(|grid| Cubic { grid }, shape, axes, values, policies)?
+            }
+            (Method::Linear, 1) => {
+                build::<1, _>(|grid| Linear { grid }, shape, axes, values, policies)?
+            }
+            (Method::Linear, 2) => {
+                build::<2, _>(|grid| Linear { grid }, shape, axes, values, policies)?
+            }
+            (Method::Linear, 3) => {
+                build::<3, _>(|grid| Linear { grid }, shape, axes, values, policies)?
+            }
+            (Method::Linear, 4) => {
+                build::<4, _>(|grid| Linear { grid }, shape, axes, values, policies)?
+            }
+            (Method::Linear, 5) => {
+                build::<5, _>(|grid| Linear { grid }, shape, axes, values, policies)?
+            }
+            (Method::Linear, 6) => {
+                build::<6, _>(|grid| Linear { grid }, shape, axes, values, policies)?
+            }
+            (method, _) => {
+                return Err(InterpolationError::InvalidGrid(format!(
+                    "{ndim}-dimensional interpolation is not supported by the method {method:?}"
+                ))
+                .into())
+            }
+        };
+
+        write_handle(
+            interpolator,
+            NdInterpolator {
+                interpolator: dyn_interpolator,
+                ndim,
+            },
+            "interpolator",
+        )
+    })
+}
+
+/// Interpolates at `query`, an array with one coordinate per dimension, storing the value in
+/// `result`.
+///
+/// # Safety
+///
+/// `interpolator` must have been created by [`ndinterp_interpolator_new`], `query` must be an
+/// array with as many elements as the dimensions of the interpolator and `result` must be valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_interpolator_eval(
+    interpolator: *const NdInterpolator,
+    query: *const f64,
+    result: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let interpolator = as_handle(interpolator, "interpolator")?;
+        if query.is_null() {
+            return Err(Error::NullPointer("query"));
+        }
+        let query = slice::from_raw_parts(query, interpolator.ndim);
+
+        write_out(result, interpolator.interpolate(query)?, "result")
+    })
+}
+
+/// Interpolates at the `npoints` points of `queries`, storing the values in `results` and, unless
+/// it is NULL, the status of each point in `statuses`.
+///
+/// The coordinates of the i-th point are `queries[i * ndim]`, ..., `queries[i * ndim + ndim - 1]`
+/// where `ndim` is the number of dimensions of the interpolator. The points that fail are set to
+/// NaN, and the status of the first of them is returned.
+///
+/// # Safety
+///
+/// `interpolator` must have been created by [`ndinterp_interpolator_new`], `queries` must be an
+/// array of length `npoints * ndim`, `results` an array of length `npoints`, and so must be
+/// `statuses` unless it is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_interpolator_eval_batch(
+    interpolator: *const NdInterpolator,
+    queries: *const f64,
+    npoints: usize,
+    results: *mut f64,
+    statuses: *mut Status,
+) -> Status {
+    ffi_call(|| {
+        let interpolator = as_handle(interpolator, "interpolator")?;
+        if queries.is_null() {
+            return Err(Error::NullPointer("queries"));
+        }
+        let ndim = interpolator.ndim;
+        let queries =
+            slice::from_raw_parts(queries, array_len::<f64>(&[npoints, ndim], "queries")?);
+
+        evaluate_batch(npoints, results, statuses, |i| {
+            interpolator.interpolate(&queries[i * ndim..(i + 1) * ndim])
+        })
+    })
+}
+
+/// Interpolates at `query`, an array with one coordinate per dimension, storing the value in
+/// `result` and its partial derivatives in `gradient`.
+///
+/// # Safety
+///
+/// `interpolator` must have been created by [`ndinterp_interpolator_new`], `query` and `gradient`
+/// must be arrays with as many elements as the dimensions of the interpolator and `result` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_interpolator_eval_gradient(
+    interpolator: *const NdInterpolator,
+    query: *const f64,
+    result: *mut f64,
+    gradient: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let interpolator = as_handle(interpolator, "interpolator")?;
+        let ndim = interpolator.ndim;
+        let query = as_slice(query, ndim, "query")?;
+        let gradient = as_mut_slice(gradient, ndim, "gradient")?;
+
+        let value = interpolator
+            .interpolator
+            .value_and_gradient(query, gradient)?;
+        write_out(result, value, "result")
+    })
+}
+
+/// Integrates the interpolated function over the box with corners `lower` and `upper`, storing
+/// the integral in `result`. The bounds must lie within the grid, whatever the extrapolation
+/// policy, and swapping them along one axis flips the sign of the result.
+///
+/// # Safety
+///
+/// `interpolator` must have been created by [`ndinterp_interpolator_new`], `lower` and `upper`
+/// must be arrays with as many elements as the dimensions of the interpolator and `result` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_interpolator_integrate(
+    interpolator: *const NdInterpolator,
+    lower: *const f64,
+    upper: *const f64,
+    result: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let interpolator = as_handle(interpolator, "interpolator")?;
+        let lower = as_slice(lower, interpolator.ndim, "lower")?;
+        let upper = as_slice(upper, interpolator.ndim, "upper")?;
+
+        let integral = interpolator.interpolator.integrate(lower, upper)?;
+        write_out(result, integral, "result")
+    })
+}
+
+/// Stores the number of dimensions of `interpolator` in `ndim`.
+///
+/// # Safety
+///
+/// `interpolator` must have been created by [`ndinterp_interpolator_new`] and `ndim` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_interpolator_ndim(
+    interpolator: *const NdInterpolator,
+    ndim: *mut usize,
+) -> Status {
+    ffi_call(|| write_out(ndim, as_handle(interpolator, "interpolator")?.ndim, "ndim"))
+}
+
+/// Deletes an object created by [`ndinterp_interpolator_new`]. Deleting a NULL pointer does
+/// nothing.
+///
+/// # Safety
+///
+/// The object given to this function must have been created by [`ndinterp_interpolator_new`] and
+/// this function must not have been called with it before.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_interpolator_delete(interpolator: *mut NdInterpolator) -> Status {
+    ffi_call(|| delete_handle(interpolator, "interpolator"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    unsafe fn new_2d(config: &Config) -> (Status, *mut NdInterpolator) {
+        let x1 = [0., 1., 2.];
+        let x2 = [0., 1., 2., 3.];
+        let values = [1., 2., 3., 4., 2., 3., 4., 5., 4., 5., 6., 8.];
+        let mut interpolator = ptr::null_mut();
+
+        let status = ndinterp_interpolator_new(
+            config,
+            2,
+            [3, 4].as_ptr(),
+            [x1.as_ptr(), x2.as_ptr()].as_ptr(),
+            values.as_ptr(),
+            &mut interpolator,
+        );
+        (status, interpolator)
+    }
+
+    unsafe fn eval(interpolator: *const NdInterpolator, query: [f64; 2]) -> (Status, f64) {
+        let mut result = f64::NAN;
+        let status = ndinterp_interpolator_eval(interpolator, query.as_ptr(), &mut result);
+        (status, result)
+    }
+
+    #[test]
+    fn check_methods() {
+        unsafe {
+            let config = Config {
+                method: Method::Cubic as u32,
+                extrapolation: ptr::null(),
+            };
+            let (status, cubic) = new_2d(&config);
+            assert_eq!(status, Status::Ok);
+            assert_eq!(eval(cubic, [2., 3.]), (Status::Ok, 8.));
+            assert_eq!(eval(cubic, [2., 4.]).0, Status::ExtrapolationAbove);
+            ndinterp_interpolator_delete(cubic);
+
+            let config = Config {
+                method: Method::Linear as u32,
+                extrapolation: ptr::null(),
+            };
+            let (status, linear) = new_2d(&config);
+            assert_eq!(status, Status::Ok);
+            assert_eq!(eval(linear, [0.5, 0.5]), (Status::Ok, 2.));
+            assert_eq!(eval(linear, [-1., 0.5]).0, Status::ExtrapolationBelow);
+
+            let mut ndim = 0;
+            assert_eq!(ndinterp_interpolator_ndim(linear, &mut ndim), Status::Ok);
+            assert_eq!(ndim, 2);
+
+            let mut results = [0.; 2];
+            assert_eq!(
+                ndinterp_interpolator_eval_batch(
+                    linear,
+                    [0.5, 0.5, 2., 3.].as_ptr(),
+                    2,
+                    results.as_mut_ptr(),
+                    ptr::null_mut()
+                ),
+                Status::Ok
+            );
+            assert_eq!(results, [2., 8.]);
+            assert_eq!(
+                ndinterp_interpolator_eval_batch(
+                    linear,
+                    [0.5, 0.5].as_ptr(),
+                    usize::MAX / 2 + 1,
+                    results.as_mut_ptr(),
+                    ptr::null_mut()
+                ),
+                Status::InvalidArgument
+            );
+
+            // in the first cell the function is 1 + x1 + x2
+            let (mut result, mut gradient) = (0., [0.; 2]);
+            assert_eq!(
+                ndinterp_interpolator_eval_gradient(
+                    linear,
+                    [0.5, 0.5].as_ptr(),
+                    &mut result,
+                    gradient.as_mut_ptr()
+                ),
+                Status::Ok
+            );
+            assert_eq!((result, gradient), (2., [1., 1.]));
+            assert_eq!(
+                ndinterp_interpolator_integrate(
+                    linear,
+                    [0., 0.].as_ptr(),
+                    [1., 1.].as_ptr(),
+                    &mut result
+                ),
+                Status::Ok
+            );
+            assert_eq!(result, 2.);
+            assert_eq!(
+                ndinterp_interpolator_integrate(
+                    linear,
+                    [0., 0.].as_ptr(),
+                    ptr::null(),
+                    &mut result
+                ),
+                Status::NullPointer
+            );
+            ndinterp_interpolator_delete(linear);
+        }
+    }
+
+    #[test]
+    fn check_extrapolation() {
+        let policies = [
+            ExtrapolationPolicy {
+                kind: ExtrapolationKind::Clamp as u32,
+                value: 0.,
+            },
+            ExtrapolationPolicy {
+                kind: ExtrapolationKind::Constant as u32,
+                value: -1.,
+            },
+        ];
+        let config = Config {
+            method: Method::Cubic as u32,
+            extrapolation: policies.as_ptr(),
+        };
+
+        unsafe {
+            let (status, cubic) = new_2d(&config);
+            assert_eq!(status, Status::Ok);
+            assert_eq!(eval(cubic, [5., 3.]), (Status::Ok, 8.));
+            assert_eq!(eval(cubic, [1., 3.5]), (Status::Ok, -1.));
+            ndinterp_interpolator_delete(cubic);
+        }
+    }
+
+    #[test]
+    fn check_invalid_config() {
+        let x = [0., 1.];
+        let new_1d = |config: &Config| {
+            let mut interpolator = ptr::null_mut();
+            let status = unsafe {
+                ndinterp_interpolator_new(
+                    config,
+                    1,
+                    [2].as_ptr(),
+                    [x.as_ptr()].as_ptr(),
+                    [0.; 2].as_ptr(),
+                    &mut interpolator,
+                )
+            };
+            (status, interpolator)
+        };
+
+        let config = Config {
+            method: 7,
+            extrapolation: ptr::null(),
+        };
+        let (status, interpolator) = new_1d(&config);
+        assert_eq!(status, Status::InvalidArgument);
+        assert!(interpolator.is_null());
+
+        let policies = [ExtrapolationPolicy {
+            kind: u32::MAX,
+            value: 0.,
+        }];
+        let config = Config {
+            method: Method::Linear as u32,
+            extrapolation: policies.as_ptr(),
+        };
+        let (status, interpolator) = new_1d(&config);
+        assert_eq!(status, Status::InvalidArgument);
+        assert!(interpolator.is_null());
+    }
+
+    #[test]
+    fn check_unsupported() {
+        let x = [0., 1.];
+        let mut interpolator = ptr::null_mut();
+        let config = Config {
+            method: Method::Cubic as u32,
+            extrapolation: ptr::null(),
+        };
+
+        unsafe {
+            assert_eq!(
+                ndinterp_interpolator_new(
+                    &config,
+                    3,
+                    [2; 3].as_ptr(),
+                    [x.as_ptr(); 3].as_ptr(),
+                    [0.; 8].as_ptr(),
+                    &mut interpolator
+                ),
+                Status::InvalidGrid
+            );
+            assert!(interpolator.is_null());
+            assert_eq!(
+                ndinterp_interpolator_new(
+                    ptr::null(),
+                    1,
+                    [2].as_ptr(),
+                    [x.as_ptr()].as_ptr(),
+                    [0.; 2].as_ptr(),
+                    &mut interpolator
+                ),
+                Status::NullPointer
+            );
+        }
+    }
+}
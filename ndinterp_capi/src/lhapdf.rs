@@ -0,0 +1,286 @@
+//! Loading of LHAPDF sets
+//!
+//! PDF sets are given either by name, looked up in the folders listed in `LHAPDF_DATA_PATH` and
+//! `LHAPATH`, or by the path of their folder, see [`ndinterp::io::lhapdf`]. Each member yields a
+//! [`Pdf`] handle, evaluating x f(x, Q²), and an [`AlphaS`] handle, evaluating αs(Q²).
+
+use crate::batch::evaluate_batch;
+use crate::error::{ffi_call, write_out, Error, Status};
+use crate::handle::{as_handle, delete_handle, write_handle};
+use core::slice;
+use ndinterp::io::lhapdf::{self, PdfSet};
+use std::ffi::{c_char, c_int, CStr};
+
+/// A member of an LHAPDF set
+#[derive(Debug)]
+pub struct Pdf(lhapdf::Pdf);
+
+/// The αs interpolation of a member of an LHAPDF set
+#[derive(Debug)]
+pub struct AlphaS(lhapdf::AlphaS);
+
+/// Opens the set named by the NUL-terminated string `set`
+///
+/// # Safety
+///
+/// If not NULL, `set` must point to a NUL-terminated string.
+unsafe fn open_set(set: *const c_char) -> Result<PdfSet, Error> {
+    if set.is_null() {
+        return Err(Error::NullPointer("set"));
+    }
+    // names that are not UTF-8 cannot match any set and are reported as not found
+    Ok(PdfSet::open(&CStr::from_ptr(set).to_string_lossy())?)
+}
+
+/// Loads `member` of the LHAPDF set `set`, given by name or path, and stores it in `pdf`.
+///
+/// # Safety
+///
+/// `set` must be a NUL-terminated string and `pdf` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_pdf_load(
+    set: *const c_char,
+    member: usize,
+    pdf: *mut *mut Pdf,
+) -> Status {
+    ffi_call(|| {
+        let loaded = Pdf(open_set(set)?.member(member)?);
+        write_handle(pdf, loaded, "pdf")
+    })
+}
+
+/// Evaluates x f(x, Q²) for the flavour `pid`, given as a PDG id, at `x` and `q2`, storing the
+/// value in `result`. As done by LHAPDF, the value is zero for flavours not in the set.
+///
+/// # Safety
+///
+/// `pdf` must point to an object created by [`ndinterp_pdf_load`] and `result` must be valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_pdf_xfxq2(
+    pdf: *const Pdf,
+    pid: c_int,
+    x: f64,
+    q2: f64,
+    result: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let value = as_handle(pdf, "pdf")?.0.xfx_q2(pid, x, q2)?;
+        write_out(result, value, "result")
+    })
+}
+
+/// Evaluates x f(x, Q²) for the flavour `pid` at the `npoints` points (`x[i]`, `q2[i]`), storing
+/// the values in `results` and, unless it is NULL, the status of each point in `statuses`.
+///
+/// The points outside the grid are set to NaN, and the status of the first of them is returned.
+///
+/// # Safety
+///
+/// `pdf` must point to an object created by [`ndinterp_pdf_load`], `x`, `q2` and `results` must
+/// be arrays of length `npoints`, and so must be `statuses` unless it is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_pdf_xfxq2_batch(
+    pdf: *const Pdf,
+    pid: c_int,
+    x: *const f64,
+    q2: *const f64,
+    npoints: usize,
+    results: *mut f64,
+    statuses: *mut Status,
+) -> Status {
+    ffi_call(|| {
+        let pdf = as_handle(pdf, "pdf")?;
+        if x.is_null() {
+            return Err(Error::NullPointer("x"));
+        }
+        if q2.is_null() {
+            return Err(Error::NullPointer("q2"));
+        }
+        let x = slice::from_raw_parts(x, npoints);
+        let q2 = slice::from_raw_parts(q2, npoints);
+
+        evaluate_batch(npoints, results, statuses, |i| {
+            pdf.0.xfx_q2(pid, x[i], q2[i])
+        })
+    })
+}
+
+/// Deletes an object created by [`ndinterp_pdf_load`]. Deleting a NULL pointer does nothing.
+///
+/// # Safety
+///
+/// The object given to this function must have been created by [`ndinterp_pdf_load`] and this
+/// function must not have been called with it before.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_pdf_delete(pdf: *mut Pdf) -> Status {
+    ffi_call(|| delete_handle(pdf, "pdf"))
+}
+
+/// Loads the αs interpolation of `member` of the LHAPDF set `set`, given by name or path, and
+/// stores it in `alphas`. Only the `ipol` type of LHAPDF is supported.
+///
+/// # Safety
+///
+/// `set` must be a NUL-terminated string and `alphas` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_alphas_load(
+    set: *const c_char,
+    member: usize,
+    alphas: *mut *mut AlphaS,
+) -> Status {
+    ffi_call(|| {
+        let loaded = AlphaS(open_set(set)?.alphas(member)?);
+        write_handle(alphas, loaded, "alphas")
+    })
+}
+
+/// Evaluates αs at the scale `q2`, storing the value in `result`.
+///
+/// # Safety
+///
+/// `alphas` must point to an object created by [`ndinterp_alphas_load`] and `result` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_alphas_alphasq2(
+    alphas: *const AlphaS,
+    q2: f64,
+    result: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let value = as_handle(alphas, "alphas")?.0.alphas_q2(q2)?;
+        write_out(result, value, "result")
+    })
+}
+
+/// Deletes an object created by [`ndinterp_alphas_load`]. Deleting a NULL pointer does nothing.
+///
+/// # Safety
+///
+/// The object given to this function must have been created by [`ndinterp_alphas_load`] and this
+/// function must not have been called with it before.
+#[no_mangle]
+pub unsafe extern "C" fn ndinterp_alphas_delete(alphas: *mut AlphaS) -> Status {
+    ffi_call(|| delete_handle(alphas, "alphas"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::path::PathBuf;
+    use std::{env, fs, ptr};
+
+    /// Directory in the temporary directory that is removed on drop, even when the test fails
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn check_load() {
+        let root =
+            TempDir(env::temp_dir().join(format!("ndinterp-capi-{}-lhapdf", std::process::id())));
+        let dir = root.0.join("CapiSet");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("CapiSet.info"),
+            "NumMembers: 1\nAlphaS_Qs: [1., 2., 4.]\nAlphaS_Vals: [0.3, 0.2, 0.1]\n",
+        )
+        .unwrap();
+        // x f(x, Q²) = ln Q² for the gluon and 2 ln Q² for the up quark
+        let mut data = "PdfType: central\n---\n0.1 0.5 1\n1 2 4\n2 21\n".to_owned();
+        for _ in 0..3 {
+            for q in [1_f64, 2., 4.] {
+                let logq2 = (q * q).ln();
+                data.push_str(&format!("{} {}\n", 2. * logq2, logq2));
+            }
+        }
+        data.push_str("---\n");
+        fs::write(dir.join("CapiSet_0000.dat"), &data).unwrap();
+
+        // a set without αs nodes
+        let empty_dir = root.0.join("EmptySet");
+        fs::create_dir_all(&empty_dir).unwrap();
+        fs::write(
+            empty_dir.join("EmptySet.info"),
+            "NumMembers: 1\nAlphaS_Qs: []\nAlphaS_Vals: []\n",
+        )
+        .unwrap();
+        fs::write(empty_dir.join("EmptySet_0000.dat"), data).unwrap();
+        let empty = CString::new(empty_dir.to_str().unwrap()).unwrap();
+
+        let set = CString::new(dir.to_str().unwrap()).unwrap();
+        let missing = CString::new("NoSuchSet").unwrap();
+        let mut pdf = ptr::null_mut();
+        let mut alphas = ptr::null_mut();
+        let mut result = 0.;
+
+        unsafe {
+            assert_eq!(ndinterp_pdf_load(set.as_ptr(), 0, &mut pdf), Status::Ok);
+            assert_eq!(
+                ndinterp_pdf_xfxq2(pdf, 21, 0.3, 4., &mut result),
+                Status::Ok
+            );
+            assert!((result - 4_f64.ln()).abs() < 1e-12);
+            assert_eq!(ndinterp_pdf_xfxq2(pdf, 2, 0.3, 4., &mut result), Status::Ok);
+            assert!((result - 2. * 4_f64.ln()).abs() < 1e-12);
+            assert_eq!(ndinterp_pdf_xfxq2(pdf, 1, 0.3, 4., &mut result), Status::Ok);
+            assert_eq!(result, 0.);
+
+            let mut results = [0.; 2];
+            let mut statuses = [Status::Ok; 2];
+            assert_eq!(
+                ndinterp_pdf_xfxq2_batch(
+                    pdf,
+                    0,
+                    [0.3, 0.3].as_ptr(),
+                    [4., 20.].as_ptr(),
+                    2,
+                    results.as_mut_ptr(),
+                    statuses.as_mut_ptr()
+                ),
+                Status::ExtrapolationAbove
+            );
+            assert!((results[0] - 4_f64.ln()).abs() < 1e-12);
+            assert!(results[1].is_nan());
+            assert_eq!(statuses, [Status::Ok, Status::ExtrapolationAbove]);
+            assert_eq!(ndinterp_pdf_delete(pdf), Status::Ok);
+
+            assert_eq!(
+                ndinterp_alphas_load(set.as_ptr(), 0, &mut alphas),
+                Status::Ok
+            );
+            assert_eq!(
+                ndinterp_alphas_alphasq2(alphas, 4., &mut result),
+                Status::Ok
+            );
+            assert!((result - 0.2).abs() < 1e-12);
+            assert_eq!(
+                ndinterp_alphas_alphasq2(alphas, 0.5, &mut result),
+                Status::ExtrapolationBelow
+            );
+            assert_eq!(ndinterp_alphas_delete(alphas), Status::Ok);
+            assert_eq!(
+                ndinterp_alphas_load(empty.as_ptr(), 0, &mut alphas),
+                Status::FileError
+            );
+
+            assert_eq!(
+                ndinterp_pdf_load(set.as_ptr(), 1, &mut pdf),
+                Status::FileError
+            );
+            assert_eq!(
+                ndinterp_pdf_load(missing.as_ptr(), 0, &mut pdf),
+                Status::FileError
+            );
+            assert_eq!(
+                ndinterp_alphas_load(ptr::null(), 0, &mut alphas),
+                Status::NullPointer
+            );
+        }
+    }
+}
@@ -1,71 +1,218 @@
 //! C interface for ndinterp
+//!
+//! Every function returns a [`Status`] and writes its results through out-parameters, see the
+//! [`error`] module.
 #![warn(clippy::all, clippy::cargo)]
+#![allow(clippy::multiple_crate_versions)]
 #![warn(missing_docs)]
 
 use core::slice;
 
 use ndarray::ArrayView1;
 use ndinterp::grid;
-use ndinterp::interpolate::Interpolator;
+use ndinterp::interpolate::{Differentiate, Integrate, Interpolator};
+
+mod batch;
+pub mod cubic;
+pub mod error;
+mod handle;
+pub mod interpolator;
+pub mod lhapdf;
+
+use batch::evaluate_batch;
+pub use cubic::CubicNd;
+use error::{array_len, ffi_call, write_out, Error};
+pub use error::{ndinterp_last_error_message, Status};
+use handle::{as_handle, delete_handle, write_handle};
+pub use interpolator::{Config, ExtrapolationKind, ExtrapolationPolicy, Method, NdInterpolator};
+pub use lhapdf::{AlphaS, Pdf};
 
 /// Cubic1d interpolator
 pub struct Cubic1d(grid::cubic::Cubic<1>);
-/// Cubic2d interpolator
-pub struct Cubic2d(grid::cubic::Cubic<2>);
+/// Cubic2d interpolator, owning or borrowing its values
+pub struct Cubic2d(grid::cubic::Cubic<2, cubic::Values>);
 
-/// Creates a Cubic1d interpolator given the nodes
-/// and the values of the function in said nodes
+/// Creates a Cubic1d interpolator given the nodes and the values of the function in said nodes,
+/// and stores it in `interpolator`.
 ///
 /// # Safety
 ///
 /// This function is only safe to call as long as `xgrid_c` and `values_c` are arrays with sizes
-/// larger or equal to `size`.
+/// larger or equal to `size`, and `interpolator` is valid for writes.
 #[no_mangle]
 pub unsafe extern "C" fn create_cubic_interpolator1d(
     xgrid_c: *const f64,
     values_c: *const f64,
     size: usize,
-) -> Box<Cubic1d> {
-    // Use slice instead of vec, so that rust doesn't release the memory coming from C++
-    let slice_input = unsafe { slice::from_raw_parts(xgrid_c, size) };
-    // Make a copy of the data into a vector (of vectors) for rust to own
-    let xgrid = vec![slice_input.to_vec()];
-    let values = ArrayView1::from_shape_ptr(size, values_c);
-
-    let grid = grid::Grid {
-        xgrid,
-        values: values.into_owned(),
-    };
-    let cubic_interpolator = Cubic1d(grid::cubic::Cubic { grid });
-    Box::new(cubic_interpolator)
+    interpolator: *mut *mut Cubic1d,
+) -> Status {
+    ffi_call(|| {
+        if xgrid_c.is_null() {
+            return Err(Error::NullPointer("xgrid_c"));
+        }
+        if values_c.is_null() {
+            return Err(Error::NullPointer("values_c"));
+        }
+        array_len::<f64>(&[size], "values_c")?;
+
+        // Use slice instead of vec, so that rust doesn't release the memory coming from C++
+        let slice_input = unsafe { slice::from_raw_parts(xgrid_c, size) };
+        // Make a copy of the data into a vector (of vectors) for rust to own
+        let xgrid = vec![slice_input.to_vec()];
+        let values = ArrayView1::from_shape_ptr(size, values_c);
+
+        let grid = grid::Grid::new(xgrid, values.into_owned())?;
+        let cubic_interpolator = Cubic1d(grid::cubic::Cubic { grid });
+        write_handle(interpolator, cubic_interpolator, "interpolator")
+    })
 }
 
-/// Deletes an object created by [`create_cubic_interpolator1d`].
+/// Deletes an object created by [`create_cubic_interpolator1d`]. Deleting a NULL pointer does
+/// nothing.
 ///
 /// # Safety
 ///
 /// The object given to this function must have been created by [`create_cubic_interpolator1d`] and
 /// this function must not have been called with it before.
 #[no_mangle]
-pub unsafe extern "C" fn delete_cubic_interpolator1d(_: Box<Cubic1d>) {}
+pub unsafe extern "C" fn delete_cubic_interpolator1d(interpolator: *mut Cubic1d) -> Status {
+    ffi_call(|| delete_handle(interpolator, "interpolator"))
+}
+
+/// Perform Cubic1d interpolation in a previously generated interpolator, storing the value in
+/// `result`
+///
+/// # Safety
+///
+/// The parameter `interpolator` must point to an object created by
+/// [`create_cubic_interpolator1d`] and `result` must be valid for writes, otherwise this function
+/// is not safe to call.
+#[no_mangle]
+pub unsafe extern "C" fn interpolate_cubic_1d(
+    interpolator: *const Cubic1d,
+    query: f64,
+    result: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let value = as_handle(interpolator, "interpolator")?
+            .0
+            .interpolate(query)?;
+        write_out(result, value, "result")
+    })
+}
+
+/// Performs Cubic1d interpolation at the `npoints` values of `queries`, storing the values in
+/// `results` and, unless it is NULL, the status of each point in `statuses`.
+///
+/// The points outside the grid are set to NaN, and the status of the first of them is returned.
+///
+/// # Safety
+///
+/// The parameter `interpolator` must point to an object created by
+/// [`create_cubic_interpolator1d`], `queries` and `results` must be arrays of length `npoints`, and
+/// so must be `statuses` unless it is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn interpolate_cubic_1d_batch(
+    interpolator: *const Cubic1d,
+    queries: *const f64,
+    npoints: usize,
+    results: *mut f64,
+    statuses: *mut Status,
+) -> Status {
+    ffi_call(|| {
+        let interpolator = as_handle(interpolator, "interpolator")?;
+        if queries.is_null() {
+            return Err(Error::NullPointer("queries"));
+        }
+        let queries = slice::from_raw_parts(queries, npoints);
+
+        evaluate_batch(npoints, results, statuses, |i| {
+            interpolator.0.interpolate(queries[i])
+        })
+    })
+}
+
+/// Perform Cubic1d interpolation at `query`, storing the value in `result` and the derivative in
+/// `derivative`
+///
+/// # Safety
+///
+/// The parameter `interpolator` must point to an object created by
+/// [`create_cubic_interpolator1d`] and `result` and `derivative` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn derivative_cubic_1d(
+    interpolator: *const Cubic1d,
+    query: f64,
+    result: *mut f64,
+    derivative: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let mut gradient = [0.];
+        let value = as_handle(interpolator, "interpolator")?
+            .0
+            .value_and_gradient(&[query], &mut gradient)?;
+        write_out(derivative, gradient[0], "derivative")?;
+        write_out(result, value, "result")
+    })
+}
 
-/// Perform Cubic1d interpolation in a previously generated interpolator
+/// Integrates the Cubic1d interpolation between `lower` and `upper`, which must lie within the
+/// grid, storing the integral in `result`
 ///
 /// # Safety
 ///
 /// The parameter `interpolator` must point to an object created by
-/// [`create_cubic_interpolator1d`], otherwise this function is not safe to call.
+/// [`create_cubic_interpolator1d`] and `result` must be valid for writes.
 #[no_mangle]
-pub unsafe extern "C" fn interpolate_cubic_1d(interpolator: *mut Cubic1d, query: f64) -> f64 {
-    (*interpolator).0.interpolate(query).unwrap()
+pub unsafe extern "C" fn integrate_cubic_1d(
+    interpolator: *const Cubic1d,
+    lower: f64,
+    upper: f64,
+    result: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let integral = as_handle(interpolator, "interpolator")?
+            .0
+            .integrate(&[lower], &[upper])?;
+        write_out(result, integral, "result")
+    })
 }
 
 // 2D version of the functions above
-/// Creates a cubic interpolator 2d Cubic2d
+/// Creates a [`Cubic2d`], see [`create_cubic_interpolator2d`] and
+/// [`create_cubic_interpolator2d_borrowed`]
+///
+/// # Safety
+///
+/// See [`create_cubic_interpolator2d_borrowed`], `values_c` must stay valid only if `borrow` is
+/// true.
+unsafe fn new_cubic_2d(
+    x1_c: *const f64,
+    x2_c: *const f64,
+    values_c: *const f64,
+    size1: usize,
+    size2: usize,
+    interpolator: *mut *mut Cubic2d,
+    borrow: bool,
+) -> Result<(), Error> {
+    for (ptr, name) in [(x1_c, "x1_c"), (x2_c, "x2_c"), (values_c, "values_c")] {
+        if ptr.is_null() {
+            return Err(Error::NullPointer(name));
+        }
+    }
+
+    let grid = cubic::cow_grid_from_raw(&[size1, size2], &[x1_c, x2_c], values_c, borrow)?;
+    let cubic_interpolator = Cubic2d(grid::cubic::Cubic { grid });
+    write_handle(interpolator, cubic_interpolator, "interpolator")
+}
+
+/// Creates a cubic interpolator 2d Cubic2d and stores it in `interpolator`, copying the nodes and
+/// the values
+///
 /// # Safety
 ///
-/// This function is only safe to call as long as `xN_c` have size equal to `sizeN`
-/// and the size of `values_c` is equal to size1*size2
+/// This function is only safe to call as long as `xN_c` have size equal to `sizeN`, the size of
+/// `values_c` is equal to size1*size2 and `interpolator` is valid for writes
 #[no_mangle]
 pub unsafe extern "C" fn create_cubic_interpolator2d(
     x1_c: *const f64,
@@ -73,40 +220,488 @@ pub unsafe extern "C" fn create_cubic_interpolator2d(
     values_c: *const f64,
     size1: usize,
     size2: usize,
-) -> Box<Cubic2d> {
-    // Use slice instead of vec, so that rust doesn't release the memory coming from C++
-    let slice_x1 = unsafe { slice::from_raw_parts(x1_c, size1) };
-    let slice_x2 = unsafe { slice::from_raw_parts(x2_c, size2) };
-    // Make a copy of the data into a vector (of vectors) for rust to own
-    let xgrid = vec![slice_x1.to_vec(), slice_x2.to_vec()];
-    let values = ArrayView1::from_shape_ptr(size1 * size2, values_c)
-        .into_shape((size1, size2))
-        .unwrap();
-
-    let grid = grid::Grid {
-        xgrid,
-        values: values.into_owned(),
-    };
-    let cubic_interpolator = Cubic2d(grid::cubic::Cubic { grid });
-    Box::new(cubic_interpolator)
+    interpolator: *mut *mut Cubic2d,
+) -> Status {
+    ffi_call(|| new_cubic_2d(x1_c, x2_c, values_c, size1, size2, interpolator, false))
 }
 
-/// Interpolate 2D
+/// Creates a cubic interpolator 2d Cubic2d like [`create_cubic_interpolator2d`], but borrowing
+/// `values_c` instead of copying it, so that the caller and the interpolator share a single copy
+/// of the values. The nodes, which are much smaller, are still copied.
+///
+/// # Safety
+///
+/// Besides the requirements of [`create_cubic_interpolator2d`], the array `values_c` must stay
+/// valid and must not be modified until the interpolator is deleted with
+/// [`delete_cubic_interpolator2d`].
+#[no_mangle]
+pub unsafe extern "C" fn create_cubic_interpolator2d_borrowed(
+    x1_c: *const f64,
+    x2_c: *const f64,
+    values_c: *const f64,
+    size1: usize,
+    size2: usize,
+    interpolator: *mut *mut Cubic2d,
+) -> Status {
+    ffi_call(|| new_cubic_2d(x1_c, x2_c, values_c, size1, size2, interpolator, true))
+}
+
+/// Interpolate 2D, storing the value in `result`
+///
+/// # Safety
+///
+/// The parameter `interpolator` must point to an object created by
+/// [`create_cubic_interpolator2d`] and `result` must be valid for writes, otherwise this function
+/// is not safe to call.
+#[no_mangle]
+pub unsafe extern "C" fn interpolate_cubic_2d(
+    interpolator: *const Cubic2d,
+    x1: f64,
+    x2: f64,
+    result: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let value = as_handle(interpolator, "interpolator")?
+            .0
+            .interpolate(&[x1, x2][..])?;
+        write_out(result, value, "result")
+    })
+}
+
+/// Interpolate 2D at the `npoints` points (`x1[i]`, `x2[i]`), storing the values in `results`
+/// and, unless it is NULL, the status of each point in `statuses`.
+///
+/// The points outside the grid are set to NaN, and the status of the first of them is returned.
+///
+/// # Safety
+///
+/// The parameter `interpolator` must point to an object created by
+/// [`create_cubic_interpolator2d`], `x1`, `x2` and `results` must be arrays of length `npoints`,
+/// and so must be `statuses` unless it is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn interpolate_cubic_2d_batch(
+    interpolator: *const Cubic2d,
+    x1: *const f64,
+    x2: *const f64,
+    npoints: usize,
+    results: *mut f64,
+    statuses: *mut Status,
+) -> Status {
+    ffi_call(|| {
+        let interpolator = as_handle(interpolator, "interpolator")?;
+        if x1.is_null() {
+            return Err(Error::NullPointer("x1"));
+        }
+        if x2.is_null() {
+            return Err(Error::NullPointer("x2"));
+        }
+        let x1 = slice::from_raw_parts(x1, npoints);
+        let x2 = slice::from_raw_parts(x2, npoints);
+
+        evaluate_batch(npoints, results, statuses, |i| {
+            interpolator.0.interpolate(&[x1[i], x2[i]][..])
+        })
+    })
+}
+
+/// Interpolate 2D, storing the value in `result` and the partial derivatives with respect to `x1`
+/// and `x2` in `dx1` and `dx2`
 ///
 /// # Safety
 ///
 /// The parameter `interpolator` must point to an object created by
-/// [`create_cubic_interpolator2d`], otherwise this function is not safe to call.
+/// [`create_cubic_interpolator2d`] and `result`, `dx1` and `dx2` must be valid for writes.
 #[no_mangle]
-pub unsafe extern "C" fn interpolate_cubic_2d(interpolator: *mut Cubic2d, x1: f64, x2: f64) -> f64 {
-    (*interpolator).0.interpolate(&[x1, x2]).unwrap()
+pub unsafe extern "C" fn gradient_cubic_2d(
+    interpolator: *const Cubic2d,
+    x1: f64,
+    x2: f64,
+    result: *mut f64,
+    dx1: *mut f64,
+    dx2: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let mut gradient = [0.; 2];
+        let value = as_handle(interpolator, "interpolator")?
+            .0
+            .value_and_gradient(&[x1, x2], &mut gradient)?;
+        write_out(dx1, gradient[0], "dx1")?;
+        write_out(dx2, gradient[1], "dx2")?;
+        write_out(result, value, "result")
+    })
 }
 
-/// Destructor 2D
+/// Integrates the 2D interpolation over the rectangle [`lower1`, `upper1`] x [`lower2`, `upper2`],
+/// which must lie within the grid, storing the integral in `result`
+///
+/// # Safety
+///
+/// The parameter `interpolator` must point to an object created by
+/// [`create_cubic_interpolator2d`] and `result` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn integrate_cubic_2d(
+    interpolator: *const Cubic2d,
+    lower1: f64,
+    upper1: f64,
+    lower2: f64,
+    upper2: f64,
+    result: *mut f64,
+) -> Status {
+    ffi_call(|| {
+        let integral = as_handle(interpolator, "interpolator")?
+            .0
+            .integrate(&[lower1, lower2], &[upper1, upper2])?;
+        write_out(result, integral, "result")
+    })
+}
+
+/// Destructor 2D. Deleting a NULL pointer does nothing.
 ///
 /// # Safety
 ///
 /// The object given to this function must have been created by [`create_cubic_interpolator2d`] and
 /// this function must not have been called with it before.
 #[no_mangle]
-pub unsafe extern "C" fn delete_cubic_interpolator2d(_: Box<Cubic2d>) {}
+pub unsafe extern "C" fn delete_cubic_interpolator2d(interpolator: *mut Cubic2d) -> Status {
+    ffi_call(|| delete_handle(interpolator, "interpolator"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array2;
+    use ndinterp::grid::{cubic::Cubic, Grid};
+    use std::ffi::CStr;
+    use std::ptr;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(ndinterp_last_error_message()) }
+            .to_str()
+            .unwrap()
+            .to_owned()
+    }
+
+    #[test]
+    fn check_status_1d() {
+        let xgrid = [0., 1., 2., 3.];
+        let values = [1., 2., 4., 8.];
+        let mut cubic = ptr::null_mut();
+        let mut result = 0.;
+
+        unsafe {
+            assert_eq!(
+                create_cubic_interpolator1d(xgrid.as_ptr(), values.as_ptr(), 4, &mut cubic),
+                Status::Ok
+            );
+            assert_eq!(interpolate_cubic_1d(cubic, 2., &mut result), Status::Ok);
+            assert_eq!(result, 4.);
+
+            assert_eq!(
+                interpolate_cubic_1d(cubic, 3.5, &mut result),
+                Status::ExtrapolationAbove
+            );
+            assert!(last_error().contains("3.5"));
+            assert_eq!(
+                interpolate_cubic_1d(cubic, -1., &mut result),
+                Status::ExtrapolationBelow
+            );
+            assert_eq!(
+                interpolate_cubic_1d(cubic, 1., ptr::null_mut()),
+                Status::NullPointer
+            );
+            assert!(last_error().contains("result"));
+            assert_eq!(
+                interpolate_cubic_1d(ptr::null(), 1., &mut result),
+                Status::NullPointer
+            );
+
+            let mut derivative = 0.;
+            assert_eq!(
+                derivative_cubic_1d(cubic, 2., &mut result, &mut derivative),
+                Status::Ok
+            );
+            assert_eq!((result, derivative), (4., 3.));
+            assert_eq!(integrate_cubic_1d(cubic, 2., 0., &mut result), Status::Ok);
+            // the Hermite integrals over [0, 1] and [1, 2] are 35 / 24 and 23 / 8
+            assert!((result + 13. / 3.).abs() < 1e-12);
+            assert_eq!(
+                integrate_cubic_1d(cubic, 0., 5., &mut result),
+                Status::ExtrapolationAbove
+            );
+
+            assert_eq!(delete_cubic_interpolator1d(cubic), Status::Ok);
+            assert_eq!(delete_cubic_interpolator1d(ptr::null_mut()), Status::Ok);
+        }
+    }
+
+    #[test]
+    fn check_status_2d() {
+        let x1 = [0., 1., 2.];
+        let x2 = [0., 1., 2., 3.];
+        let values = [1., 2., 3., 4., 2., 3., 4., 5., 4., 5., 6., 8.];
+        let mut cubic = ptr::null_mut();
+        let mut result = 0.;
+
+        unsafe {
+            assert_eq!(
+                create_cubic_interpolator2d(
+                    x1.as_ptr(),
+                    x2.as_ptr(),
+                    values.as_ptr(),
+                    3,
+                    4,
+                    &mut cubic
+                ),
+                Status::Ok
+            );
+            assert_eq!(interpolate_cubic_2d(cubic, 1., 2., &mut result), Status::Ok);
+            assert_eq!(result, 4.);
+            assert_eq!(
+                interpolate_cubic_2d(cubic, 1., 4., &mut result),
+                Status::ExtrapolationAbove
+            );
+
+            let (mut dx1, mut dx2) = (0., 0.);
+            assert_eq!(
+                gradient_cubic_2d(cubic, 1., 1., &mut result, &mut dx1, &mut dx2),
+                Status::Ok
+            );
+            assert_eq!((result, dx1, dx2), (3., 1.5, 1.));
+            assert_eq!(
+                gradient_cubic_2d(cubic, 1., 1., &mut result, ptr::null_mut(), &mut dx2),
+                Status::NullPointer
+            );
+            assert_eq!(
+                integrate_cubic_2d(cubic, 0., 1., 0.5, 3., &mut result),
+                Status::Ok
+            );
+            // the same interpolator of the core library
+            let grid = Grid::<2>::new(
+                vec![x1.to_vec(), x2.to_vec()],
+                Array2::from_shape_vec((3, 4), values.to_vec()).unwrap(),
+            )
+            .unwrap();
+            let expected = Cubic { grid }.integrate(&[0., 0.5], &[1., 3.]).unwrap();
+            assert_eq!(result, expected);
+
+            assert_eq!(delete_cubic_interpolator2d(cubic), Status::Ok);
+
+            // linear functions are interpolated exactly, and so is their integral
+            let linear: Vec<_> = x1
+                .iter()
+                .flat_map(|a| x2.iter().map(move |b| 1. + 2. * a + 3. * b))
+                .collect();
+            assert_eq!(
+                create_cubic_interpolator2d(
+                    x1.as_ptr(),
+                    x2.as_ptr(),
+                    linear.as_ptr(),
+                    3,
+                    4,
+                    &mut cubic
+                ),
+                Status::Ok
+            );
+            assert_eq!(
+                integrate_cubic_2d(cubic, 0., 1., 0., 2., &mut result),
+                Status::Ok
+            );
+            // the integral of 1 + 2 x1 + 3 x2 over [0, 1] x [0, 2]
+            assert!((result - 10.).abs() < 1e-12, "{result}");
+            assert_eq!(delete_cubic_interpolator2d(cubic), Status::Ok);
+        }
+    }
+
+    #[test]
+    fn check_borrowed_2d() {
+        let x1 = [0., 1., 2.];
+        let x2 = [0., 1., 2., 3.];
+        let values = vec![1., 2., 3., 4., 2., 3., 4., 5., 4., 5., 6., 8.];
+        let mut owned = ptr::null_mut();
+        let mut borrowed = ptr::null_mut();
+        let (mut expected, mut result) = (0., 0.);
+
+        unsafe {
+            assert_eq!(
+                create_cubic_interpolator2d(
+                    x1.as_ptr(),
+                    x2.as_ptr(),
+                    values.as_ptr(),
+                    3,
+                    4,
+                    &mut owned
+                ),
+                Status::Ok
+            );
+            assert_eq!(
+                create_cubic_interpolator2d_borrowed(
+                    x1.as_ptr(),
+                    x2.as_ptr(),
+                    values.as_ptr(),
+                    3,
+                    4,
+                    &mut borrowed
+                ),
+                Status::Ok
+            );
+            // only the borrowed interpolator shares the values with the caller
+            assert_ne!(
+                as_handle(owned, "owned").unwrap().0.grid.values.as_ptr(),
+                values.as_ptr()
+            );
+            assert_eq!(
+                as_handle(borrowed, "borrowed")
+                    .unwrap()
+                    .0
+                    .grid
+                    .values
+                    .as_ptr(),
+                values.as_ptr()
+            );
+
+            for (q1, q2) in [(0.5, 2.5), (1.2, 0.3), (2., 3.)] {
+                assert_eq!(
+                    interpolate_cubic_2d(owned, q1, q2, &mut expected),
+                    Status::Ok
+                );
+                assert_eq!(
+                    interpolate_cubic_2d(borrowed, q1, q2, &mut result),
+                    Status::Ok
+                );
+                assert_eq!(result, expected);
+            }
+            assert_eq!(
+                create_cubic_interpolator2d_borrowed(
+                    x2.as_ptr(),
+                    x1.as_ptr(),
+                    ptr::null(),
+                    4,
+                    3,
+                    &mut borrowed
+                ),
+                Status::NullPointer
+            );
+
+            assert_eq!(delete_cubic_interpolator2d(owned), Status::Ok);
+            assert_eq!(delete_cubic_interpolator2d(borrowed), Status::Ok);
+        }
+        drop(values);
+    }
+
+    #[test]
+    fn check_batch() {
+        let xgrid = [0., 1., 2., 3.];
+        let values = [1., 2., 4., 8.];
+        let mut cubic1d = ptr::null_mut();
+        let queries = [0.5, 4., 2.5, -1.];
+        let mut results = [0.; 4];
+        let mut statuses = [Status::Ok; 4];
+
+        unsafe {
+            create_cubic_interpolator1d(xgrid.as_ptr(), values.as_ptr(), 4, &mut cubic1d);
+            assert_eq!(
+                interpolate_cubic_1d_batch(
+                    cubic1d,
+                    queries.as_ptr(),
+                    4,
+                    results.as_mut_ptr(),
+                    statuses.as_mut_ptr()
+                ),
+                Status::ExtrapolationAbove
+            );
+            assert_eq!(
+                statuses,
+                [
+                    Status::Ok,
+                    Status::ExtrapolationAbove,
+                    Status::Ok,
+                    Status::ExtrapolationBelow
+                ]
+            );
+            for i in [0, 2] {
+                let mut result = 0.;
+                interpolate_cubic_1d(cubic1d, queries[i], &mut result);
+                assert_eq!(results[i], result);
+            }
+            assert!(results[1].is_nan() && results[3].is_nan());
+
+            assert_eq!(
+                interpolate_cubic_1d_batch(
+                    cubic1d,
+                    queries.as_ptr(),
+                    1,
+                    results.as_mut_ptr(),
+                    ptr::null_mut()
+                ),
+                Status::Ok
+            );
+            delete_cubic_interpolator1d(cubic1d);
+        }
+
+        let mut cubic2d = ptr::null_mut();
+        let x1 = [0.5, 1.5, 3.];
+        let x2 = [2.5, 0., 0.];
+
+        unsafe {
+            create_cubic_interpolator2d(
+                xgrid.as_ptr(),
+                xgrid.as_ptr(),
+                [1.; 16].as_ptr(),
+                4,
+                4,
+                &mut cubic2d,
+            );
+            assert_eq!(
+                interpolate_cubic_2d_batch(
+                    cubic2d,
+                    x1.as_ptr(),
+                    x2.as_ptr(),
+                    3,
+                    results.as_mut_ptr(),
+                    ptr::null_mut()
+                ),
+                Status::Ok
+            );
+            assert_eq!(results[..3], [1.; 3]);
+            delete_cubic_interpolator2d(cubic2d);
+        }
+    }
+
+    #[test]
+    fn check_invalid_grid() {
+        let xgrid = [0., 2., 1.];
+        let values = [1., 2., 3.];
+        let mut cubic = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(
+                create_cubic_interpolator1d(xgrid.as_ptr(), values.as_ptr(), 3, &mut cubic),
+                Status::InvalidGrid
+            );
+            assert!(cubic.is_null());
+            assert!(last_error().contains("strictly increasing"));
+
+            assert_eq!(
+                create_cubic_interpolator1d(ptr::null(), values.as_ptr(), 3, &mut cubic),
+                Status::NullPointer
+            );
+            assert_eq!(
+                create_cubic_interpolator1d(
+                    xgrid.as_ptr(),
+                    values.as_ptr(),
+                    usize::MAX,
+                    &mut cubic
+                ),
+                Status::InvalidArgument
+            );
+            assert!(last_error().contains("too large"));
+        }
+    }
+
+    #[test]
+    fn check_panic() {
+        let status = error::ffi_call(|| panic!("boom"));
+        assert_eq!(status, Status::Panic);
+        assert!(last_error().contains("boom"));
+    }
+}
@@ -0,0 +1,209 @@
+//! Errors raised to Python
+
+use crate::exceptions;
+use ndinterp::interpolate::InterpolationError;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use thiserror::Error;
+
+/// Errors raised by the Python classes
+#[derive(Debug, Error)]
+pub(crate) enum Error {
+    /// An error raised by the interpolation library
+    #[error(transparent)]
+    Interpolation(#[from] InterpolationError),
+
+    /// A query is outside of the grid along `axis`
+    #[error("{error} in dimension {axis}")]
+    Extrapolation {
+        error: InterpolationError,
+        axis: usize,
+    },
+
+    /// The arrays given by the caller do not have the expected shapes
+    #[error("{0}")]
+    Shape(String),
+
+    /// An argument given by the caller has an invalid value
+    #[error("{0}")]
+    Argument(String),
+
+    /// A query is outside of the grid along `axis` and no fill value was given, reported with the
+    /// message of SciPy
+    #[error("One of the requested xi is out of bounds in dimension {axis}")]
+    OutOfBounds {
+        error: InterpolationError,
+        axis: usize,
+    },
+
+    /// An error raised by the Python interpreter, for instance while converting the arguments
+    #[error(transparent)]
+    Python(#[from] PyErr),
+}
+
+/// The first axis along which `query` is outside of `xgrid`, together with the error raised by
+/// the library for it. The axes are checked in the same order as the library does.
+pub(crate) fn outside(query: &[f64], xgrid: &[Vec<f64>]) -> Option<(usize, InterpolationError)> {
+    query
+        .iter()
+        .zip(xgrid)
+        .enumerate()
+        .find_map(|(axis, (&x, nodes))| {
+            if x > nodes[nodes.len() - 1] {
+                Some((axis, InterpolationError::ExtrapolationAbove(x)))
+            } else if x < nodes[0] {
+                Some((axis, InterpolationError::ExtrapolationBelow(x)))
+            } else {
+                None
+            }
+        })
+}
+
+impl Error {
+    /// Converts `error`, raised while interpolating at `query` on a grid with input vectors
+    /// `xgrid`, attaching the axis of the coordinate outside of the grid if it is an
+    /// extrapolation error
+    pub(crate) fn locate(error: InterpolationError, query: &[f64], xgrid: &[Vec<f64>]) -> Self {
+        match (&error, outside(query, xgrid)) {
+            (
+                InterpolationError::ExtrapolationAbove(_)
+                | InterpolationError::ExtrapolationBelow(_),
+                Some((axis, error)),
+            ) => Self::Extrapolation { error, axis },
+            _ => error.into(),
+        }
+    }
+
+    /// Whether the error is raised by a query outside of the grid, rather than by invalid
+    /// arguments
+    pub(crate) fn is_extrapolation(&self) -> bool {
+        matches!(
+            self,
+            Self::Extrapolation { .. }
+                | Self::OutOfBounds { .. }
+                | Self::Interpolation(
+                    InterpolationError::ExtrapolationAbove(_)
+                        | InterpolationError::ExtrapolationBelow(_)
+                )
+        )
+    }
+}
+
+/// Builds the exception matching `error`, with message `message`. The extrapolation errors are
+/// given the attributes `value` and `axis`, the latter `None` if unknown.
+fn exception(error: &InterpolationError, axis: Option<usize>, message: String) -> PyErr {
+    let (err, value) = match *error {
+        InterpolationError::ExtrapolationAbove(value) => {
+            (exceptions::ExtrapolationAbove::new_err(message), value)
+        }
+        InterpolationError::ExtrapolationBelow(value) => {
+            (exceptions::ExtrapolationBelow::new_err(message), value)
+        }
+        InterpolationError::InvalidGrid(_) => return exceptions::InvalidGrid::new_err(message),
+        InterpolationError::InvalidConfiguration(_) => {
+            return exceptions::InvalidConfiguration::new_err(message)
+        }
+        InterpolationError::InvalidQuery(_) => return exceptions::InvalidQuery::new_err(message),
+    };
+
+    Python::attach(|py| {
+        let instance = err.value(py);
+        match instance
+            .setattr("value", value)
+            .and_then(|()| instance.setattr("axis", axis))
+        {
+            Ok(()) => err,
+            Err(err) => err,
+        }
+    })
+}
+
+impl From<Error> for PyErr {
+    fn from(err: Error) -> Self {
+        let message = err.to_string();
+        match err {
+            Error::Python(err) => err,
+            Error::Interpolation(error) => exception(&error, None, message),
+            Error::Extrapolation { error, axis } | Error::OutOfBounds { error, axis } => {
+                exception(&error, Some(axis), message)
+            }
+            Error::Shape(_) | Error::Argument(_) => PyValueError::new_err(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_locate() {
+        let xgrid = vec![vec![0., 1.], vec![0., 2.], vec![-1., 1.]];
+        assert!(outside(&[0.5, 2., -1.], &xgrid).is_none());
+
+        let error = Error::locate(
+            InterpolationError::ExtrapolationBelow(-3.),
+            &[0.5, 3., -3.],
+            &xgrid,
+        );
+        // the first axis outside of the grid is the one reported by the library
+        assert!(matches!(
+            error,
+            Error::Extrapolation {
+                error: InterpolationError::ExtrapolationAbove(x),
+                axis: 1
+            } if x == 3.
+        ));
+        assert!(error.is_extrapolation());
+
+        let error = Error::locate(
+            InterpolationError::InvalidGrid(String::new()),
+            &[2.],
+            &xgrid,
+        );
+        assert!(matches!(error, Error::Interpolation(_)));
+        assert!(!error.is_extrapolation());
+    }
+
+    #[test]
+    fn check_exceptions() {
+        Python::initialize();
+        Python::attach(|py| {
+            let err = PyErr::from(Error::Extrapolation {
+                error: InterpolationError::ExtrapolationBelow(-2.),
+                axis: 1,
+            });
+            assert!(err.is_instance_of::<exceptions::ExtrapolationBelow>(py));
+            assert!(err.is_instance_of::<exceptions::InterpolationError>(py));
+            assert!(err.is_instance_of::<PyValueError>(py));
+            let value = err.value(py);
+            assert_eq!(
+                value.getattr("value").unwrap().extract::<f64>().unwrap(),
+                -2.
+            );
+            assert_eq!(
+                value.getattr("axis").unwrap().extract::<usize>().unwrap(),
+                1
+            );
+
+            let err = PyErr::from(Error::from(InterpolationError::ExtrapolationAbove(5.)));
+            assert!(err.is_instance_of::<exceptions::ExtrapolationAbove>(py));
+            assert!(err.value(py).getattr("axis").unwrap().is_none());
+
+            let err = PyErr::from(Error::from(InterpolationError::InvalidGrid(String::new())));
+            assert!(err.is_instance_of::<exceptions::InvalidGrid>(py));
+
+            let err = PyErr::from(Error::from(InterpolationError::InvalidConfiguration(
+                String::new(),
+            )));
+            assert!(err.is_instance_of::<exceptions::InvalidConfiguration>(py));
+
+            let err = PyErr::from(Error::from(InterpolationError::InvalidQuery(String::new())));
+            assert!(err.is_instance_of::<exceptions::InvalidQuery>(py));
+            assert!(err.is_instance_of::<exceptions::InterpolationError>(py));
+
+            let err = PyErr::from(Error::Shape(String::new()));
+            assert!(!err.is_instance_of::<exceptions::InterpolationError>(py));
+        });
+    }
+}
@@ -0,0 +1,425 @@
+//! Evaluation of the interpolators on arrays of points, following the broadcasting rules of NumPy
+//!
+//! The points are evaluated without holding the GIL, writing directly into the buffer of the
+//! NumPy array returned to Python, and can be split across several threads. Each point is
+//! evaluated by the same code whatever the number of threads, therefore the results are identical
+//! bit for bit.
+
+use crate::error::Error;
+use ndarray::{ArrayViewD, CowArray, IxDyn};
+use numpy::{PyArrayDyn, PyArrayMethods};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::collections::HashMap;
+use std::iter;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+/// Minimum number of points evaluated by each thread, smaller batches are not worth splitting
+const MIN_POINTS_PER_THREAD: usize = 1024;
+
+/// The pool with `threads` threads, built on first use and then kept for the following calls,
+/// since spawning the threads costs more than evaluating a small batch of points
+fn pool(threads: usize) -> Result<Arc<ThreadPool>, Error> {
+    static POOLS: OnceLock<Mutex<HashMap<usize, Arc<ThreadPool>>>> = OnceLock::new();
+
+    let mut pools = POOLS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(pool) = pools.get(&threads) {
+        return Ok(Arc::clone(pool));
+    }
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|err| Error::Argument(err.to_string()))?;
+    Ok(Arc::clone(pools.entry(threads).or_insert(Arc::new(pool))))
+}
+
+/// Shape of the result of broadcasting together arrays of the given `shapes`
+pub(crate) fn broadcast_shape<'a>(
+    shapes: impl IntoIterator<Item = &'a [usize]>,
+) -> Result<Vec<usize>, Error> {
+    let mut result = Vec::new();
+    for shape in shapes {
+        if shape.len() > result.len() {
+            let missing = shape.len() - result.len();
+            result.splice(0..0, iter::repeat_n(1, missing));
+        }
+        let offset = result.len() - shape.len();
+        for (len, &other) in result[offset..].iter_mut().zip(shape) {
+            if *len == 1 {
+                *len = other;
+            } else if other != 1 && other != *len {
+                return Err(Error::Shape(format!(
+                    "the query arrays cannot be broadcast together, found shape {shape:?} \
+                     against {result:?}"
+                )));
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Broadcasts `array` to `shape`, copying it only if it is not already contiguous
+fn contiguous<'a>(array: &'a ArrayViewD<'_, f64>, shape: &[usize]) -> CowArray<'a, f64, IxDyn> {
+    let array = array
+        .broadcast(shape)
+        .unwrap_or_else(|| unreachable!("the shape is compatible by construction"));
+    if array.is_standard_layout() {
+        array.into()
+    } else {
+        array.as_standard_layout().into_owned().into()
+    }
+}
+
+/// How the coordinates of the points are stored, always in row-major order
+enum Layout<'a> {
+    /// One array per dimension
+    Coordinates(Vec<CowArray<'a, f64, IxDyn>>),
+    /// A single array with the coordinates of each point next to each other
+    Interleaved(CowArray<'a, f64, IxDyn>),
+}
+
+/// The points at which an interpolator is evaluated
+pub(crate) struct Points<'a> {
+    shape: Vec<usize>,
+    ndim: usize,
+    layout: Layout<'a>,
+}
+
+impl<'a> Points<'a> {
+    /// The points whose coordinates are given by `coordinates`, one array per dimension, which
+    /// are broadcast together. The points have the broadcast shape.
+    pub(crate) fn from_coordinates(coordinates: &'a [ArrayViewD<'_, f64>]) -> Result<Self, Error> {
+        let shape = broadcast_shape(coordinates.iter().map(|axis| axis.shape()))?;
+        let layout = Layout::Coordinates(
+            coordinates
+                .iter()
+                .map(|axis| contiguous(axis, &shape))
+                .collect(),
+        );
+
+        Ok(Self {
+            shape,
+            ndim: coordinates.len(),
+            layout,
+        })
+    }
+
+    /// The points of `points`, whose last axis runs over the `ndim` coordinates of each point.
+    /// The points have the shape of `points` without its last axis.
+    pub(crate) fn from_points(points: &'a ArrayViewD<'_, f64>, ndim: usize) -> Result<Self, Error> {
+        match points.shape().split_last() {
+            Some((&last, shape)) if last == ndim => Ok(Self {
+                shape: shape.to_vec(),
+                ndim,
+                layout: Layout::Interleaved(contiguous(points, points.shape())),
+            }),
+            _ => Err(Error::Shape(format!(
+                "the last axis of the query array must have length {ndim}, found shape {:?}",
+                points.shape()
+            ))),
+        }
+    }
+
+    /// Shape of the array of points
+    pub(crate) fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// Number of points
+    pub(crate) fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Evaluates `eval` at the points from `start` on, storing `width` values per point in
+    /// `values`. If `mask` is given the points outside of the grid are masked, and their values
+    /// set to NaN, instead of failing.
+    fn evaluate_chunk<E>(
+        &self,
+        start: usize,
+        values: &mut [f64],
+        mut mask: Option<&mut [bool]>,
+        width: usize,
+        eval: &impl Fn(&[f64], &mut [f64]) -> Result<(), E>,
+    ) -> Result<(), Error>
+    where
+        Error: From<E>,
+    {
+        let mut query = vec![0.; self.ndim];
+        for (index, values) in values.chunks_exact_mut(width).enumerate() {
+            let point = start + index;
+            let result = match &self.layout {
+                Layout::Coordinates(coordinates) => {
+                    for (coordinate, axis) in query.iter_mut().zip(coordinates) {
+                        *coordinate = axis.as_slice().unwrap_or_else(|| unreachable!())[point];
+                    }
+                    eval(&query, values)
+                }
+                Layout::Interleaved(points) => {
+                    let points = points.as_slice().unwrap_or_else(|| unreachable!());
+                    eval(&points[point * self.ndim..(point + 1) * self.ndim], values)
+                }
+            };
+
+            if let Err(err) = result.map_err(Error::from) {
+                match mask.as_deref_mut() {
+                    Some(mask) if err.is_extrapolation() => {
+                        values.fill(f64::NAN);
+                        mask[index * width..(index + 1) * width].fill(true);
+                    }
+                    _ => return Err(err),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluates `eval` at every point, in row-major order, letting it write the values of each
+    /// point into a slice of `values` of length `values.len() / self.len()`. The points are split
+    /// across `threads` threads or, if `None`, the threads of the global `rayon` pool. The error
+    /// of the first failing point is returned, unless it is outside of the grid and `mask`, with
+    /// the same length as `values` and initially false, is given: the values of such points are
+    /// then masked.
+    pub(crate) fn evaluate_into<E>(
+        &self,
+        values: &mut [f64],
+        mask: Option<&mut [bool]>,
+        threads: Option<usize>,
+        eval: impl Fn(&[f64], &mut [f64]) -> Result<(), E> + Sync,
+    ) -> Result<(), Error>
+    where
+        E: Send,
+        Error: From<E>,
+    {
+        let threads = match threads {
+            Some(0) => {
+                return Err(Error::Argument(
+                    "the number of threads must be positive".to_owned(),
+                ))
+            }
+            Some(threads) => threads,
+            None => rayon::current_num_threads(),
+        };
+        let npoints = self.len();
+        if values.is_empty() {
+            return Ok(());
+        }
+        let width = values.len() / npoints;
+
+        let used = threads.min(npoints.div_ceil(MIN_POINTS_PER_THREAD));
+        if used <= 1 {
+            return self.evaluate_chunk(0, values, mask, width, &eval);
+        }
+
+        let chunk = npoints.div_ceil(used);
+        let masks: Vec<_> = match mask {
+            Some(mask) => mask.chunks_mut(chunk * width).map(Some).collect(),
+            None => iter::repeat_with(|| None).take(used).collect(),
+        };
+        let evaluate = || {
+            values
+                .par_chunks_mut(chunk * width)
+                .zip(masks)
+                .enumerate()
+                .map(|(index, (values, mask))| {
+                    self.evaluate_chunk(index * chunk, values, mask, width, &eval)
+                })
+                .collect::<Vec<_>>()
+        };
+        let results = if threads == rayon::current_num_threads() {
+            evaluate()
+        } else {
+            pool(threads)?.install(evaluate)
+        };
+
+        // the chunks are in order, therefore this is the error of the first failing point
+        results.into_iter().collect()
+    }
+}
+
+/// Evaluates `eval` at `points` without holding the GIL, see [`Points::evaluate_into`]. The
+/// result has the shape of `points` followed by `extra`, whose product is the number of values
+/// written by `eval` for each point.
+pub(crate) fn evaluate<'py, E>(
+    py: Python<'py>,
+    points: &Points<'_>,
+    extra: &[usize],
+    threads: Option<usize>,
+    eval: impl Fn(&[f64], &mut [f64]) -> Result<(), E> + Send + Sync,
+) -> Result<Bound<'py, PyArrayDyn<f64>>, Error>
+where
+    E: Send,
+    Error: From<E>,
+{
+    let shape = [points.shape(), extra].concat();
+    // SAFETY: the array is not visible to Python until all its elements have been written
+    let array = unsafe { PyArrayDyn::<f64>::new(py, shape, false) };
+    // SAFETY: the array has just been created, therefore it is contiguous and not shared
+    let values = unsafe { array.as_slice_mut() }
+        .unwrap_or_else(|_| unreachable!("new arrays are contiguous"));
+
+    py.detach(|| points.evaluate_into(values, None, threads, eval))?;
+    Ok(array)
+}
+
+/// Like [`evaluate`], but masking the points outside of the grid instead of failing. The result
+/// is a `numpy.ma.MaskedArray`.
+pub(crate) fn evaluate_masked<'py, E>(
+    py: Python<'py>,
+    points: &Points<'_>,
+    extra: &[usize],
+    threads: Option<usize>,
+    eval: impl Fn(&[f64], &mut [f64]) -> Result<(), E> + Send + Sync,
+) -> Result<Bound<'py, PyAny>, Error>
+where
+    E: Send,
+    Error: From<E>,
+{
+    let shape = [points.shape(), extra].concat();
+    // SAFETY: the array is not visible to Python until all its elements have been written
+    let array = unsafe { PyArrayDyn::<f64>::new(py, shape.as_slice(), false) };
+    let mask = PyArrayDyn::<bool>::zeros(py, shape, false);
+    // SAFETY: the arrays have just been created, therefore they are contiguous and not shared
+    let (values, flags) = unsafe { (array.as_slice_mut(), mask.as_slice_mut()) };
+    let values = values.unwrap_or_else(|_| unreachable!("new arrays are contiguous"));
+    let flags = flags.unwrap_or_else(|_| unreachable!("new arrays are contiguous"));
+
+    py.detach(|| points.evaluate_into(values, Some(flags), threads, eval))?;
+    let masked_array = py.import("numpy.ma")?.getattr("MaskedArray")?;
+    Ok(masked_array.call1((array, mask))?)
+}
+
+/// Adapts `eval`, which returns the single value of each point, to [`evaluate`]
+pub(crate) fn single<E>(
+    eval: impl Fn(&[f64]) -> Result<f64, E> + Send + Sync,
+) -> impl Fn(&[f64], &mut [f64]) -> Result<(), E> + Send + Sync {
+    move |query, values| {
+        values[0] = eval(query)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{array, Array, ArrayD, ShapeBuilder};
+    use ndinterp::interpolate::InterpolationError;
+
+    fn sum(query: &[f64], values: &mut [f64]) -> Result<(), Error> {
+        values[0] = query.iter().sum();
+        Ok(())
+    }
+
+    #[test]
+    fn check_broadcast_shape() {
+        let shape = |shapes: &[&[usize]]| broadcast_shape(shapes.iter().copied()).ok();
+
+        assert_eq!(shape(&[&[], &[]]), Some(vec![]));
+        assert_eq!(shape(&[&[3], &[]]), Some(vec![3]));
+        assert_eq!(shape(&[&[4, 1], &[3]]), Some(vec![4, 3]));
+        assert_eq!(shape(&[&[3], &[2, 1, 1]]), Some(vec![2, 1, 3]));
+        assert_eq!(shape(&[&[2, 0], &[1]]), Some(vec![2, 0]));
+        assert_eq!(shape(&[&[2, 3], &[3, 2]]), None);
+    }
+
+    #[test]
+    fn check_evaluate() {
+        let x1 = array![[1., 2., 3.]].into_dyn();
+        let x2 = array![[10.], [20.]].into_dyn();
+        let coordinates = [x1.view(), x2.view()];
+        let points = Points::from_coordinates(&coordinates).unwrap();
+        let mut values = [0.; 6];
+        points
+            .evaluate_into(&mut values, None, Some(1), sum)
+            .unwrap();
+        assert_eq!(points.shape(), &[2, 3]);
+        assert_eq!(values, [11., 12., 13., 21., 22., 23.]);
+
+        // column-major, therefore the coordinates of each point are not next to each other
+        let interleaved = Array::from_shape_vec((2, 1, 2).f(), vec![1., 2., 10., 20.])
+            .unwrap()
+            .into_dyn();
+        let view = interleaved.view();
+        let points = Points::from_points(&view, 2).unwrap();
+        let mut values = [0.; 2];
+        points.evaluate_into(&mut values, None, None, sum).unwrap();
+        assert_eq!(points.shape(), &[2, 1]);
+        assert_eq!(values, [11., 22.]);
+        assert!(Points::from_points(&view, 3).is_err());
+
+        let scalar = ArrayD::from_elem(IxDyn(&[]), 2.);
+        let coordinates = [scalar.view()];
+        let points = Points::from_coordinates(&coordinates).unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points.shape(), &[] as &[usize]);
+    }
+
+    #[test]
+    fn check_pools() {
+        let pool3 = pool(3).unwrap();
+        assert_eq!(pool3.current_num_threads(), 3);
+        assert!(Arc::ptr_eq(&pool3, &pool(3).unwrap()));
+        assert!(!Arc::ptr_eq(&pool3, &pool(4).unwrap()));
+    }
+
+    #[test]
+    fn check_threads() {
+        let x = Array::linspace(0., 1., 10_000)
+            .into_shape_with_order((100, 100))
+            .unwrap();
+        let half = ArrayD::from_elem(IxDyn(&[1]), 0.5);
+        let coordinates = [x.t().into_dyn(), half.view()];
+        let points = Points::from_coordinates(&coordinates).unwrap();
+
+        let eval = |query: &[f64], values: &mut [f64]| -> Result<(), Error> {
+            values[0] = query[0].sin() * query[1].exp();
+            values[1] = query[0].cos();
+            Ok(())
+        };
+        let mut expected = vec![f64::NAN; 20_000];
+        points
+            .evaluate_into(&mut expected, None, Some(1), eval)
+            .unwrap();
+        assert!(expected.iter().all(|value| !value.is_nan()));
+        for threads in [None, Some(2), Some(3), Some(7)] {
+            let mut values = vec![f64::NAN; 20_000];
+            points
+                .evaluate_into(&mut values, None, threads, eval)
+                .unwrap();
+            assert_eq!(values, expected);
+        }
+
+        let failing = |query: &[f64], values: &mut [f64]| {
+            if query[0] > 0.75 {
+                return Err(InterpolationError::ExtrapolationAbove(query[0]));
+            }
+            values[0] = query[0];
+            Ok(())
+        };
+        let mut values = vec![0.; 10_000];
+        let first = points.evaluate_into(&mut values, None, Some(1), failing);
+        for threads in [None, Some(2), Some(5)] {
+            let error = points.evaluate_into(&mut values, None, threads, failing);
+            assert_eq!(format!("{error:?}"), format!("{first:?}"));
+        }
+        assert!(points
+            .evaluate_into(&mut values, None, Some(0), failing)
+            .is_err());
+
+        let expected: Vec<_> = x.t().iter().map(|&x| x > 0.75).collect();
+        for threads in [Some(1), Some(3)] {
+            let mut mask = vec![false; 10_000];
+            points
+                .evaluate_into(&mut values, Some(&mut mask), threads, failing)
+                .unwrap();
+            assert_eq!(mask, expected);
+            assert!(values
+                .iter()
+                .zip(&mask)
+                .all(|(value, &masked)| value.is_nan() == masked));
+        }
+    }
+}
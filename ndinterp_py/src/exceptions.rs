@@ -0,0 +1,63 @@
+//! Exceptions raised by the interpolators
+//!
+//! The exceptions mirror the variants of [`ndinterp::interpolate::InterpolationError`], so that
+//! the callers can tell them apart without parsing the messages. They all derive from
+//! `InterpolationError`, which in turn derives from `ValueError`, the exception raised before
+//! they were introduced. The extrapolation errors carry the offending coordinate in `value` and
+//! the index of its axis in `axis`.
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+create_exception!(
+    ndinterp,
+    InterpolationError,
+    PyValueError,
+    "Base class of the errors raised by the interpolators."
+);
+create_exception!(
+    ndinterp,
+    ExtrapolationAbove,
+    InterpolationError,
+    "A query is above the last node of the grid along `axis`, with coordinate `value`."
+);
+create_exception!(
+    ndinterp,
+    ExtrapolationBelow,
+    InterpolationError,
+    "A query is below the first node of the grid along `axis`, with coordinate `value`."
+);
+create_exception!(
+    ndinterp,
+    InvalidGrid,
+    InterpolationError,
+    "The nodes or the values of the grid are not valid."
+);
+create_exception!(
+    ndinterp,
+    InvalidConfiguration,
+    InterpolationError,
+    "The interpolator is not configured to answer the query."
+);
+create_exception!(
+    ndinterp,
+    InvalidQuery,
+    InterpolationError,
+    "The query does not match the interpolator, e.g. it has the wrong number of coordinates."
+);
+
+/// Adds the exceptions to the module `m`
+pub(crate) fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("InterpolationError", py.get_type::<InterpolationError>())?;
+    m.add("ExtrapolationAbove", py.get_type::<ExtrapolationAbove>())?;
+    m.add("ExtrapolationBelow", py.get_type::<ExtrapolationBelow>())?;
+    m.add("InvalidGrid", py.get_type::<InvalidGrid>())?;
+    m.add(
+        "InvalidConfiguration",
+        py.get_type::<InvalidConfiguration>(),
+    )?;
+    m.add("InvalidQuery", py.get_type::<InvalidQuery>())?;
+    Ok(())
+}
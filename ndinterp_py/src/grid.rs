@@ -0,0 +1,276 @@
+//! Interpolators on grids
+//!
+//! The constructors accept anything convertible to NumPy arrays of floats, and the evaluation
+//! broadcasts over query arrays of any shape, returning a float for scalar queries and an array
+//! otherwise. The evaluation releases the GIL and is split across `threads` threads, by default
+//! as many as the available cores, see [`crate::eval`]. The interpolators can be pickled, and
+//! therefore sent to `multiprocessing` workers: they are stored as the arrays of their grid.
+//!
+//! Queries outside of the grid raise the exceptions of [`crate::exceptions`] or, with
+//! `masked=True`, are masked in the returned `numpy.ma.MaskedArray`.
+
+use crate::error::Error;
+use crate::eval::{evaluate, evaluate_masked, single, Points};
+use ndarray::{Ix1, Ix2, IxDyn};
+use ndinterp::grid::cubic::Cubic;
+use ndinterp::grid::Grid;
+use ndinterp::interpolate::{InterpolationError, Interpolator};
+use numpy::{
+    AllowTypeChange, PyArray1, PyArray2, PyArrayLike1, PyArrayLike2, PyArrayLikeDyn,
+    PyArrayMethods, PyUntypedArrayMethods, ToPyArray,
+};
+use pyo3::prelude::*;
+use pyo3::types::{PyFloat, PyType};
+
+type Array1<'py> = PyArrayLike1<'py, f64, AllowTypeChange>;
+type Array2<'py> = PyArrayLike2<'py, f64, AllowTypeChange>;
+type ArrayDyn<'py> = PyArrayLikeDyn<'py, f64, AllowTypeChange>;
+type Vector<'py> = Bound<'py, PyArray1<f64>>;
+
+/// The value returned by `__reduce__`: the class and the arguments of its constructor, which
+/// rebuild an equal interpolator when unpickled
+pub(crate) type Reduced<'py, A> = (Bound<'py, PyType>, A);
+
+/// Dimensions supported by [`CubicNd`]
+const SUPPORTED_DIMENSIONS: [usize; 2] = [1, 2];
+
+/// Evaluates `eval` at `points`, returning a float for scalar queries and an array otherwise,
+/// or a masked array if `masked`
+fn call<'py>(
+    py: Python<'py>,
+    points: &Points<'_>,
+    threads: Option<usize>,
+    masked: bool,
+    eval: impl Fn(&[f64]) -> Result<f64, Error> + Send + Sync,
+) -> Result<Bound<'py, PyAny>, Error> {
+    if masked {
+        return evaluate_masked(py, points, &[], threads, single(eval));
+    }
+    let values = evaluate(py, points, &[], threads, single(eval))?;
+    Ok(if values.ndim() == 0 {
+        PyFloat::new(py, values.get_owned(IxDyn(&[])).unwrap_or_default()).into_any()
+    } else {
+        values.into_any()
+    })
+}
+
+/// Cubic interpolation in one dimension
+///
+/// Parameters
+/// ----------
+/// x : array_like
+///     nodes of the grid, in strictly increasing order
+/// values : array_like
+///     values of the function at the nodes
+#[pyclass(module = "ndinterp", frozen)]
+pub struct Cubic1d(Cubic<1>);
+
+#[pymethods]
+impl Cubic1d {
+    #[new]
+    fn new(x: Array1<'_>, values: Array1<'_>) -> Result<Self, Error> {
+        let grid = Grid::new(vec![x.as_array().to_vec()], values.as_array().to_owned())?;
+        Ok(Self(Cubic { grid }))
+    }
+
+    /// Number of dimensions of the interpolator
+    #[getter]
+    fn ndim(&self) -> usize {
+        1
+    }
+
+    /// Pickles the interpolator as the arguments of its constructor
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> Reduced<'py, (Vector<'py>, Vector<'py>)> {
+        let py = slf.py();
+        let grid = &slf.get().0.grid;
+        let args = (grid.xgrid[0].to_pyarray(py), grid.values.to_pyarray(py));
+        (slf.get_type(), args)
+    }
+
+    /// Interpolates at `x`, an array of any shape, using `threads` threads. If `masked` the
+    /// queries outside of the grid are masked instead of raising an exception.
+    #[pyo3(signature = (x, *, threads = None, masked = false))]
+    fn __call__<'py>(
+        &self,
+        py: Python<'py>,
+        x: ArrayDyn<'py>,
+        threads: Option<usize>,
+        masked: bool,
+    ) -> Result<Bound<'py, PyAny>, Error> {
+        let coordinates = [x.as_array()];
+        let points = Points::from_coordinates(&coordinates)?;
+        call(py, &points, threads, masked, |query| {
+            Interpolator::<&[f64]>::interpolate(&self.0, query)
+                .map_err(|err| Error::locate(err, query, &self.0.grid.xgrid))
+        })
+    }
+}
+
+/// Cubic interpolation in two dimensions
+///
+/// Parameters
+/// ----------
+/// x1, x2 : array_like
+///     nodes of the grid along each axis, in strictly increasing order
+/// values : array_like
+///     values of the function at the nodes, with shape ``(len(x1), len(x2))``
+#[pyclass(module = "ndinterp", frozen)]
+pub struct Cubic2d(Cubic<2>);
+
+#[pymethods]
+impl Cubic2d {
+    #[new]
+    fn new(x1: Array1<'_>, x2: Array1<'_>, values: Array2<'_>) -> Result<Self, Error> {
+        let xgrid = vec![x1.as_array().to_vec(), x2.as_array().to_vec()];
+        let grid = Grid::new(xgrid, values.as_array().to_owned())?;
+        Ok(Self(Cubic { grid }))
+    }
+
+    /// Number of dimensions of the interpolator
+    #[getter]
+    fn ndim(&self) -> usize {
+        2
+    }
+
+    /// Pickles the interpolator as the arguments of its constructor
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> Reduced<'py, (Vector<'py>, Vector<'py>, Bound<'py, PyArray2<f64>>)> {
+        let py = slf.py();
+        let grid = &slf.get().0.grid;
+        let [x1, x2] = [0, 1].map(|axis| grid.xgrid[axis].to_pyarray(py));
+        (slf.get_type(), (x1, x2, grid.values.to_pyarray(py)))
+    }
+
+    /// Interpolates at the points with coordinates `x1` and `x2`, arrays which are broadcast
+    /// together, using `threads` threads. If `masked` the queries outside of the grid are masked
+    /// instead of raising an exception.
+    #[pyo3(signature = (x1, x2, *, threads = None, masked = false))]
+    fn __call__<'py>(
+        &self,
+        py: Python<'py>,
+        x1: ArrayDyn<'py>,
+        x2: ArrayDyn<'py>,
+        threads: Option<usize>,
+        masked: bool,
+    ) -> Result<Bound<'py, PyAny>, Error> {
+        let coordinates = [x1.as_array(), x2.as_array()];
+        let points = Points::from_coordinates(&coordinates)?;
+        call(py, &points, threads, masked, |query| {
+            self.0
+                .interpolate(query)
+                .map_err(|err| Error::locate(err, query, &self.0.grid.xgrid))
+        })
+    }
+}
+
+enum Kind {
+    D1(Cubic<1>),
+    D2(Cubic<2>),
+}
+
+/// Cubic interpolation in any supported number of dimensions
+///
+/// Parameters
+/// ----------
+/// axes : sequence of array_like
+///     nodes of the grid along each axis, in strictly increasing order
+/// values : array_like
+///     values of the function at the nodes, with one axis per element of `axes`
+#[pyclass(module = "ndinterp", frozen)]
+pub struct CubicNd(Kind);
+
+impl CubicNd {
+    fn interpolate(&self, query: &[f64]) -> Result<f64, Error> {
+        let (result, xgrid) = match &self.0 {
+            Kind::D1(cubic) => (cubic.interpolate(query), &cubic.grid.xgrid),
+            Kind::D2(cubic) => (cubic.interpolate(query), &cubic.grid.xgrid),
+        };
+        result.map_err(|err| Error::locate(err, query, xgrid))
+    }
+}
+
+#[pymethods]
+impl CubicNd {
+    #[new]
+    fn new(axes: Vec<Array1<'_>>, values: ArrayDyn<'_>) -> Result<Self, Error> {
+        let xgrid: Vec<_> = axes.iter().map(|axis| axis.as_array().to_vec()).collect();
+        let values = values.as_array();
+        if xgrid.len() != values.ndim() {
+            return Err(Error::Shape(format!(
+                "{} axes were given for values with {} dimensions",
+                xgrid.len(),
+                values.ndim()
+            )));
+        }
+        let invalid = |err: ndarray::ShapeError| InterpolationError::InvalidGrid(err.to_string());
+
+        Ok(Self(match xgrid.len() {
+            1 => {
+                let values = values.into_dimensionality::<Ix1>().map_err(invalid)?;
+                Kind::D1(Cubic {
+                    grid: Grid::new(xgrid, values.to_owned())?,
+                })
+            }
+            2 => {
+                let values = values.into_dimensionality::<Ix2>().map_err(invalid)?;
+                Kind::D2(Cubic {
+                    grid: Grid::new(xgrid, values.to_owned())?,
+                })
+            }
+            ndim => {
+                return Err(InterpolationError::InvalidGrid(format!(
+                    "{ndim}-dimensional cubic interpolation is not supported, the supported \
+                     dimensions are {SUPPORTED_DIMENSIONS:?}"
+                ))
+                .into())
+            }
+        }))
+    }
+
+    /// Number of dimensions of the interpolator
+    #[getter]
+    fn ndim(&self) -> usize {
+        match &self.0 {
+            Kind::D1(_) => 1,
+            Kind::D2(_) => 2,
+        }
+    }
+
+    /// Pickles the interpolator as the arguments of its constructor
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> Reduced<'py, (Vec<Vector<'py>>, Bound<'py, PyAny>)> {
+        let py = slf.py();
+        let (xgrid, values) = match &slf.get().0 {
+            Kind::D1(cubic) => (
+                &cubic.grid.xgrid,
+                cubic.grid.values.to_pyarray(py).into_any(),
+            ),
+            Kind::D2(cubic) => (
+                &cubic.grid.xgrid,
+                cubic.grid.values.to_pyarray(py).into_any(),
+            ),
+        };
+        let axes = xgrid.iter().map(|x| x.to_pyarray(py)).collect();
+        (slf.get_type(), (axes, values))
+    }
+
+    /// Interpolates at `points`, an array whose last axis runs over the coordinates of each
+    /// point, using `threads` threads. If `masked` the queries outside of the grid are masked
+    /// instead of raising an exception.
+    #[pyo3(signature = (points, *, threads = None, masked = false))]
+    fn __call__<'py>(
+        &self,
+        py: Python<'py>,
+        points: ArrayDyn<'py>,
+        threads: Option<usize>,
+        masked: bool,
+    ) -> Result<Bound<'py, PyAny>, Error> {
+        let points = points.as_array();
+        let points = Points::from_points(&points, self.ndim())?;
+        call(py, &points, threads, masked, |query| {
+            self.interpolate(query)
+        })
+    }
+}
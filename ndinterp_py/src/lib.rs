@@ -1,13 +1,28 @@
+//! Python interface for ndinterp
+#![warn(clippy::all, clippy::cargo)]
+#![allow(clippy::multiple_crate_versions)]
+
 use pyo3::prelude::*;
 
-pub mod scatter;
+mod error;
+mod eval;
+pub mod exceptions;
+pub mod grid;
+pub mod scipy;
 
 /// PyO3 Python module that contains all exposed classes from Rust.
 ///
 /// NOTE: this name has to match the one in Cargo.toml 'lib.name'
 #[pymodule]
-fn ndinterp(_py: Python, m: &PyModule) -> PyResult<()> {
-    m.add_class::<scatter::InvDistAll>()?;
+fn ndinterp(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    // fail at import time, rather than at the first conversion of an array, without NumPy
+    m.py().import("numpy")?;
+
+    exceptions::register(m)?;
+    m.add_class::<grid::Cubic1d>()?;
+    m.add_class::<grid::Cubic2d>()?;
+    m.add_class::<grid::CubicNd>()?;
+    m.add_class::<scipy::RegularGridInterpolator>()?;
     m.add("version", env!("CARGO_PKG_VERSION"))?;
 
     Ok(())
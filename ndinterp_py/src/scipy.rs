@@ -0,0 +1,378 @@
+//! Drop-in replacement for `scipy.interpolate.RegularGridInterpolator`
+//!
+//! [`RegularGridInterpolator`] accepts the arguments of SciPy, with the same names and defaults,
+//! and the same query shapes, so that it can replace the SciPy class without touching the call
+//! sites. The differences are:
+//!
+//! - only the methods `"linear"`, in up to six dimensions, and `"cubic"`, in up to two, are
+//!   available. The cubic method is the one of LHAPDF, which uses finite differences for the
+//!   derivatives at the nodes, instead of the cubic spline of SciPy;
+//! - `fill_value=None`, i.e. extrapolation, is not supported;
+//! - the attributes are read-only.
+
+use crate::error::{outside, Error};
+use crate::eval::{evaluate, evaluate_masked, Points};
+use crate::grid::Reduced;
+use ndarray::{ArrayD, ArrayViewD, Axis, IxDyn};
+use ndinterp::grid::cubic::Cubic;
+use ndinterp::grid::linear::Linear;
+use ndinterp::grid::{DimensionHelper, GridView, ToDimension};
+use ndinterp::interpolate::{InterpolationError, Interpolator};
+use numpy::{
+    AllowTypeChange, PyArrayDyn, PyArrayLike1, PyArrayLikeDyn, PyUntypedArrayMethods, ToPyArray,
+};
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+
+type Array1<'py> = PyArrayLike1<'py, f64, AllowTypeChange>;
+type ArrayDyn<'py> = PyArrayLikeDyn<'py, f64, AllowTypeChange>;
+
+/// The arguments of the constructor of [`RegularGridInterpolator`], used to pickle it
+type Arguments<'py> = (
+    Bound<'py, PyTuple>,
+    Bound<'py, PyArrayDyn<f64>>,
+    &'static str,
+    bool,
+    Option<f64>,
+);
+
+/// An interpolator on one component of the values
+type DynInterpolator<'a> = Box<dyn for<'b> Interpolator<&'b [f64]> + Sync + 'a>;
+
+/// Interpolation methods available to [`RegularGridInterpolator`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Method {
+    Linear,
+    Cubic,
+}
+
+impl Method {
+    fn parse(name: &str) -> Result<Self, Error> {
+        match name {
+            "linear" => Ok(Self::Linear),
+            "cubic" => Ok(Self::Cubic),
+            _ => Err(Error::Argument(format!("Method '{name}' is not defined"))),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Cubic => "cubic",
+        }
+    }
+}
+
+/// Builds the interpolator created by `new` on the grid with input vectors `xgrid` and `values`
+fn build<'a, const D: usize, I>(
+    new: impl FnOnce(GridView<'a, D>) -> I,
+    xgrid: &[Vec<f64>],
+    values: ArrayViewD<'a, f64>,
+) -> Result<DynInterpolator<'a>, Error>
+where
+    DimensionHelper<D>: ToDimension,
+    I: for<'b> Interpolator<&'b [f64]> + Sync + 'a,
+{
+    let values = values
+        .into_dimensionality()
+        .map_err(|err| InterpolationError::InvalidGrid(err.to_string()))?;
+    Ok(Box::new(new(GridView::new(xgrid.to_vec(), values)?)))
+}
+
+/// The points at which the interpolator is queried
+enum Query<'py> {
+    /// One array per dimension, broadcast together
+    Coordinates(Vec<ArrayDyn<'py>>),
+    /// A single array whose last axis runs over the dimensions, or a flat array of points
+    Points(ArrayDyn<'py>),
+}
+
+impl<'py> Query<'py> {
+    /// Interprets `xi` as SciPy does, checking that its points have `ndim` dimensions
+    fn new(xi: &Bound<'py, PyAny>, ndim: usize) -> Result<Self, Error> {
+        let mismatch = |found| {
+            Error::Shape(format!(
+                "The requested sample points xi have dimension {found}, but this \
+                 RegularGridInterpolator has dimension {ndim}"
+            ))
+        };
+
+        let mut xi = xi.clone();
+        if let Ok(tuple) = xi.cast::<PyTuple>() {
+            if tuple.len() != 1 {
+                if tuple.len() != ndim {
+                    return Err(mismatch(tuple.len()));
+                }
+                let coordinates = tuple.iter().map(|x| x.extract()).collect::<PyResult<_>>()?;
+                return Ok(Self::Coordinates(coordinates));
+            }
+            xi = tuple.get_item(0)?;
+        }
+
+        let points: ArrayDyn<'py> = xi.extract()?;
+        match points.shape() {
+            [] => return Err(mismatch(0)),
+            &[len] if len % ndim != 0 => {
+                return Err(Error::Shape(format!(
+                    "cannot reshape array of size {len} into points of dimension {ndim}"
+                )))
+            }
+            [_] => {}
+            [.., last] if *last != ndim => return Err(mismatch(*last)),
+            _ => {}
+        }
+        Ok(Self::Points(points))
+    }
+
+    /// Calls `f` with the points of the query, which have `ndim` dimensions
+    fn with_points<T>(
+        &self,
+        ndim: usize,
+        f: impl FnOnce(&Points<'_>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        match self {
+            Self::Coordinates(coordinates) => {
+                let coordinates: Vec<_> = coordinates.iter().map(|x| x.as_array()).collect();
+                f(&Points::from_coordinates(&coordinates)?)
+            }
+            Self::Points(points) => {
+                let points = points.as_array();
+                if let &[len] = points.shape() {
+                    let points = points.as_standard_layout();
+                    let points = points
+                        .view()
+                        .into_shape_with_order(IxDyn(&[len / ndim, ndim]))
+                        .unwrap_or_else(|_| unreachable!("the length is checked by `Query::new`"));
+                    f(&Points::from_points(&points, ndim)?)
+                } else {
+                    f(&Points::from_points(&points, ndim)?)
+                }
+            }
+        }
+    }
+}
+
+/// Interpolator on a regular or rectilinear grid in arbitrary dimensions, compatible with
+/// ``scipy.interpolate.RegularGridInterpolator``
+///
+/// Parameters
+/// ----------
+/// points : tuple of array_like
+///     nodes of the grid along each axis, strictly ascending or descending
+/// values : array_like
+///     values of the function at the nodes, with shape ``(m1, ..., mn, ...)``: the axes beyond the
+///     grid ones are interpolated independently
+/// method : str, optional
+///     either ``"linear"``, the default, or ``"cubic"``, which is the cubic interpolation of LHAPDF
+/// bounds_error : bool, optional
+///     whether to raise a ``ValueError`` for queries outside of the grid, the default, or to
+///     return `fill_value`
+/// fill_value : float, optional
+///     the value returned outside of the grid if `bounds_error` is false, by default NaN.
+///     Extrapolation, requested by ``None``, is not supported
+#[pyclass(module = "ndinterp", frozen)]
+pub struct RegularGridInterpolator {
+    xgrid: Vec<Vec<f64>>,
+    /// Values in standard layout, with the grid axes first
+    values: ArrayD<f64>,
+    method: Method,
+    bounds_error: bool,
+    fill_value: Option<f64>,
+}
+
+impl RegularGridInterpolator {
+    fn ndim(&self) -> usize {
+        self.xgrid.len()
+    }
+
+    /// One interpolator for each component of the values, i.e. for each combination of the
+    /// indices along the axes beyond the grid ones
+    fn interpolators(&self, method: Method) -> Result<Vec<DynInterpolator<'_>>, Error> {
+        let ndim = self.ndim();
+        let mut shape = self.values.shape()[..ndim].to_vec();
+        let components = self.values.shape()[ndim..].iter().product();
+        shape.push(components);
+        let values = self
+            .values
+            .view()
+            .into_shape_with_order(shape)
+            .unwrap_or_else(|_| unreachable!("the values are stored in standard layout"));
+
+        (0..components)
+            .map(|component| {
+                let xgrid = &self.xgrid;
+                let values = values.clone().index_axis_move(Axis(ndim), component);
+                match (method, ndim) {
+                    (Method::Linear, 1) => build::<1, _>(|grid| Linear { grid }, xgrid, values),
+                    (Method::Linear, 2) => build::<2, _>(|grid| Linear { grid }, xgrid, values),
+                    (Method::Linear, 3) => build::<3, _>(|grid| Linear { grid }, xgrid, values),
+                    (Method::Linear, 4) => build::<4, _>(|grid| Linear { grid }, xgrid, values),
+                    (Method::Linear, 5) => build::<5, _>(|grid| Linear { grid }, xgrid, values),
+                    (Method::Linear, 6) => build::<6, _>(|grid| Linear { grid }, xgrid, values),
+                    (Method::Cubic, 1) => build::<1, _>(|grid| Cubic { grid }, xgrid, values),
+                    (Method::Cubic, 2) => build::<2, _>(|grid| Cubic { grid }, xgrid, values),
+                    (method, _) => Err(InterpolationError::InvalidGrid(format!(
+                        "{ndim}-dimensional interpolation is not supported by the method '{}'",
+                        method.name()
+                    ))
+                    .into()),
+                }
+            })
+            .collect()
+    }
+
+    /// Interpolates one component of the values at `query`, applying the bounds policy
+    fn interpolate(&self, interpolator: &DynInterpolator<'_>, query: &[f64]) -> Result<f64, Error> {
+        if query.iter().any(|x| x.is_nan()) {
+            return Ok(f64::NAN);
+        }
+        if let Some((axis, error)) = outside(query, &self.xgrid) {
+            return match self.fill_value.filter(|_| !self.bounds_error) {
+                Some(fill_value) => Ok(fill_value),
+                None => Err(Error::OutOfBounds { error, axis }),
+            };
+        }
+        Ok(interpolator.interpolate(query)?)
+    }
+}
+
+#[pymethods]
+impl RegularGridInterpolator {
+    #[new]
+    #[pyo3(signature = (points, values, method = "linear", bounds_error = true, fill_value = Some(f64::NAN)))]
+    fn new(
+        points: Vec<Array1<'_>>,
+        values: ArrayDyn<'_>,
+        method: &str,
+        bounds_error: bool,
+        fill_value: Option<f64>,
+    ) -> Result<Self, Error> {
+        let method = Method::parse(method)?;
+        if !bounds_error && fill_value.is_none() {
+            return Err(Error::Argument(
+                "extrapolation with fill_value=None is not supported".to_owned(),
+            ));
+        }
+
+        let mut values = values.as_array().to_owned();
+        if points.is_empty() {
+            return Err(Error::Shape("There are no point arrays".to_owned()));
+        }
+        if points.len() > values.ndim() {
+            return Err(Error::Shape(format!(
+                "There are {} point arrays, but values has {} dimensions",
+                points.len(),
+                values.ndim()
+            )));
+        }
+
+        let mut xgrid = Vec::with_capacity(points.len());
+        for (axis, x) in points.iter().enumerate() {
+            let mut x = x.as_array().to_vec();
+            if x.len() != values.len_of(Axis(axis)) {
+                return Err(Error::Shape(format!(
+                    "There are {} points and {} values in dimension {axis}",
+                    x.len(),
+                    values.len_of(Axis(axis))
+                )));
+            }
+            // descending axes are flipped, as done by SciPy
+            if x.len() > 1 && x.windows(2).all(|pair| pair[0] > pair[1]) {
+                x.reverse();
+                values.invert_axis(Axis(axis));
+            }
+            xgrid.push(x);
+        }
+
+        let interpolator = Self {
+            xgrid,
+            values: values.as_standard_layout().into_owned(),
+            method,
+            bounds_error,
+            fill_value,
+        };
+        // check the grid once, rather than at every query
+        interpolator.interpolators(method)?;
+
+        Ok(interpolator)
+    }
+
+    /// Nodes of the grid along each axis, in ascending order
+    #[getter]
+    fn grid<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyTuple>> {
+        PyTuple::new(py, self.xgrid.iter().map(|x| x.to_pyarray(py)))
+    }
+
+    /// Values of the function at the nodes
+    #[getter]
+    fn values<'py>(&self, py: Python<'py>) -> Bound<'py, PyArrayDyn<f64>> {
+        self.values.to_pyarray(py)
+    }
+
+    /// Interpolation method
+    #[getter]
+    fn method(&self) -> &'static str {
+        self.method.name()
+    }
+
+    /// Whether queries outside of the grid raise a ``ValueError``
+    #[getter]
+    fn bounds_error(&self) -> bool {
+        self.bounds_error
+    }
+
+    /// Value returned for queries outside of the grid, unless `bounds_error` is true
+    #[getter]
+    fn fill_value(&self) -> Option<f64> {
+        self.fill_value
+    }
+
+    /// Pickles the interpolator as the arguments of its constructor
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> PyResult<Reduced<'py, Arguments<'py>>> {
+        let interp = slf.get();
+        let args = (
+            interp.grid(slf.py())?,
+            interp.values(slf.py()),
+            interp.method(),
+            interp.bounds_error,
+            interp.fill_value,
+        );
+        Ok((slf.get_type(), args))
+    }
+
+    /// Interpolates at `xi`, either an array of shape ``(..., ndim)`` or a tuple of ``ndim``
+    /// arrays broadcast together, using `method` if given instead of the one of the constructor.
+    /// The evaluation releases the GIL and is split across `threads` threads, by default as many
+    /// as the available cores. If `masked` the queries outside of the grid are masked in the
+    /// returned ``numpy.ma.MaskedArray`` instead of raising an exception.
+    #[pyo3(signature = (xi, method = None, *, threads = None, masked = false))]
+    fn __call__<'py>(
+        &self,
+        py: Python<'py>,
+        xi: &Bound<'py, PyAny>,
+        method: Option<&str>,
+        threads: Option<usize>,
+        masked: bool,
+    ) -> Result<Bound<'py, PyAny>, Error> {
+        let ndim = self.ndim();
+        let method = method.map_or(Ok(self.method), Method::parse)?;
+        let interpolators = self.interpolators(method)?;
+        let query = Query::new(xi, ndim)?;
+        let extra = &self.values.shape()[ndim..];
+
+        // the components of each point are next to each other, in the order of `interpolators`
+        let eval = |query: &[f64], values: &mut [f64]| {
+            for (value, interpolator) in values.iter_mut().zip(&interpolators) {
+                *value = self.interpolate(interpolator, query)?;
+            }
+            Ok::<_, Error>(())
+        };
+        query.with_points(ndim, |points| {
+            if masked {
+                evaluate_masked(py, points, extra, threads, eval)
+            } else {
+                Ok(evaluate(py, points, extra, threads, eval)?.into_any())
+            }
+        })
+    }
+}
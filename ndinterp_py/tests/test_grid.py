@@ -0,0 +1,164 @@
+import pickle
+
+import numpy as np
+import pytest
+
+import ndinterp
+
+
+def cubic_1d():
+    x = np.linspace(0.0, 3.0, 7)
+    return ndinterp.Cubic1d(x, x**2)
+
+
+def cubic_2d():
+    x1 = np.linspace(0.0, 2.0, 5)
+    x2 = np.linspace(-1.0, 1.0, 4)
+    values = x1[:, np.newaxis] + 2.0 * x2[np.newaxis, :]
+    return x1, x2, values
+
+
+def test_cubic_1d_nodes():
+    cubic = cubic_1d()
+    x = np.linspace(0.0, 3.0, 7)
+    np.testing.assert_allclose(cubic(x), x**2)
+    assert cubic.ndim == 1
+
+
+def test_cubic_1d_shapes():
+    cubic = cubic_1d()
+    assert isinstance(cubic(1.5), float)
+    assert cubic(1.5) == pytest.approx(2.25, rel=1e-2)
+    assert cubic([0.5, 1.0]).shape == (2,)
+    assert cubic(np.full((3, 4, 2), 1.0)).shape == (3, 4, 2)
+    assert cubic(np.empty((0, 3))).shape == (0, 3)
+
+
+def test_cubic_1d_integers():
+    cubic = ndinterp.Cubic1d([0, 1, 2, 3], [0, 1, 4, 9])
+    assert cubic(2) == 4.0
+
+
+def test_cubic_2d_broadcast():
+    x1, x2, values = cubic_2d()
+    cubic = ndinterp.Cubic2d(x1, x2, values)
+    # the function is linear, therefore reproduced exactly
+    q1 = np.array([0.1, 0.7, 1.9])
+    q2 = np.array([[-0.5], [0.5]])
+    np.testing.assert_allclose(cubic(q1, q2), q1 + 2.0 * q2)
+    assert cubic(q1, q2).shape == (2, 3)
+    np.testing.assert_allclose(cubic(q1, 0.25), q1 + 0.5)
+
+    with pytest.raises(ValueError, match="broadcast"):
+        cubic(np.zeros(3), np.zeros(2))
+
+
+def test_cubic_nd():
+    x1, x2, values = cubic_2d()
+    cubic = ndinterp.CubicNd([x1, x2], values)
+    reference = ndinterp.Cubic2d(x1, x2, values)
+    assert cubic.ndim == 2
+
+    points = np.array([[[0.1, -0.5], [1.3, 0.2]], [[1.9, 0.9], [0.0, 0.0]]])
+    np.testing.assert_array_equal(cubic(points), reference(points[..., 0], points[..., 1]))
+    assert isinstance(cubic([0.5, 0.5]), float)
+
+    with pytest.raises(ValueError, match="last axis"):
+        cubic(np.zeros((4, 3)))
+    with pytest.raises(ValueError, match="not supported"):
+        ndinterp.CubicNd([x1, x1, x1], np.zeros((5, 5, 5)))
+    with pytest.raises(ValueError, match="axes"):
+        ndinterp.CubicNd([x1], values)
+
+
+def test_errors():
+    cubic = cubic_1d()
+    with pytest.raises(ValueError, match="above"):
+        cubic([1.0, 4.0])
+    with pytest.raises(ValueError, match="below"):
+        cubic(-1.0)
+    with pytest.raises(ValueError, match="Invalid grid"):
+        ndinterp.Cubic1d([0.0, 2.0, 1.0], [0.0, 1.0, 2.0])
+
+
+def test_threads():
+    x1, x2, values = cubic_2d()
+    cubic = ndinterp.Cubic2d(x1, x2, values)
+    rng = np.random.default_rng(7)
+    q1 = rng.uniform(0.0, 2.0, (50, 400))
+    q2 = rng.uniform(-1.0, 1.0, (50, 400))
+
+    expected = cubic(q1, q2, threads=1)
+    for threads in [None, 2, 3, 8]:
+        np.testing.assert_array_equal(cubic(q1, q2, threads=threads), expected)
+
+    # the first failing point is reported whatever the number of threads
+    q1[40, 7] = 5.0
+    q1[10, 3] = 3.0
+    for threads in [1, 4]:
+        with pytest.raises(ValueError, match=r"\(3\)"):
+            cubic(q1, q2, threads=threads)
+    with pytest.raises(ValueError, match="positive"):
+        cubic(0.5, 0.5, threads=0)
+
+
+def test_pickle():
+    x1, x2, values = cubic_2d()
+    rng = np.random.default_rng(11)
+    q1 = rng.uniform(0.0, 2.0, 20)
+    q2 = rng.uniform(-1.0, 1.0, 20)
+
+    cubic = cubic_1d()
+    restored = pickle.loads(pickle.dumps(cubic))
+    assert type(restored) is ndinterp.Cubic1d
+    np.testing.assert_array_equal(restored(q1), cubic(q1))
+
+    cubic = ndinterp.Cubic2d(x1, x2, values)
+    restored = pickle.loads(pickle.dumps(cubic))
+    np.testing.assert_array_equal(restored(q1, q2), cubic(q1, q2))
+
+    cubic = ndinterp.CubicNd([x1, x2], values)
+    restored = pickle.loads(pickle.dumps(cubic))
+    points = np.stack([q1, q2], axis=-1)
+    assert restored.ndim == 2
+    np.testing.assert_array_equal(restored(points), cubic(points))
+
+
+def test_exceptions():
+    x1, x2, values = cubic_2d()
+    cubic = ndinterp.Cubic2d(x1, x2, values)
+    with pytest.raises(ndinterp.ExtrapolationBelow) as info:
+        cubic([0.5, 1.0], [0.0, -3.0])
+    assert info.value.value == -3.0
+    assert info.value.axis == 1
+    assert isinstance(info.value, ndinterp.InterpolationError)
+    assert isinstance(info.value, ValueError)
+
+    cubic = ndinterp.CubicNd([x1, x2], values)
+    with pytest.raises(ndinterp.ExtrapolationAbove) as info:
+        cubic([[0.5, 0.0], [2.5, 2.0]])
+    assert (info.value.value, info.value.axis) == (2.5, 0)
+
+    with pytest.raises(ndinterp.InvalidGrid):
+        ndinterp.Cubic1d([0.0, 0.0, 1.0], [0.0, 1.0, 2.0])
+    # invalid arguments are not interpolation errors
+    with pytest.raises(ValueError) as info:
+        cubic(np.zeros((4, 3)))
+    assert not isinstance(info.value, ndinterp.InterpolationError)
+
+
+def test_masked():
+    cubic = cubic_1d()
+    x = np.array([[-1.0, 0.5], [1.5, 4.0]])
+    result = cubic(x, masked=True)
+    assert isinstance(result, np.ma.MaskedArray)
+    np.testing.assert_array_equal(result.mask, [[True, False], [False, True]])
+    np.testing.assert_array_equal(result.compressed(), cubic(np.array([0.5, 1.5])))
+    assert not cubic(0.5, masked=True).mask
+
+    x1, x2, values = cubic_2d()
+    cubic = ndinterp.Cubic2d(x1, x2, values)
+    q1 = np.linspace(-1.0, 3.0, 5000)
+    for threads in [1, 4]:
+        result = cubic(q1, 0.0, threads=threads, masked=True)
+        np.testing.assert_array_equal(result.mask, (q1 < 0.0) | (q1 > 2.0))
@@ -0,0 +1,154 @@
+import pickle
+
+import numpy as np
+import pytest
+
+import ndinterp
+from ndinterp import RegularGridInterpolator
+
+
+def grid_3d():
+    x = np.linspace(0.0, 1.0, 5)
+    y = np.linspace(-1.0, 1.0, 7)
+    z = np.array([0.0, 0.5, 2.0, 3.0])
+    xx, yy, zz = np.meshgrid(x, y, z, indexing="ij")
+    # multilinear functions are reproduced exactly by the linear method
+    return (x, y, z), lambda x, y, z: 1.0 + x - 2.0 * y + 0.5 * z + x * y * z, (xx, yy, zz)
+
+
+def test_linear_shapes():
+    points, f, nodes = grid_3d()
+    interp = RegularGridInterpolator(points, f(*nodes))
+    assert interp.method == "linear"
+    assert interp.bounds_error
+    assert np.isnan(interp.fill_value)
+
+    xi = np.array([[0.1, 0.2, 0.3], [0.9, -0.7, 2.5]])
+    np.testing.assert_allclose(interp(xi), f(*xi.T))
+    assert interp(xi).shape == (2,)
+    assert interp(xi[0]).shape == (1,)
+    assert interp(np.zeros((4, 5, 3))).shape == (4, 5)
+
+    # a tuple of coordinate arrays is broadcast
+    x = np.array([0.1, 0.5, 0.9])
+    np.testing.assert_allclose(interp((x, 0.25, 1.0)), f(x, 0.25, 1.0))
+    assert interp((0.5, 0.5, 0.5)).shape == ()
+
+    with pytest.raises(ValueError, match="have dimension 2"):
+        interp(np.zeros((3, 2)))
+
+
+def test_bounds():
+    points, f, nodes = grid_3d()
+    interp = RegularGridInterpolator(points, f(*nodes))
+    with pytest.raises(ValueError, match="out of bounds in dimension 2"):
+        interp([0.5, 0.5, 3.5])
+
+    interp = RegularGridInterpolator(points, f(*nodes), bounds_error=False)
+    values = interp([[0.5, 0.5, 3.5], [0.5, 0.5, 1.0]])
+    assert np.isnan(values[0]) and not np.isnan(values[1])
+
+    interp = RegularGridInterpolator(points, f(*nodes), bounds_error=False, fill_value=-1.0)
+    assert interp([0.5, 2.0, 1.0])[0] == -1.0
+    assert np.isnan(interp([np.nan, 0.0, 1.0])[0])
+
+    with pytest.raises(ValueError, match="fill_value=None"):
+        RegularGridInterpolator(points, f(*nodes), bounds_error=False, fill_value=None)
+
+
+def test_arguments():
+    points, f, nodes = grid_3d()
+    values = f(*nodes)
+    with pytest.raises(ValueError, match="Method 'quadratic' is not defined"):
+        RegularGridInterpolator(points, values, method="quadratic")
+    with pytest.raises(ValueError, match="point arrays"):
+        RegularGridInterpolator(points + (points[0],), values)
+    with pytest.raises(ValueError, match="There are 5 points and 7 values in dimension 1"):
+        RegularGridInterpolator((points[0], points[0], points[2]), values)
+    with pytest.raises(ValueError, match="not supported"):
+        RegularGridInterpolator(points, values, method="cubic")
+
+
+def test_descending_and_trailing_axes():
+    points, f, nodes = grid_3d()
+    values = f(*nodes)
+    reference = RegularGridInterpolator(points, values)
+
+    flipped = RegularGridInterpolator((points[0], points[1][::-1], points[2]), values[:, ::-1, :])
+    np.testing.assert_array_equal(flipped.grid[1], points[1])
+    xi = np.array([[0.1, 0.2, 0.3], [0.9, -0.7, 2.5]])
+    np.testing.assert_allclose(flipped(xi), reference(xi))
+
+    vector = RegularGridInterpolator(points, np.stack([values, 2.0 * values], axis=-1))
+    assert vector(xi).shape == (2, 2)
+    np.testing.assert_allclose(vector(xi)[:, 1], 2.0 * reference(xi))
+
+
+def test_cubic():
+    x = np.linspace(0.0, 1.0, 11)
+    y = np.linspace(0.0, 2.0, 21)
+    values = np.sin(x)[:, np.newaxis] * np.cos(y)[np.newaxis, :]
+    interp = RegularGridInterpolator((x, y), values, method="cubic")
+    xi = np.array([[0.33, 1.21], [0.5, 0.5]])
+    np.testing.assert_allclose(interp(xi), np.sin(xi[:, 0]) * np.cos(xi[:, 1]), atol=1e-3)
+
+    # the method can be changed at each call
+    linear = RegularGridInterpolator((x, y), values)
+    np.testing.assert_array_equal(interp(xi, method="linear"), linear(xi))
+
+
+def test_scipy():
+    interpolate = pytest.importorskip("scipy.interpolate")
+    points, f, nodes = grid_3d()
+    values = f(*nodes) + np.cos(nodes[0] * nodes[2])
+    rng = np.random.default_rng(42)
+    xi = rng.uniform([0.0, -1.0, 0.0], [1.0, 1.0, 3.0], (20, 2, 3))
+
+    ours = RegularGridInterpolator(points, values)
+    theirs = interpolate.RegularGridInterpolator(points, values)
+    np.testing.assert_allclose(ours(xi), theirs(xi), rtol=1e-13)
+
+
+def test_threads():
+    points, f, nodes = grid_3d()
+    values = np.stack([f(*nodes), np.cos(nodes[0] * nodes[2])], axis=-1)
+    interp = RegularGridInterpolator(points, values, bounds_error=False)
+    rng = np.random.default_rng(3)
+    xi = rng.uniform([0.0, -1.0, 0.0], [1.2, 1.0, 3.0], (100, 50, 3))
+
+    expected = interp(xi, threads=1)
+    assert expected.shape == (100, 50, 2)
+    assert np.isnan(expected).any()
+    for threads in [None, 2, 5]:
+        np.testing.assert_array_equal(interp(xi, threads=threads), expected)
+
+
+def test_pickle():
+    points, f, nodes = grid_3d()
+    interp = RegularGridInterpolator(
+        (points[0][::-1], points[1], points[2]),
+        f(*nodes)[::-1],
+        method="linear",
+        bounds_error=False,
+        fill_value=-1.0,
+    )
+    restored = pickle.loads(pickle.dumps(interp))
+    assert restored.method == "linear"
+    assert not restored.bounds_error
+    assert restored.fill_value == -1.0
+    for axis, expected in zip(restored.grid, points):
+        np.testing.assert_array_equal(axis, expected)
+    xi = np.array([[0.1, 0.2, 0.3], [2.0, 0.0, 0.0]])
+    np.testing.assert_array_equal(restored(xi), interp(xi))
+
+
+def test_exceptions():
+    points, f, nodes = grid_3d()
+    interp = RegularGridInterpolator(points, f(*nodes))
+    with pytest.raises(ndinterp.ExtrapolationAbove, match="out of bounds in dimension 2") as info:
+        interp([[0.5, 0.0, 1.0], [0.5, 0.0, 3.5]])
+    assert (info.value.value, info.value.axis) == (3.5, 2)
+
+    result = interp([[0.5, 0.0, 1.0], [0.5, 0.0, 3.5]], masked=True)
+    np.testing.assert_array_equal(result.mask, [False, True])
+    assert result[0] == interp([0.5, 0.0, 1.0])[0]